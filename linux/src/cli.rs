@@ -0,0 +1,621 @@
+// Headless command line interface for Mosaic Launcher
+
+use anyhow::{Result, anyhow};
+use log::{info, error};
+use std::collections::HashMap;
+use std::io::Write;
+use std::rc::Rc;
+use uuid::Uuid;
+
+use crate::config::{Config, Profile, ModLoader, LaunchSettings, ProxySettings, save_config};
+use crate::desktop;
+use crate::links;
+use crate::file_manager::{FileManager, CancellationToken};
+use crate::progress::InstallProgress;
+use crate::utils::format_file_size;
+use crate::games::minecraft::MinecraftManager;
+use crate::games::minecraft::auth::{AuthManager, AuthSession};
+use crate::games::minecraft::ui::mods::MinecraftModManager;
+
+/// Exit code for a command that completed successfully
+pub const EXIT_SUCCESS: i32 = 0;
+/// Exit code for a command that failed while running
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code for invalid command line usage
+pub const EXIT_USAGE: i32 = 2;
+
+const USAGE: &str = "Usage: mosaic-launcher [COMMAND]
+       mosaic-launcher --launch <profile-id>
+       mosaic-launcher mosaic://<link>
+
+Without a command the graphical launcher is started. With --launch the profile
+is started right away using the stored login, falling back to the launcher
+window when there is no usable login. mosaic:// links (as opened from mod
+websites) are confirmed and installed in the launcher window.
+
+Commands:
+  launch --profile <name|id> [--offline <username>]
+                                  Install if needed and launch a profile
+  install <version>               Install a Minecraft version
+  verify <name|id>                Check a profile's files and download damaged ones again
+  cleanup [--dry-run]             Remove versions, libraries and assets no profile uses
+  profiles list                   List the profiles of the selected game
+  profiles create <name> --version <version> [--loader <forge|fabric|quilt|neoforge>]
+                  [--loader-version <version>] [--memory <MB>]
+                                  Create a profile
+  profiles delete <name|id>       Delete a profile
+  mods list --profile <name|id>   List installed mods
+  help                            Show this message";
+
+/// A headless command parsed from the command line
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliCommand {
+    Launch {
+        profile: String,
+        offline_username: Option<String>,
+    },
+    Install {
+        version: String,
+    },
+    Verify {
+        profile: String,
+    },
+    Cleanup {
+        dry_run: bool,
+    },
+    ProfilesList,
+    ProfilesCreate {
+        name: String,
+        version: String,
+        mod_loader: ModLoader,
+        mod_loader_version: Option<String>,
+        memory: Option<u32>,
+    },
+    ProfilesDelete {
+        profile: String,
+    },
+    ModsList {
+        profile: String,
+    },
+    /// `--launch <profile-id>`, used by the generated desktop entries
+    QuickLaunch {
+        profile: String,
+    },
+    /// A `mosaic://` link, handled by the graphical launcher
+    OpenLink {
+        url: String,
+    },
+    Help,
+}
+
+/// Options that take no value, they are stored with an empty one
+const FLAGS: &[&str] = &["dry-run"];
+
+/// Splits arguments into positionals and `--option value` pairs
+fn split_options(args: &[String]) -> Result<(Vec<String>, HashMap<String, String>)> {
+    let mut positionals = Vec::new();
+    let mut options = HashMap::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if let Some(name) = arg.strip_prefix("--") {
+            if FLAGS.contains(&name) {
+                options.insert(name.to_string(), String::new());
+                continue;
+            }
+
+            // Accept both --option value and --option=value
+            let (name, value) = match name.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => {
+                    let value = iter.next()
+                        .ok_or_else(|| anyhow!("Missing value for --{}", name))?;
+                    (name.to_string(), value.clone())
+                }
+            };
+            options.insert(name, value);
+        } else {
+            positionals.push(arg.clone());
+        }
+    }
+
+    Ok((positionals, options))
+}
+
+/// Fails if any option outside `allowed` was given
+fn check_options(options: &HashMap<String, String>, allowed: &[&str]) -> Result<()> {
+    for name in options.keys() {
+        if !allowed.contains(&name.as_str()) {
+            return Err(anyhow!("Unknown option --{}", name));
+        }
+    }
+    Ok(())
+}
+
+/// Parses the command line (without the program name)
+///
+/// Returns `None` when no headless command was given and the GUI should start.
+pub fn parse_args(args: &[String]) -> Result<Option<CliCommand>> {
+    let command = match args.first() {
+        Some(command) => command.as_str(),
+        None => return Ok(None),
+    };
+
+    // Links are passed on their own by the desktop's URL handler
+    if links::is_mosaic_link(command) {
+        links::parse_link(command)?;
+        return Ok(Some(CliCommand::OpenLink { url: command.to_string() }));
+    }
+
+    let (positionals, mut options) = split_options(&args[1..])?;
+
+    let command = match (command, positionals.first().map(|s| s.as_str())) {
+        ("help" | "--help" | "-h", _) => CliCommand::Help,
+        ("--launch", _) => {
+            check_options(&options, &[])?;
+            CliCommand::QuickLaunch {
+                profile: positionals.first().cloned()
+                    .ok_or_else(|| anyhow!("--launch requires a profile id"))?,
+            }
+        },
+        ("launch", _) => {
+            check_options(&options, &["profile", "offline"])?;
+            CliCommand::Launch {
+                profile: options.remove("profile")
+                    .or_else(|| positionals.first().cloned())
+                    .ok_or_else(|| anyhow!("launch requires --profile <name|id>"))?,
+                offline_username: options.remove("offline"),
+            }
+        },
+        ("install", _) => {
+            check_options(&options, &[])?;
+            CliCommand::Install {
+                version: positionals.first().cloned()
+                    .ok_or_else(|| anyhow!("install requires a version"))?,
+            }
+        },
+        ("verify", _) => {
+            check_options(&options, &["profile"])?;
+            CliCommand::Verify {
+                profile: options.remove("profile")
+                    .or_else(|| positionals.first().cloned())
+                    .ok_or_else(|| anyhow!("verify requires a profile name or id"))?,
+            }
+        },
+        ("cleanup", _) => {
+            check_options(&options, &["dry-run"])?;
+            CliCommand::Cleanup {
+                dry_run: options.contains_key("dry-run"),
+            }
+        },
+        ("profiles", Some("list")) => {
+            check_options(&options, &[])?;
+            CliCommand::ProfilesList
+        },
+        ("profiles", Some("create")) => {
+            check_options(&options, &["version", "loader", "loader-version", "memory"])?;
+            let mod_loader = match options.remove("loader").as_deref().map(|l| l.to_lowercase()) {
+                None => ModLoader::None,
+                Some(loader) => match loader.as_str() {
+                    "none" | "vanilla" => ModLoader::None,
+                    "forge" => ModLoader::Forge,
+                    "fabric" => ModLoader::Fabric,
+                    "quilt" => ModLoader::Quilt,
+                    "neoforge" => ModLoader::NeoForge,
+                    other => return Err(anyhow!("Unknown mod loader: {}", other)),
+                },
+            };
+            let memory = match options.remove("memory") {
+                Some(memory) => Some(memory.parse::<u32>()
+                    .map_err(|_| anyhow!("Invalid memory value: {}", memory))?),
+                None => None,
+            };
+
+            CliCommand::ProfilesCreate {
+                name: positionals.get(1).cloned()
+                    .ok_or_else(|| anyhow!("profiles create requires a name"))?,
+                version: options.remove("version")
+                    .ok_or_else(|| anyhow!("profiles create requires --version <version>"))?,
+                mod_loader,
+                mod_loader_version: options.remove("loader-version"),
+                memory,
+            }
+        },
+        ("profiles", Some("delete")) => {
+            check_options(&options, &[])?;
+            CliCommand::ProfilesDelete {
+                profile: positionals.get(1).cloned()
+                    .ok_or_else(|| anyhow!("profiles delete requires a profile name or id"))?,
+            }
+        },
+        ("mods", Some("list")) => {
+            check_options(&options, &["profile"])?;
+            CliCommand::ModsList {
+                profile: options.remove("profile")
+                    .or_else(|| positionals.get(1).cloned())
+                    .ok_or_else(|| anyhow!("mods list requires --profile <name|id>"))?,
+            }
+        },
+        ("profiles", _) => return Err(anyhow!("Expected profiles list|create|delete")),
+        ("mods", _) => return Err(anyhow!("Expected mods list")),
+        (other, _) => return Err(anyhow!("Unknown command: {}", other)),
+    };
+
+    Ok(Some(command))
+}
+
+/// Prints the command line usage
+pub fn print_usage() {
+    println!("{}", USAGE);
+}
+
+/// Prints the command line usage to stderr, after a usage error
+pub fn eprint_usage() {
+    eprintln!("{}", USAGE);
+}
+
+/// Runs a headless command and returns the process exit code
+pub fn run(command: CliCommand, config: Config) -> i32 {
+    let result = match command {
+        CliCommand::Help => {
+            print_usage();
+            Ok(())
+        },
+        CliCommand::Launch { profile, offline_username } => launch(config, &profile, offline_username),
+        CliCommand::Install { version } => install(config, &version),
+        CliCommand::Verify { profile } => verify(config, &profile),
+        CliCommand::Cleanup { dry_run } => cleanup(config, dry_run),
+        CliCommand::ProfilesList => list_profiles(config),
+        CliCommand::ProfilesCreate { name, version, mod_loader, mod_loader_version, memory } => {
+            create_profile(config, name, version, mod_loader, mod_loader_version, memory)
+        },
+        CliCommand::ProfilesDelete { profile } => delete_profile(config, &profile),
+        CliCommand::ModsList { profile } => list_mods(config, &profile),
+        CliCommand::QuickLaunch { profile } => launch(config, &profile, None),
+        CliCommand::OpenLink { .. } => Err(anyhow!("Links can only be opened in the launcher window")),
+    };
+
+    match result {
+        Ok(()) => EXIT_SUCCESS,
+        Err(e) => {
+            error!("Command failed: {}", e);
+            eprintln!("Error: {}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Finds a profile by id, or by name ignoring case
+fn find_profile(profiles: &[Profile], profile_ref: &str) -> Result<Profile> {
+    profiles.iter()
+        .find(|p| p.id == profile_ref)
+        .or_else(|| profiles.iter().find(|p| p.name.eq_ignore_ascii_case(profile_ref)))
+        .cloned()
+        .ok_or_else(|| anyhow!("Profile '{}' not found", profile_ref))
+}
+
+/// Prints install progress on a single terminal line
+fn print_progress(progress: InstallProgress) {
+    eprint!(
+        "\r\x1b[2K[{:>5.1}%] {}",
+        progress.fraction() * 100.0,
+        progress.status_text()
+    );
+    let _ = std::io::stderr().flush();
+}
+
+/// Returns true if a stored login can be used to launch without any interaction
+pub fn has_stored_session() -> bool {
+    // Only reads the stored session, so the proxy settings don't matter
+    AuthManager::new(&ProxySettings::default()).load_session().is_some()
+}
+
+/// Logs in with the Microsoft device code flow, printing the code to the terminal
+fn login_with_device_code(auth_manager: &AuthManager, rt: &tokio::runtime::Runtime) -> Result<AuthSession> {
+    let device_auth = auth_manager.start_login()?;
+
+    println!("To sign in, open {} and enter the code {}",
+        device_auth.verification_uri().as_str(),
+        device_auth.user_code().secret());
+    println!("Waiting for authentication...");
+
+    rt.block_on(auth_manager.complete_login(device_auth))
+}
+
+fn launch(config: Config, profile_ref: &str, offline_username: Option<String>) -> Result<()> {
+    let auth_manager = AuthManager::new(&config.proxy);
+    let manager = MinecraftManager::new(config, Rc::new(FileManager::new()));
+    let profile = find_profile(&manager.get_profiles(), profile_ref)?;
+
+    let rt = tokio::runtime::Runtime::new()?;
+    let auth_session = match offline_username {
+        Some(username) => auth_manager.create_offline_session(&username)?,
+        None => match auth_manager.load_session() {
+            Some(session) => {
+                info!("Using stored auth session");
+                session
+            },
+            None => {
+                let session = login_with_device_code(&auth_manager, &rt)?;
+                if let Err(e) = auth_manager.save_session(&session) {
+                    eprintln!("Warning: failed to store the login: {}", e);
+                }
+                session
+            },
+        },
+    };
+
+    // Launching installs whatever the version is missing, with progress on the same line
+    match manager.get_launch_version(&profile) {
+        Ok(version_id) => println!("Launching {} ({})", profile.name, version_id),
+        Err(_) => println!("Launching {}, installing its mod loader first", profile.name),
+    }
+    let pid = rt.block_on(manager.launch_game(&profile, &auth_session, print_progress, &CancellationToken::new()))?;
+    eprintln!();
+    println!("Launched with PID {}", pid);
+
+    Ok(())
+}
+
+fn install(config: Config, version: &str) -> Result<()> {
+    let manager = MinecraftManager::new(config, Rc::new(FileManager::new()));
+
+    if manager.is_version_installed(version) {
+        println!("Minecraft {} is already installed", version);
+        return Ok(());
+    }
+
+    println!("Installing Minecraft {}", version);
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(manager.install_version(version, print_progress, &CancellationToken::new()))?;
+    eprintln!();
+    println!("Installed Minecraft {}", version);
+
+    Ok(())
+}
+
+fn verify(config: Config, profile_ref: &str) -> Result<()> {
+    let manager = MinecraftManager::new(config, Rc::new(FileManager::new()));
+    let profile = find_profile(&manager.get_profiles(), profile_ref)?;
+
+    println!("Verifying {} ({})", profile.name, manager.get_launch_version(&profile)?);
+    let rt = tokio::runtime::Runtime::new()?;
+    let report = rt.block_on(manager.verify_profile(&profile, print_progress, &CancellationToken::new()))?;
+    eprintln!();
+
+    if report.is_intact() {
+        println!("All {} files are intact", report.checked);
+        return Ok(());
+    }
+
+    for file in &report.damaged {
+        println!("{}\t{}", file.issue, file.path.display());
+    }
+    if report.natives_missing {
+        println!("missing\tnatives");
+    }
+
+    let generated = report.damaged.iter().filter(|file| file.url.is_none()).count();
+    println!("Repaired {} files", report.damaged.len() - generated);
+    if generated > 0 {
+        return Err(anyhow!("{} generated files can't be downloaded, reinstall the mod loader to restore them", generated));
+    }
+
+    Ok(())
+}
+
+fn cleanup(config: Config, dry_run: bool) -> Result<()> {
+    let manager = MinecraftManager::new(config, Rc::new(FileManager::new()));
+    let plan = manager.find_unused_files()?;
+
+    if plan.is_empty() {
+        println!("No unused files");
+        return Ok(());
+    }
+
+    if dry_run {
+        let minecraft_dir = manager.get_minecraft_directory();
+        for entry in plan.entries() {
+            let path = entry.path.strip_prefix(&minecraft_dir).unwrap_or(&entry.path);
+            println!("{}\t{}", format_file_size(entry.size), path.display());
+        }
+        println!(
+            "{} versions, {} libraries and {} assets are unused, {} can be freed",
+            plan.versions.len(), plan.libraries.len(), plan.assets.len(), format_file_size(plan.total_size())
+        );
+        return Ok(());
+    }
+
+    let freed = manager.remove_unused_files()?;
+    println!("Freed {}", format_file_size(freed));
+
+    Ok(())
+}
+
+fn list_profiles(config: Config) -> Result<()> {
+    let manager = MinecraftManager::new(config, Rc::new(FileManager::new()));
+
+    for profile in manager.get_profiles() {
+        let loader = match &profile.mod_loader {
+            Some(ModLoader::Forge) => " Forge",
+            Some(ModLoader::Fabric) => " Fabric",
+            Some(ModLoader::Quilt) => " Quilt",
+            Some(ModLoader::NeoForge) => " NeoForge",
+            _ => "",
+        };
+        // Loader profiles launch the version their installer created, not the vanilla one
+        let installed = match manager.get_launch_version(&profile) {
+            Ok(version) if manager.is_version_installed(&version) => "",
+            _ => " (not installed)",
+        };
+        println!("{}\t{}\t{}{}{}", profile.id, profile.name, profile.version, loader, installed);
+    }
+
+    Ok(())
+}
+
+fn create_profile(
+    config: Config,
+    name: String,
+    version: String,
+    mod_loader: ModLoader,
+    mod_loader_version: Option<String>,
+    memory: Option<u32>,
+) -> Result<()> {
+    let mut manager = MinecraftManager::new(config, Rc::new(FileManager::new()));
+
+    if manager.get_profiles().iter().any(|p| p.name.eq_ignore_ascii_case(&name)) {
+        return Err(anyhow!("A profile named '{}' already exists", name));
+    }
+
+    let id = Uuid::new_v4().to_string();
+    manager.add_profile(Profile {
+        id: id.clone(),
+        name: name.clone(),
+        version,
+        mod_loader: Some(mod_loader),
+        mod_loader_version,
+        mods: Vec::new(),
+        game_directory: None,
+        resolution: None,
+        icon: None,
+        launch: LaunchSettings {
+            max_memory: memory,
+            ..LaunchSettings::default()
+        },
+    });
+
+    save_config(manager.get_config())?;
+    info!("Created profile {} ({})", name, id);
+    println!("Created profile {} ({})", name, id);
+
+    Ok(())
+}
+
+fn delete_profile(config: Config, profile_ref: &str) -> Result<()> {
+    let mut manager = MinecraftManager::new(config, Rc::new(FileManager::new()));
+    let profile = find_profile(&manager.get_profiles(), profile_ref)?;
+
+    manager.delete_profile(&profile.id)?;
+    save_config(manager.get_config())?;
+
+    if let Err(e) = desktop::remove_desktop_entry(&profile.id) {
+        eprintln!("Warning: failed to remove the desktop entry: {}", e);
+    }
+    println!("Deleted profile {} ({})", profile.name, profile.id);
+
+    Ok(())
+}
+
+fn list_mods(config: Config, profile_ref: &str) -> Result<()> {
+    let manager = MinecraftManager::new(config.clone(), Rc::new(FileManager::new()));
+    let profile = find_profile(&manager.get_profiles(), profile_ref)?;
+
+    let mod_manager = MinecraftModManager::new(config, Rc::new(FileManager::new()));
+    let rt = tokio::runtime::Runtime::new()?;
+    let mods = rt.block_on(mod_manager.get_installed_mods(&profile))?;
+
+    if mods.is_empty() {
+        println!("No mods installed for {}", profile.name);
+    }
+    for installed in mods {
+        let state = if installed.enabled { "" } else { " (disabled)" };
+        println!("{}\t{}{}", installed.name, installed.version, state);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_config;
+
+    fn parse(args: &[&str]) -> Result<Option<CliCommand>> {
+        parse_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    fn command(args: &[&str]) -> CliCommand {
+        parse(args).unwrap().unwrap()
+    }
+
+    #[test]
+    fn starts_the_gui_without_arguments() {
+        assert_eq!(parse(&[]).unwrap(), None);
+    }
+
+    #[test]
+    fn parses_each_command() {
+        assert_eq!(command(&["help"]), CliCommand::Help);
+        assert_eq!(command(&["--help"]), CliCommand::Help);
+        assert_eq!(
+            command(&["launch", "--profile", "Survival", "--offline=Steve"]),
+            CliCommand::Launch { profile: "Survival".to_string(), offline_username: Some("Steve".to_string()) },
+        );
+        assert_eq!(
+            command(&["launch", "Survival"]),
+            CliCommand::Launch { profile: "Survival".to_string(), offline_username: None },
+        );
+        assert_eq!(command(&["install", "1.20.4"]), CliCommand::Install { version: "1.20.4".to_string() });
+        assert_eq!(command(&["verify", "Survival"]), CliCommand::Verify { profile: "Survival".to_string() });
+        assert_eq!(command(&["cleanup"]), CliCommand::Cleanup { dry_run: false });
+        assert_eq!(command(&["cleanup", "--dry-run"]), CliCommand::Cleanup { dry_run: true });
+        assert_eq!(command(&["profiles", "list"]), CliCommand::ProfilesList);
+        assert_eq!(
+            command(&["profiles", "create", "Modded", "--version", "1.20.1", "--loader", "Forge", "--loader-version", "47.2.0", "--memory", "4096"]),
+            CliCommand::ProfilesCreate {
+                name: "Modded".to_string(),
+                version: "1.20.1".to_string(),
+                mod_loader: ModLoader::Forge,
+                mod_loader_version: Some("47.2.0".to_string()),
+                memory: Some(4096),
+            },
+        );
+        assert_eq!(command(&["profiles", "delete", "Modded"]), CliCommand::ProfilesDelete { profile: "Modded".to_string() });
+        assert_eq!(command(&["mods", "list", "--profile", "Modded"]), CliCommand::ModsList { profile: "Modded".to_string() });
+        assert_eq!(command(&["--launch", "0b5c"]), CliCommand::QuickLaunch { profile: "0b5c".to_string() });
+    }
+
+    #[test]
+    fn rejects_unknown_commands_and_options() {
+        assert!(parse(&["frobnicate"]).is_err());
+        assert!(parse(&["profiles"]).is_err());
+        assert!(parse(&["profiles", "rename"]).is_err());
+        assert!(parse(&["mods"]).is_err());
+        assert!(parse(&["install", "1.20.4", "--force"]).is_err());
+        assert!(parse(&["cleanup", "--profile", "Survival"]).is_err());
+        assert!(parse(&["launch", "--profile", "Survival", "--fullscreen", "yes"]).is_err());
+        assert!(parse(&["profiles", "create", "Modded", "--version", "1.20.1", "--loader", "rift"]).is_err());
+        assert!(parse(&["profiles", "create", "Modded", "--version", "1.20.1", "--memory", "lots"]).is_err());
+    }
+
+    #[test]
+    fn requires_arguments() {
+        assert!(parse(&["launch"]).is_err());
+        assert!(parse(&["launch", "--offline", "Steve"]).is_err());
+        assert!(parse(&["launch", "--profile"]).is_err());
+        assert!(parse(&["mods", "list"]).is_err());
+        assert!(parse(&["verify"]).is_err());
+        assert!(parse(&["install"]).is_err());
+        assert!(parse(&["profiles", "create", "Modded"]).is_err());
+        assert!(parse(&["profiles", "delete"]).is_err());
+        assert!(parse(&["--launch"]).is_err());
+        assert!(parse(&["--launch", "--profile", "0b5c"]).is_err());
+    }
+
+    #[test]
+    fn passes_links_through() {
+        let link = "mosaic://install?source=modrinth&project=AANobbMI";
+        assert_eq!(command(&[link]), CliCommand::OpenLink { url: link.to_string() });
+        assert!(parse(&["mosaic://uninstall?project=AANobbMI"]).is_err());
+    }
+
+    #[test]
+    fn returns_exit_codes() {
+        assert_eq!(run(CliCommand::Help, default_config()), EXIT_SUCCESS);
+        let link = CliCommand::OpenLink { url: "mosaic://install?project=AANobbMI".to_string() };
+        assert_eq!(run(link, default_config()), EXIT_FAILURE);
+        assert_ne!(EXIT_USAGE, EXIT_SUCCESS);
+        assert_ne!(EXIT_USAGE, EXIT_FAILURE);
+    }
+}
@@ -1,9 +1,17 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use directories::ProjectDirs;
-use log::{info, warn, error};
+use log::{info, warn, error, debug};
+
+use crate::utils::write_file_atomically;
+
+/// Number of rotating `config.json.bak.N` backups kept next to the config file
+pub const CONFIG_BACKUP_COUNT: usize = 5;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Game {
@@ -14,6 +22,8 @@ pub struct Game {
     pub game_directory: PathBuf,
     pub profiles: Vec<Profile>,
     pub game_type: GameType,
+    #[serde(default)]
+    pub launch: LaunchSettings, // Overrides the global launch settings for this game
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -24,14 +34,17 @@ pub enum GameType {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    pub schema_version: u32,
     pub games: Vec<Game>,
     pub selected_game: Option<String>,
     pub last_used_profile: Option<String>,
     pub theme: Theme,
-    pub max_memory: u32, // in MB
-    pub java_arguments: Vec<String>,
-    pub java_path: Option<PathBuf>,
+    pub launch: LaunchSettings, // Global launch settings, the base layer for every game
     pub disable_sandbox: bool, // Disable sandbox mode for games
+    pub offline_mode: bool, // Never use the network, launch only what is installed
+    pub downloads: DownloadSettings,
+    pub mirrors: Vec<Mirror>, // Checked for every download and API request
+    pub proxy: ProxySettings, // Applied to every HTTP client the launcher builds
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,7 +57,184 @@ pub struct Profile {
     pub mods: Vec<Mod>,
     pub game_directory: Option<PathBuf>,
     pub resolution: Option<(u32, u32)>,
-    pub memory: Option<u32>, // RAM in MB
+    pub icon: Option<String>, // Block name or data URI, as used by the official launcher
+    #[serde(default)]
+    pub launch: LaunchSettings, // Overrides the game launch settings for this profile
+}
+
+/// One layer of launch settings
+///
+/// Layers are stacked global <- game <- profile; unset fields fall through to
+/// the layer below.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LaunchSettings {
+    pub jvm_arguments: Option<Vec<String>>,
+    pub min_memory: Option<u32>, // in MB
+    pub max_memory: Option<u32>, // in MB
+    pub java_path: Option<PathBuf>,
+    #[serde(default)]
+    pub environment: HashMap<String, String>, // Merged per variable across layers
+}
+
+impl LaunchSettings {
+    /// Returns this layer with every field set in `overrides` replaced
+    pub fn overlay(&self, overrides: &LaunchSettings) -> LaunchSettings {
+        let mut environment = self.environment.clone();
+        environment.extend(overrides.environment.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        LaunchSettings {
+            jvm_arguments: overrides.jvm_arguments.clone().or_else(|| self.jvm_arguments.clone()),
+            min_memory: overrides.min_memory.or(self.min_memory),
+            max_memory: overrides.max_memory.or(self.max_memory),
+            java_path: overrides.java_path.clone().or_else(|| self.java_path.clone()),
+            environment,
+        }
+    }
+
+    /// Returns true if this layer does not override anything
+    pub fn is_empty(&self) -> bool {
+        *self == LaunchSettings::default()
+    }
+}
+
+/// Launch settings after all layers have been applied
+#[derive(Debug, Clone)]
+pub struct EffectiveLaunchSettings {
+    pub jvm_arguments: Vec<String>,
+    pub min_memory: u32, // in MB
+    pub max_memory: u32, // in MB
+    pub java_path: Option<PathBuf>, // None means auto-detect
+    pub environment: HashMap<String, String>,
+}
+
+/// Default heap size used when no layer sets one
+pub const DEFAULT_MAX_MEMORY: u32 = 2048;
+
+/// JVM arguments used when no layer sets any
+pub const DEFAULT_JVM_ARGUMENTS: &[&str] = &[
+    "-XX:+UnlockExperimentalVMOptions",
+    "-XX:+UseG1GC",
+    "-XX:G1NewSizePercent=20",
+    "-XX:G1ReservePercent=20",
+    "-XX:MaxGCPauseMillis=50",
+    "-XX:G1HeapRegionSize=32M",
+];
+
+/// Resolves the launch settings for a profile, applying global <- game <- profile
+pub fn resolve_launch_settings(config: &Config, game: Option<&Game>, profile: &Profile) -> EffectiveLaunchSettings {
+    let mut layered = config.launch.clone();
+    if let Some(game) = game {
+        layered = layered.overlay(&game.launch);
+    }
+    layered = layered.overlay(&profile.launch);
+
+    let max_memory = layered.max_memory.unwrap_or(DEFAULT_MAX_MEMORY);
+    // Without an explicit minimum the heap starts at its maximum size
+    let mut min_memory = layered.min_memory.unwrap_or(max_memory);
+    if min_memory > max_memory {
+        warn!("Minimum memory {}MB exceeds maximum memory {}MB for profile {}, clamping", min_memory, max_memory, profile.name);
+        min_memory = max_memory;
+    }
+
+    EffectiveLaunchSettings {
+        jvm_arguments: layered.jvm_arguments
+            .unwrap_or_else(|| DEFAULT_JVM_ARGUMENTS.iter().map(|s| s.to_string()).collect()),
+        min_memory,
+        max_memory,
+        java_path: layered.java_path,
+        environment: layered.environment,
+    }
+}
+
+/// Default number of files downloaded at the same time
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 16;
+
+/// Default number of simultaneous connections to a single host
+pub const DEFAULT_MAX_CONNECTIONS_PER_HOST: usize = 8;
+
+/// Limits for parallel downloads during installs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DownloadSettings {
+    pub max_concurrent_downloads: usize,
+    pub max_connections_per_host: usize,
+    pub max_bytes_per_second: Option<u64>, // Combined speed of all downloads, None means unlimited
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        Self {
+            max_concurrent_downloads: DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+            max_connections_per_host: DEFAULT_MAX_CONNECTIONS_PER_HOST,
+            max_bytes_per_second: None,
+        }
+    }
+}
+
+/// Replacements for a download host or URL prefix
+///
+/// Covers any URL the launcher fetches, e.g. `https://piston-meta.mojang.com/`,
+/// `https://resources.download.minecraft.net/`, the Forge, NeoForge, Fabric and
+/// Quilt mavens, `https://api.modrinth.com/` or `https://api.curseforge.com/`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Mirror {
+    /// URL prefix to rewrite
+    pub prefix: String,
+    /// Prefixes tried in order in place of `prefix`
+    pub urls: Vec<String>,
+    /// Try the original URL after every mirror failed
+    #[serde(default = "default_true")]
+    pub fallback_to_original: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Where HTTP requests are routed through
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum ProxyMode {
+    /// Use the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment variables
+    #[default]
+    System,
+    /// Connect directly, even if the environment names a proxy
+    None,
+    /// Use the proxy described by `ProxySettings`
+    Manual,
+}
+
+/// Protocol spoken to a manually configured proxy
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum ProxyKind {
+    #[default]
+    Http,
+    Https,
+    Socks5,
+}
+
+impl ProxyKind {
+    /// Gets the URL scheme for this kind of proxy
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            ProxyKind::Http => "http",
+            ProxyKind::Https => "https",
+            ProxyKind::Socks5 => "socks5h", // Let the proxy resolve host names
+        }
+    }
+}
+
+/// Proxy used for downloads, mod APIs and sign-in
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProxySettings {
+    pub mode: ProxyMode,
+    /// Only used in `ProxyMode::Manual`, as are the fields below
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Hosts or domains that bypass the proxy, e.g. `localhost` or `.example.com`
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -99,95 +289,510 @@ pub fn get_config_file() -> Result<PathBuf> {
     Ok(config_dir.join("config.json"))
 }
 
-// Define the old config format for migration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct OldConfig {
-    pub minecraft_directory: PathBuf,
-    pub profiles: Vec<Profile>,
-    pub last_used_profile: Option<String>,
-    pub theme: Theme,
-    pub max_memory: u32, // in MB
-    pub java_arguments: Vec<String>,
-    pub java_path: Option<PathBuf>,
-    pub disable_sandbox: bool, // Disable sandbox mode for games
+/// Current version of the `config.json` schema
+///
+/// Bump this whenever the shape of `Config`, `Game` or `Profile` changes and
+/// register a matching entry in `MIGRATIONS`.
+pub const CONFIG_SCHEMA_VERSION: u32 = 9;
+
+/// A single upgrade step for the raw config JSON
+struct Migration {
+    /// Schema version this migration upgrades from (it produces `from + 1`)
+    from: u32,
+    /// Short description used for logging
+    description: &'static str,
+    /// Rewrites the raw JSON into the next schema version
+    apply: fn(Value) -> Result<Value>,
 }
 
-// Function to migrate from old config format to new format
-fn migrate_config(old_config: OldConfig) -> Config {
-    info!("Migrating configuration from old format to new format");
+/// Ordered registry of config migrations, one per schema version bump
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "move minecraft_directory and profiles into a Minecraft game entry",
+        apply: migrate_v0_to_v1,
+    },
+    Migration {
+        from: 1,
+        description: "add schema_version",
+        apply: migrate_v1_to_v2,
+    },
+    Migration {
+        from: 2,
+        description: "move java and memory settings into layered launch settings",
+        apply: migrate_v2_to_v3,
+    },
+    Migration {
+        from: 3,
+        description: "add profile icons",
+        apply: migrate_v3_to_v4,
+    },
+    Migration {
+        from: 4,
+        description: "add offline mode",
+        apply: migrate_v4_to_v5,
+    },
+    Migration {
+        from: 5,
+        description: "add download limits",
+        apply: migrate_v5_to_v6,
+    },
+    Migration {
+        from: 6,
+        description: "add download mirrors",
+        apply: migrate_v6_to_v7,
+    },
+    Migration {
+        from: 7,
+        description: "add proxy settings",
+        apply: migrate_v7_to_v8,
+    },
+    Migration {
+        from: 8,
+        description: "add download speed limit",
+        apply: migrate_v8_to_v9,
+    },
+];
+
+/// Determines the schema version of a raw config
+///
+/// Files written before `schema_version` existed are identified by their shape:
+/// - v0: single-game config with top-level `minecraft_directory` and `profiles`
+/// - v1: multi-game config with `games`, but no `schema_version`
+fn detect_schema_version(value: &Value) -> Result<u32> {
+    let obj = value.as_object()
+        .ok_or_else(|| anyhow!("Configuration root is not a JSON object"))?;
+
+    if let Some(version) = obj.get("schema_version") {
+        let version = version.as_u64()
+            .ok_or_else(|| anyhow!("Invalid schema_version in configuration: {}", version))?;
+        return Ok(version as u32);
+    }
+
+    if obj.contains_key("minecraft_directory") {
+        Ok(0)
+    } else {
+        Ok(1)
+    }
+}
+
+/// Upgrades a raw config to `CONFIG_SCHEMA_VERSION`
+///
+/// Returns the upgraded JSON together with the version it started from.
+pub fn migrate_config(mut value: Value) -> Result<(Value, u32)> {
+    let original_version = detect_schema_version(&value)?;
+
+    if original_version > CONFIG_SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Configuration schema version {} is newer than the supported version {}. Please update Mosaic Launcher.",
+            original_version, CONFIG_SCHEMA_VERSION
+        ));
+    }
+
+    let mut version = original_version;
+    while version < CONFIG_SCHEMA_VERSION {
+        let migration = MIGRATIONS.iter()
+            .find(|m| m.from == version)
+            .ok_or_else(|| anyhow!("No migration registered for config schema version {}", version))?;
+
+        info!("Migrating configuration from schema version {} to {}: {}", version, version + 1, migration.description);
+        value = (migration.apply)(value)
+            .map_err(|e| anyhow!("Config migration from schema version {} failed: {}", version, e))?;
+        version += 1;
+
+        // Record progress so a later step always sees the version it expects
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert("schema_version".to_string(), json!(version));
+        }
+    }
+
+    Ok((value, original_version))
+}
+
+/// v0 -> v1: the single-game config becomes a `games` list with one Minecraft entry
+///
+/// ```json
+/// { "minecraft_directory": "/home/user/.minecraft", "profiles": [], "last_used_profile": null,
+///   "theme": "System", "max_memory": 2048, "java_arguments": [], "java_path": null,
+///   "disable_sandbox": false }
+/// ```
+fn migrate_v0_to_v1(mut value: Value) -> Result<Value> {
+    let obj = value.as_object_mut()
+        .ok_or_else(|| anyhow!("Configuration root is not a JSON object"))?;
+
+    let minecraft_directory = obj.remove("minecraft_directory")
+        .ok_or_else(|| anyhow!("Missing minecraft_directory"))?;
+    let profiles = obj.remove("profiles").unwrap_or_else(|| json!([]));
 
     // Create a Minecraft game with the old minecraft_directory and profiles
-    let minecraft_game = Game {
-        id: "minecraft".to_string(),
-        name: "Minecraft".to_string(),
-        icon: None,
-        executable: None,
-        game_directory: old_config.minecraft_directory,
-        profiles: old_config.profiles,
-        game_type: GameType::Minecraft,
-    };
+    obj.insert("games".to_string(), json!([{
+        "id": "minecraft",
+        "name": "Minecraft",
+        "icon": null,
+        "executable": null,
+        "game_directory": minecraft_directory,
+        "profiles": profiles,
+        "game_type": "Minecraft",
+    }]));
+    obj.insert("selected_game".to_string(), json!("minecraft"));
 
-    // Create a new config with the migrated data
-    Config {
-        games: vec![minecraft_game],
-        selected_game: Some("minecraft".to_string()),
-        last_used_profile: old_config.last_used_profile,
-        theme: old_config.theme,
-        max_memory: old_config.max_memory,
-        java_arguments: old_config.java_arguments,
-        java_path: old_config.java_path,
-        disable_sandbox: old_config.disable_sandbox,
+    Ok(value)
+}
+
+/// v1 -> v2: introduces `schema_version`, no other changes
+///
+/// ```json
+/// { "games": [ { "id": "minecraft", "profiles": [] } ], "selected_game": "minecraft",
+///   "last_used_profile": null, "theme": "System", "max_memory": 2048,
+///   "java_arguments": [], "java_path": null, "disable_sandbox": false }
+/// ```
+fn migrate_v1_to_v2(value: Value) -> Result<Value> {
+    Ok(value)
+}
+
+/// v2 -> v3: `max_memory`, `java_arguments` and `java_path` move into `launch`,
+/// and every game and profile gets its own `launch` layer
+///
+/// ```json
+/// { "schema_version": 2,
+///   "games": [ { "id": "minecraft", "profiles": [ { "id": "...", "memory": 4096 } ] } ],
+///   "max_memory": 2048, "java_arguments": ["-XX:+UseG1GC"], "java_path": null }
+/// ```
+fn migrate_v2_to_v3(mut value: Value) -> Result<Value> {
+    let obj = value.as_object_mut()
+        .ok_or_else(|| anyhow!("Configuration root is not a JSON object"))?;
+
+    let max_memory = obj.remove("max_memory").unwrap_or(Value::Null);
+    let java_path = obj.remove("java_path").unwrap_or(Value::Null);
+
+    // The launcher used to add its G1 flags unconditionally, keep them in front
+    // of whatever the user had configured
+    let mut jvm_arguments: Vec<String> = DEFAULT_JVM_ARGUMENTS.iter().map(|s| s.to_string()).collect();
+    if let Some(Value::Array(args)) = obj.remove("java_arguments") {
+        for arg in args.iter().filter_map(|a| a.as_str()) {
+            if !jvm_arguments.iter().any(|a| a == arg) {
+                jvm_arguments.push(arg.to_string());
+            }
+        }
+    }
+
+    obj.insert("launch".to_string(), json!({
+        "jvm_arguments": jvm_arguments,
+        "min_memory": null,
+        "max_memory": max_memory,
+        "java_path": java_path,
+        "environment": {},
+    }));
+
+    if let Some(Value::Array(games)) = obj.get_mut("games") {
+        for game in games.iter_mut().filter_map(|g| g.as_object_mut()) {
+            game.insert("launch".to_string(), json!({}));
+
+            if let Some(Value::Array(profiles)) = game.get_mut("profiles") {
+                for profile in profiles.iter_mut().filter_map(|p| p.as_object_mut()) {
+                    // The old per-profile memory set both -Xms and -Xmx
+                    let memory = profile.remove("memory").unwrap_or(Value::Null);
+                    profile.insert("launch".to_string(), json!({ "max_memory": memory }));
+                }
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+/// v3 -> v4: profiles gain an optional `icon`
+///
+/// ```json
+/// { "schema_version": 3,
+///   "games": [ { "id": "minecraft", "launch": {}, "profiles": [ { "id": "...", "launch": {} } ] } ] }
+/// ```
+fn migrate_v3_to_v4(mut value: Value) -> Result<Value> {
+    if let Some(Value::Array(games)) = value.get_mut("games") {
+        for game in games.iter_mut() {
+            if let Some(Value::Array(profiles)) = game.get_mut("profiles") {
+                for profile in profiles.iter_mut().filter_map(|p| p.as_object_mut()) {
+                    profile.entry("icon").or_insert(Value::Null);
+                }
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+/// v4 -> v5: adds the global `offline_mode` switch
+///
+/// ```json
+/// { "schema_version": 4, "games": [], "disable_sandbox": false }
+/// ```
+fn migrate_v4_to_v5(mut value: Value) -> Result<Value> {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("offline_mode").or_insert(Value::Bool(false));
+    }
+
+    Ok(value)
+}
+
+/// v5 -> v6: adds the `downloads` parallelism limits
+///
+/// ```json
+/// { "schema_version": 5, "games": [], "offline_mode": false }
+/// ```
+fn migrate_v5_to_v6(mut value: Value) -> Result<Value> {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("downloads").or_insert(json!({
+            "max_concurrent_downloads": DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+            "max_connections_per_host": DEFAULT_MAX_CONNECTIONS_PER_HOST,
+        }));
+    }
+
+    Ok(value)
+}
+
+/// v6 -> v7: adds the empty `mirrors` table
+///
+/// ```json
+/// { "schema_version": 6, "games": [], "downloads": { "max_concurrent_downloads": 16 } }
+/// ```
+fn migrate_v6_to_v7(mut value: Value) -> Result<Value> {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("mirrors").or_insert(json!([]));
+    }
+
+    Ok(value)
+}
+
+/// v7 -> v8: adds `proxy`, defaulting to the system proxy
+///
+/// ```json
+/// { "schema_version": 7, "games": [], "mirrors": [] }
+/// ```
+fn migrate_v7_to_v8(mut value: Value) -> Result<Value> {
+    if let Some(obj) = value.as_object_mut() {
+        obj.entry("proxy").or_insert(json!({
+            "mode": "System",
+            "kind": "Http",
+            "host": "",
+            "port": 0,
+            "username": null,
+            "password": null,
+            "no_proxy": [],
+        }));
+    }
+
+    Ok(value)
+}
+
+/// v8 -> v9: adds the unlimited `max_bytes_per_second` to `downloads`
+///
+/// ```json
+/// { "schema_version": 8, "downloads": { "max_concurrent_downloads": 16, "max_connections_per_host": 8 } }
+/// ```
+fn migrate_v8_to_v9(mut value: Value) -> Result<Value> {
+    if let Some(downloads) = value.get_mut("downloads").and_then(|downloads| downloads.as_object_mut()) {
+        downloads.entry("max_bytes_per_second").or_insert(Value::Null);
+    }
+
+    Ok(value)
+}
+
+/// Describes a configuration that had to be restored from a backup while loading
+#[derive(Debug, Clone)]
+pub struct ConfigRecovery {
+    /// Backup file the configuration was restored from, None if no backup was
+    /// usable and the defaults run read-only
+    pub backup_file: Option<PathBuf>,
+    /// Where the unreadable `config.json` was moved or copied to, if it could be preserved
+    pub corrupt_file: Option<PathBuf>,
+    /// Why the original `config.json` could not be loaded
+    pub error: String,
+}
+
+/// Gets the path of the N-th config backup (1 is the newest)
+fn get_backup_file(config_file: &Path, index: usize) -> PathBuf {
+    config_file.with_file_name(format!("config.json.bak.{}", index))
+}
+
+/// Reads, migrates and deserializes a config file
+///
+/// Returns the config together with the schema version it was stored in.
+fn read_config_file(path: &Path) -> Result<(Config, u32)> {
+    parse_config(&fs::read_to_string(path)?)
+}
+
+/// Parses the contents of a config file of any schema version
+fn parse_config(config_str: &str) -> Result<(Config, u32)> {
+    let raw: Value = serde_json::from_str(config_str)
+        .map_err(|e| anyhow!("Failed to parse configuration: {}", e))?;
+
+    // Upgrade older files step by step before deserializing
+    let (migrated, original_version) = migrate_config(raw)?;
+
+    let config: Config = serde_json::from_value(migrated).map_err(|e| {
+        error!("Failed to load config with schema version {}: {}", CONFIG_SCHEMA_VERSION, e);
+        anyhow!("Failed to load configuration: {}", e)
+    })?;
+
+    Ok((config, original_version))
+}
+
+/// Set once no config could be loaded, so the defaults never overwrite the user's file
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if changes to the configuration are not saved this session
+pub fn is_config_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// Gets the name an unreadable config file is kept under
+fn get_corrupt_config_file(config_file: &Path) -> PathBuf {
+    config_file.with_file_name(format!(
+        "config.json.corrupt-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ))
+}
+
+/// Moves an unreadable config file aside so it is not rotated away by the next save
+fn preserve_corrupt_config(config_file: &Path) -> Option<PathBuf> {
+    let corrupt_file = get_corrupt_config_file(config_file);
+
+    match fs::rename(config_file, &corrupt_file) {
+        Ok(()) => Some(corrupt_file),
+        Err(e) => {
+            warn!("Failed to move corrupt config {:?} aside: {}", config_file, e);
+            None
+        }
+    }
+}
+
+/// Copies an unreadable config file, leaving the original where a newer build can still read it
+fn copy_corrupt_config(config_file: &Path) -> Option<PathBuf> {
+    let corrupt_file = get_corrupt_config_file(config_file);
+
+    match fs::copy(config_file, &corrupt_file) {
+        Ok(_) => Some(corrupt_file),
+        Err(e) => {
+            warn!("Failed to copy corrupt config {:?}: {}", config_file, e);
+            None
+        }
     }
 }
 
 pub fn load_config() -> Result<Config> {
+    load_config_with_recovery().map(|(config, _)| config)
+}
+
+/// Loads the configuration, falling back to the newest backup that still parses
+///
+/// When a backup had to be used, the returned `ConfigRecovery` describes what
+/// happened so the UI can tell the user. Without a usable backup the defaults
+/// are returned and the configuration turns read-only for the session.
+pub fn load_config_with_recovery() -> Result<(Config, Option<ConfigRecovery>)> {
     let config_file = get_config_file()?;
 
     if !config_file.exists() {
         warn!("Config file does not exist, creating default config");
         let default_config = default_config();
         save_config(&default_config)?;
-        return Ok(default_config);
+        return Ok((default_config, None));
     }
 
-    let config_clone = config_file.clone();
-    let config_str = fs::read_to_string(config_file)?;
+    let error = match read_config_file(&config_file) {
+        Ok((config, original_version)) => {
+            if original_version < CONFIG_SCHEMA_VERSION {
+                // Save the migrated config
+                save_config(&config)?;
+                info!("Successfully migrated and saved configuration from schema version {}", original_version);
+            }
 
-    // Try to deserialize into the new Config format
-    match serde_json::from_str::<Config>(&config_str) {
-        Ok(config) => {
-            info!("Loaded configuration from {:?}", config_clone);
-            Ok(config)
+            info!("Loaded configuration from {:?}", config_file);
+            return Ok((config, None));
         },
-        Err(e) => {
-            // If that fails, try to deserialize into the old format and migrate
-            warn!("Failed to load config in new format: {}. Attempting to migrate from old format.", e);
-            match serde_json::from_str::<OldConfig>(&config_str) {
-                Ok(old_config) => {
-                    // Migrate from old format to new format
-                    let config = migrate_config(old_config);
-
-                    // Save the migrated config
-                    save_config(&config)?;
-
-                    info!("Successfully migrated and saved configuration");
-                    Ok(config)
-                },
-                Err(e2) => {
-                    // If both formats fail, return the original error
-                    error!("Failed to load config in old format as well: {}", e2);
-                    Err(anyhow::anyhow!("Failed to load configuration: {}", e))
-                }
+        Err(e) => e,
+    };
+
+    error!("Failed to load configuration from {:?}: {}. Trying backups.", config_file, error);
+
+    // Try the backups, newest first
+    for index in 1..=CONFIG_BACKUP_COUNT {
+        let backup_file = get_backup_file(&config_file, index);
+        if !backup_file.exists() {
+            continue;
+        }
+
+        match read_config_file(&backup_file) {
+            Ok((config, _)) => {
+                warn!("Restoring configuration from backup {:?}", backup_file);
+
+                let corrupt_file = preserve_corrupt_config(&config_file);
+                save_config(&config)?;
+
+                return Ok((config, Some(ConfigRecovery {
+                    backup_file: Some(backup_file),
+                    corrupt_file,
+                    error: error.to_string(),
+                })));
+            },
+            Err(e) => {
+                warn!("Backup {:?} is not usable: {}", backup_file, e);
             }
         }
     }
+
+    // Nothing is usable, e.g. the config is from a newer build. Run on the
+    // defaults without saving, so the user's file survives this session.
+    error!("No usable configuration or backup, running on defaults without saving");
+    let corrupt_file = copy_corrupt_config(&config_file);
+    READ_ONLY.store(true, Ordering::Relaxed);
+
+    Ok((default_config(), Some(ConfigRecovery {
+        backup_file: None,
+        corrupt_file,
+        error: error.to_string(),
+    })))
+}
+
+/// Shifts `config.json.bak.1..N` by one and copies the current config into slot 1
+fn rotate_backups(config_file: &Path) -> Result<()> {
+    if !config_file.exists() {
+        return Ok(());
+    }
+
+    let oldest = get_backup_file(config_file, CONFIG_BACKUP_COUNT);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+
+    for index in (1..CONFIG_BACKUP_COUNT).rev() {
+        let from = get_backup_file(config_file, index);
+        if from.exists() {
+            fs::rename(&from, get_backup_file(config_file, index + 1))?;
+        }
+    }
+
+    fs::copy(config_file, get_backup_file(config_file, 1))?;
+    Ok(())
 }
 
 pub fn save_config(config: &Config) -> Result<()> {
+    if is_config_read_only() {
+        return Err(anyhow!("The configuration could not be loaded, changes are not saved this session"));
+    }
+
     let config_file = get_config_file()?;
     let config_str = serde_json::to_string_pretty(config)?;
 
-    fs::write(config_file.clone(), config_str)?;
+    // Skip unchanged writes so the backups keep distinct older states
+    if let Ok(existing) = fs::read_to_string(&config_file) {
+        if existing == config_str {
+            debug!("Configuration unchanged, skipping save");
+            return Ok(());
+        }
+    }
+
+    rotate_backups(&config_file)?;
+    write_file_atomically(&config_file, config_str.as_bytes())?;
     info!("Saved configuration to {:?}", config_file);
 
     Ok(())
@@ -211,16 +816,151 @@ pub fn default_config() -> Config {
         game_directory: minecraft_dir,
         profiles: vec![],
         game_type: GameType::Minecraft,
+        launch: LaunchSettings::default(),
     };
 
     Config {
+        schema_version: CONFIG_SCHEMA_VERSION,
         games: vec![minecraft_game],
         selected_game: Some("minecraft".to_string()),
         last_used_profile: None,
         theme: Theme::System,
-        max_memory: 2048, // 2GB default
-        java_arguments: vec!["-XX:+UseG1GC".to_string(), "-XX:+ParallelRefProcEnabled".to_string()],
-        java_path: None,
+        launch: LaunchSettings {
+            jvm_arguments: Some(DEFAULT_JVM_ARGUMENTS.iter().map(|s| s.to_string()).collect()),
+            max_memory: Some(DEFAULT_MAX_MEMORY),
+            ..LaunchSettings::default()
+        },
         disable_sandbox: false, // Default to using sandbox mode
+        offline_mode: false,
+        downloads: DownloadSettings::default(),
+        mirrors: Vec::new(),
+        proxy: ProxySettings::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Config files as each schema version wrote them, index N holds version N
+    const FIXTURES: &[&str] = &[
+        include_str!("../tests/fixtures/config/v0.json"),
+        include_str!("../tests/fixtures/config/v1.json"),
+        include_str!("../tests/fixtures/config/v2.json"),
+        include_str!("../tests/fixtures/config/v3.json"),
+        include_str!("../tests/fixtures/config/v4.json"),
+        include_str!("../tests/fixtures/config/v5.json"),
+        include_str!("../tests/fixtures/config/v6.json"),
+        include_str!("../tests/fixtures/config/v7.json"),
+        include_str!("../tests/fixtures/config/v8.json"),
+    ];
+
+    const FABRIC_PROFILE: &str = "3f2a9c1e-5b7d-4e0a-9c4b-2d8f6a1e7b30";
+
+    fn load_fixture(version: u32) -> Config {
+        let (config, original_version) = parse_config(FIXTURES[version as usize]).unwrap();
+        assert_eq!(original_version, version);
+        assert_eq!(config.schema_version, CONFIG_SCHEMA_VERSION);
+        config
+    }
+
+    fn get_profile<'a>(config: &'a Config, id: &str) -> &'a Profile {
+        config.games[0].profiles.iter().find(|profile| profile.id == id).unwrap()
+    }
+
+    #[test]
+    fn every_old_schema_has_a_fixture() {
+        // Bumping CONFIG_SCHEMA_VERSION needs a fixture of the version it migrates from
+        assert_eq!(FIXTURES.len() as u32, CONFIG_SCHEMA_VERSION);
+        assert_eq!(MIGRATIONS.len() as u32, CONFIG_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn every_fixture_migrates() {
+        for version in 0..CONFIG_SCHEMA_VERSION {
+            let config = load_fixture(version);
+            assert_eq!(config.games[0].game_type, GameType::Minecraft);
+            assert_eq!(config.games[0].game_directory, PathBuf::from("/home/alex/.minecraft"));
+            assert_eq!(config.last_used_profile.as_deref(), Some(FABRIC_PROFILE));
+
+            let profile = get_profile(&config, FABRIC_PROFILE);
+            assert_eq!(profile.mod_loader, Some(ModLoader::Fabric));
+            assert_eq!(profile.mods.len(), 2);
+            assert_eq!(profile.launch.max_memory, Some(4096));
+        }
+    }
+
+    #[test]
+    fn migrates_single_game_config() {
+        let config = load_fixture(0);
+
+        assert_eq!(config.games.len(), 1);
+        assert_eq!(config.selected_game.as_deref(), Some("minecraft"));
+
+        let vanilla = get_profile(&config, "b71c04d2-8e3f-4a6b-a1d9-5c0e7f2b9a44");
+        assert_eq!(vanilla.resolution, Some((1920, 1080)));
+        assert_eq!(vanilla.game_directory, Some(PathBuf::from("/home/alex/games/vanilla")));
+    }
+
+    #[test]
+    fn moves_java_settings_into_launch_layers() {
+        for version in 0..=2 {
+            let config = load_fixture(version);
+
+            assert_eq!(config.launch.max_memory, Some(3072));
+            assert_eq!(config.launch.java_path, Some(PathBuf::from("/usr/lib/jvm/java-17-openjdk/bin/java")));
+
+            // The old G1 flags stay first and aren't repeated
+            let jvm_arguments = config.launch.jvm_arguments.clone().unwrap();
+            assert_eq!(&jvm_arguments[..DEFAULT_JVM_ARGUMENTS.len()], DEFAULT_JVM_ARGUMENTS);
+            assert_eq!(&jvm_arguments[DEFAULT_JVM_ARGUMENTS.len()..], ["-Dsun.java2d.opengl=true"]);
+
+            let vanilla = get_profile(&config, "b71c04d2-8e3f-4a6b-a1d9-5c0e7f2b9a44");
+            assert!(vanilla.launch.is_empty());
+            assert!(config.games[0].launch.is_empty());
+        }
+    }
+
+    #[test]
+    fn keeps_settings_added_since() {
+        assert_eq!(get_profile(&load_fixture(3), FABRIC_PROFILE).icon, None);
+        assert_eq!(get_profile(&load_fixture(4), FABRIC_PROFILE).icon.as_deref(), Some("Grass"));
+
+        assert!(!load_fixture(4).offline_mode);
+        assert!(load_fixture(5).offline_mode);
+
+        assert_eq!(load_fixture(5).downloads, DownloadSettings::default());
+        let downloads = load_fixture(8).downloads;
+        assert_eq!(downloads.max_concurrent_downloads, 8);
+        assert_eq!(downloads.max_connections_per_host, 4);
+        assert_eq!(downloads.max_bytes_per_second, None);
+
+        assert!(load_fixture(6).mirrors.is_empty());
+        assert_eq!(load_fixture(7).mirrors[0].urls, ["https://mirror.example.org/libraries/"]);
+
+        assert_eq!(load_fixture(7).proxy, ProxySettings::default());
+        let proxy = load_fixture(8).proxy;
+        assert_eq!(proxy.mode, ProxyMode::Manual);
+        assert_eq!(proxy.kind, ProxyKind::Socks5);
+        assert_eq!(proxy.no_proxy, ["localhost"]);
+    }
+
+    #[test]
+    fn current_schema_round_trips() {
+        let config = default_config();
+        let config_str = serde_json::to_string_pretty(&config).unwrap();
+
+        let (parsed, original_version) = parse_config(&config_str).unwrap();
+        assert_eq!(original_version, CONFIG_SCHEMA_VERSION);
+        assert_eq!(serde_json::to_string_pretty(&parsed).unwrap(), config_str);
+    }
+
+    #[test]
+    fn rejects_newer_schema() {
+        let mut value: Value = serde_json::from_str(FIXTURES[8]).unwrap();
+        value["schema_version"] = json!(CONFIG_SCHEMA_VERSION + 1);
+
+        let error = migrate_config(value).unwrap_err();
+        assert!(error.to_string().contains("newer than the supported version"));
     }
 }
@@ -0,0 +1,222 @@
+// XDG desktop entries for launching profiles straight from the applications menu
+
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use directories::ProjectDirs;
+use log::{info, warn, debug};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Profile;
+use crate::links::URL_SCHEME;
+use crate::utils::write_file_atomically;
+
+/// Icon used when a profile has no usable icon of its own
+const FALLBACK_ICON: &str = "applications-games";
+
+/// Desktop entry that handles `mosaic://` links
+const URL_HANDLER_ENTRY: &str = "mosaic-launcher-url-handler.desktop";
+
+/// Gets the user's applications directory (`$XDG_DATA_HOME/applications`)
+pub fn get_applications_dir() -> Result<PathBuf> {
+    let data_home = match std::env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        Some(data_home) => PathBuf::from(data_home),
+        None => {
+            let home = std::env::var_os("HOME")
+                .ok_or_else(|| anyhow!("Could not determine home directory"))?;
+            PathBuf::from(home).join(".local").join("share")
+        }
+    };
+
+    Ok(data_home.join("applications"))
+}
+
+/// Gets the path of the desktop entry for a profile
+pub fn get_desktop_entry_path(profile_id: &str) -> Result<PathBuf> {
+    Ok(get_applications_dir()?.join(format!("mosaic-launcher-{}.desktop", profile_id)))
+}
+
+/// Writes a desktop entry that launches the profile with `--launch <profile-id>`
+pub fn create_desktop_entry(profile: &Profile, game_name: &str) -> Result<PathBuf> {
+    let applications_dir = get_applications_dir()?;
+    fs::create_dir_all(&applications_dir)?;
+
+    let executable = std::env::current_exe()
+        .map_err(|e| anyhow!("Could not determine the launcher executable: {}", e))?;
+    let icon = resolve_icon(profile);
+
+    let entry = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Version=1.0\n\
+         Name={name}\n\
+         Comment={comment}\n\
+         Exec={exec} --launch {id}\n\
+         Icon={icon}\n\
+         Terminal=false\n\
+         Categories=Game;\n\
+         StartupNotify=true\n",
+        name = escape_value(&format!("{} ({})", profile.name, game_name)),
+        comment = escape_value(&format!("Launch the {} profile with Mosaic Launcher", profile.name)),
+        exec = quote_exec_arg(&executable.to_string_lossy()),
+        id = quote_exec_arg(&profile.id),
+        icon = escape_value(&icon),
+    );
+
+    let entry_path = get_desktop_entry_path(&profile.id)?;
+    write_file_atomically(&entry_path, entry.as_bytes())?;
+
+    // Desktop environments only trust executable entries
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&entry_path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    info!("Created desktop entry for profile {} at {:?}", profile.name, entry_path);
+    Ok(entry_path)
+}
+
+/// Removes a profile's desktop entry and icon, if they exist
+pub fn remove_desktop_entry(profile_id: &str) -> Result<()> {
+    let entry_path = get_desktop_entry_path(profile_id)?;
+    if entry_path.exists() {
+        fs::remove_file(&entry_path)?;
+        info!("Removed desktop entry {:?}", entry_path);
+    }
+
+    if let Some(icon_path) = get_icon_path(profile_id) {
+        if icon_path.exists() {
+            fs::remove_file(&icon_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Registers the launcher as the handler for `mosaic://` links
+///
+/// The entry is only rewritten (and the default handler only updated) when the
+/// executable path changed since the last registration.
+pub fn register_url_handler() -> Result<()> {
+    let applications_dir = get_applications_dir()?;
+    fs::create_dir_all(&applications_dir)?;
+
+    let executable = std::env::current_exe()
+        .map_err(|e| anyhow!("Could not determine the launcher executable: {}", e))?;
+
+    let entry = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Version=1.0\n\
+         Name=Mosaic Launcher\n\
+         Comment=Open {scheme}:// links in Mosaic Launcher\n\
+         Exec={exec} %u\n\
+         Icon={icon}\n\
+         Terminal=false\n\
+         NoDisplay=true\n\
+         MimeType=x-scheme-handler/{scheme};\n",
+        scheme = URL_SCHEME,
+        exec = quote_exec_arg(&executable.to_string_lossy()),
+        icon = FALLBACK_ICON,
+    );
+
+    let entry_path = applications_dir.join(URL_HANDLER_ENTRY);
+    if fs::read_to_string(&entry_path).map_or(false, |existing| existing == entry) {
+        debug!("URL handler is already registered at {:?}", entry_path);
+        return Ok(());
+    }
+
+    write_file_atomically(&entry_path, entry.as_bytes())?;
+    info!("Registered {}:// handler at {:?}", URL_SCHEME, entry_path);
+
+    // Make it the default handler, both tools are optional on most desktops
+    let mime_type = format!("x-scheme-handler/{}", URL_SCHEME);
+    match std::process::Command::new("xdg-mime").args(["default", URL_HANDLER_ENTRY, &mime_type]).status() {
+        Ok(status) if status.success() => {},
+        Ok(status) => warn!("xdg-mime exited with {}", status),
+        Err(e) => warn!("Failed to run xdg-mime: {}", e),
+    }
+    if let Err(e) = std::process::Command::new("update-desktop-database").arg(&applications_dir).status() {
+        debug!("Failed to run update-desktop-database: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Gets where a profile's decoded icon is stored
+fn get_icon_path(profile_id: &str) -> Option<PathBuf> {
+    ProjectDirs::from("com", "mosaic", "launcher")
+        .map(|dirs| dirs.data_dir().join("icons").join(format!("{}.png", profile_id)))
+}
+
+/// Turns the profile icon into something the `Icon` key understands
+///
+/// Data URIs (as stored by the official launcher) are decoded to a PNG file,
+/// existing image paths are used as-is and anything else falls back to a
+/// generic icon name.
+fn resolve_icon(profile: &Profile) -> String {
+    let icon = match &profile.icon {
+        Some(icon) => icon,
+        None => return FALLBACK_ICON.to_string(),
+    };
+
+    if let Some(data) = icon.strip_prefix("data:image/png;base64,") {
+        match write_icon(&profile.id, data) {
+            Ok(path) => return path.to_string_lossy().to_string(),
+            Err(e) => warn!("Failed to write icon for profile {}: {}", profile.name, e),
+        }
+    } else if Path::new(icon).is_file() {
+        return icon.clone();
+    } else {
+        debug!("Icon {} of profile {} is not a file, using the fallback icon", icon, profile.name);
+    }
+
+    FALLBACK_ICON.to_string()
+}
+
+/// Decodes a base64 PNG icon and stores it for the desktop entry
+fn write_icon(profile_id: &str, data: &str) -> Result<PathBuf> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim())?;
+    let icon_path = get_icon_path(profile_id)
+        .ok_or_else(|| anyhow!("Could not determine data directory"))?;
+
+    if let Some(parent) = icon_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_file_atomically(&icon_path, &bytes)?;
+
+    Ok(icon_path)
+}
+
+/// Escapes a value for a desktop entry string key
+fn escape_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+/// Quotes an argument of the `Exec` key as described by the desktop entry spec
+fn quote_exec_arg(arg: &str) -> String {
+    let needs_quoting = arg.is_empty() || arg.chars().any(|c| {
+        c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c)
+    });
+    if !needs_quoting {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+
+    // The string itself is also subject to the general escape rules
+    escape_value(&quoted)
+}
@@ -1,43 +1,525 @@
 use anyhow::{Result, anyhow};
-use reqwest::Client as HttpClient;
+use reqwest::{Client as HttpClient, Url};
 use sha1::{Sha1, Digest};
 use sha2::{Sha256, Digest as Sha2Digest};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use tokio::fs;
 use tokio::io::{AsyncWriteExt, AsyncReadExt};
-use futures::StreamExt;
+use tokio::sync::{Semaphore, Notify, Mutex as AsyncMutex};
+use futures::{stream, StreamExt};
+use std::future::Future;
 use zip::ZipArchive;
 use std::io::Cursor;
 use std::fs::File;
 use std::io::Read;
 use log::{info, warn, error, debug};
 
+use crate::config::{Config, DownloadSettings, Mirror, ProxySettings};
+use crate::network::{build_client, get_client};
+use crate::progress::ProgressTracker;
+
 #[derive(Debug, Clone)]
 pub struct DownloadProgress {
     pub url: String,
     pub file_name: String,
     pub total_size: Option<u64>,
     pub downloaded_size: u64,
+    /// Bytes of `downloaded_size` that were already on disk from an earlier attempt
+    pub resumed_from: u64,
     pub percentage: f32,
 }
 
+/// Result of a conditional download
+#[derive(Debug, Clone)]
+pub enum ConditionalResponse {
+    /// The server confirmed the cached copy is still current
+    NotModified,
+    /// The content changed, with the validators to send next time
+    Modified {
+        body: String,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+/// Error for work that stopped because it was cancelled
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Checks whether an error means the work was cancelled rather than failed
+pub fn is_cancelled_error(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Cancelled>().is_some()
+}
+
+/// Shared flag for stopping installs and launches
+///
+/// Clones share their state, so the UI keeps one and hands clones to the work it starts.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancellationState>,
+}
+
+#[derive(Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks all work holding this token to stop
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Returns a `Cancelled` error once the token is cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Cancelled.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Waits until the token is cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            // Register before checking so a cancel in between isn't missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// A file for a `DownloadQueue` to fetch
+#[derive(Debug, Clone)]
+pub struct DownloadJob {
+    pub url: String,
+    pub path: PathBuf,
+    pub sha1: Option<String>,
+    /// Size from the metadata, used for install progress before the download starts
+    pub size: Option<u64>,
+}
+
+/// A job that could not be downloaded
+#[derive(Debug)]
+pub struct DownloadFailure {
+    pub url: String,
+    pub path: PathBuf,
+    pub error: anyhow::Error,
+}
+
+/// Every failure of a `DownloadQueue` run
+#[derive(Debug)]
+pub struct DownloadBatchError {
+    pub failures: Vec<DownloadFailure>,
+}
+
+impl std::fmt::Display for DownloadBatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} downloads failed:", self.failures.len())?;
+        for failure in &self.failures {
+            write!(f, "\n  {} ({}): {}", failure.path.display(), failure.url, failure.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DownloadBatchError {}
+
+/// Combines the results of several queue runs, merging their failures into one error
+pub fn combine_download_results(results: Vec<Result<()>>) -> Result<()> {
+    let mut failures = Vec::new();
+
+    for result in results {
+        if let Err(e) = result {
+            match e.downcast::<DownloadBatchError>() {
+                Ok(batch) => failures.extend(batch.failures),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(DownloadBatchError { failures }.into())
+    }
+}
+
+/// Locks for the paths being downloaded right now, shared by every queue in the process
+///
+/// Two profiles installing at once both want the same libraries and assets; the
+/// second one waits for the first and then finds the file already in place.
+fn in_flight_downloads() -> &'static Mutex<HashMap<PathBuf, Arc<AsyncMutex<()>>>> {
+    static IN_FLIGHT: OnceLock<Mutex<HashMap<PathBuf, Arc<AsyncMutex<()>>>>> = OnceLock::new();
+    IN_FLIGHT.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// How often a throttled download checks whether the limit was lifted
+const THROTTLE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Token bucket shared by every download in the process
+///
+/// Each received chunk takes its size in tokens; the bucket refills at the
+/// configured rate and holds at most one second of data, so concurrent
+/// downloads together stay under the limit.
+struct BandwidthLimiter {
+    bucket: Mutex<TokenBucket>,
+    /// Ignores the limit until switched off again, e.g. while the user waits on an install
+    full_speed: AtomicBool,
+}
+
+struct TokenBucket {
+    bytes_per_second: Option<u64>,
+    /// Goes negative when chunks arrive faster than the rate, later chunks wait it out
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl BandwidthLimiter {
+    fn is_limited(&self) -> bool {
+        !self.full_speed.load(Ordering::SeqCst) && self.bucket.lock().unwrap().bytes_per_second.is_some()
+    }
+
+    /// Takes tokens for `bytes` and waits until the bucket is no longer in debt
+    async fn acquire(&self, bytes: usize) {
+        let mut wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let rate = match bucket.bytes_per_second {
+                Some(rate) if !self.full_speed.load(Ordering::SeqCst) => rate.max(1) as f64,
+                _ => return,
+            };
+
+            let now = Instant::now();
+            let refilled = bucket.tokens + now.duration_since(bucket.last_refill).as_secs_f64() * rate;
+            bucket.tokens = refilled.min(rate) - bytes as f64;
+            bucket.last_refill = now;
+
+            if bucket.tokens >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        };
+
+        // Sleep in steps so switching to full speed takes effect right away
+        while !wait.is_zero() && self.is_limited() {
+            let step = wait.min(THROTTLE_CHECK_INTERVAL);
+            tokio::time::sleep(step).await;
+            wait -= step;
+        }
+    }
+}
+
+fn bandwidth_limiter() -> &'static BandwidthLimiter {
+    static LIMITER: OnceLock<BandwidthLimiter> = OnceLock::new();
+    LIMITER.get_or_init(|| BandwidthLimiter {
+        bucket: Mutex::new(TokenBucket {
+            bytes_per_second: None,
+            tokens: 0.0,
+            last_refill: Instant::now(),
+        }),
+        full_speed: AtomicBool::new(false),
+    })
+}
+
+/// Limits the combined speed of all downloads, `None` removes the limit
+pub fn set_bandwidth_limit(bytes_per_second: Option<u64>) {
+    let mut bucket = bandwidth_limiter().bucket.lock().unwrap();
+    if bucket.bytes_per_second != bytes_per_second {
+        info!("Download speed limit set to {:?} bytes per second", bytes_per_second);
+        bucket.bytes_per_second = bytes_per_second;
+        bucket.tokens = 0.0;
+        bucket.last_refill = Instant::now();
+    }
+}
+
+/// Temporarily ignores the download speed limit, without changing it
+pub fn set_full_speed(enabled: bool) {
+    let limiter = bandwidth_limiter();
+    if limiter.full_speed.swap(enabled, Ordering::SeqCst) && !enabled {
+        // Start from an empty bucket rather than the debt from before the override
+        let mut bucket = limiter.bucket.lock().unwrap();
+        bucket.tokens = 0.0;
+        bucket.last_refill = Instant::now();
+    }
+}
+
+/// Downloads many files at once, bounded overall and per host
+pub struct DownloadQueue {
+    file_manager: FileManager,
+    jobs: Vec<DownloadJob>,
+    paths: HashSet<PathBuf>,
+}
+
+impl DownloadQueue {
+    /// Creates an empty queue using the file manager's download limits
+    pub fn new(file_manager: &FileManager) -> Self {
+        Self {
+            file_manager: file_manager.clone(),
+            jobs: Vec::new(),
+            paths: HashSet::new(),
+        }
+    }
+
+    /// Adds a job, returns false if the same path is already queued
+    pub fn add(&mut self, job: DownloadJob) -> bool {
+        if !self.paths.insert(job.path.clone()) {
+            debug!("Skipping duplicate download of {:?}", job.path);
+            return false;
+        }
+
+        self.jobs.push(job);
+        true
+    }
+
+    /// Number of queued jobs
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Returns true if nothing is queued
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Adds the queued jobs to a tracker's totals ahead of running them
+    pub fn register(&self, tracker: &ProgressTracker) {
+        for job in &self.jobs {
+            tracker.add_file(&job.url, job.size);
+        }
+    }
+
+    /// Downloads every queued job
+    ///
+    /// All jobs are attempted even when some fail; the error is a
+    /// `DownloadBatchError` listing each failed file.
+    pub async fn run(self, tracker: &ProgressTracker) -> Result<()> {
+        self.register(tracker);
+
+        let DownloadQueue { file_manager, jobs, .. } = self;
+        let total = jobs.len();
+        if total == 0 {
+            return Ok(());
+        }
+
+        let settings = &file_manager.download_settings;
+        let max_concurrent = settings.max_concurrent_downloads.max(1);
+        let max_per_host = settings.max_connections_per_host.max(1);
+        info!("Downloading {} files ({} at a time, {} per host)", total, max_concurrent, max_per_host);
+
+        let mut host_limits: HashMap<String, Arc<Semaphore>> = HashMap::new();
+        for job in &jobs {
+            host_limits.entry(get_host(&job.url))
+                .or_insert_with(|| Arc::new(Semaphore::new(max_per_host)));
+        }
+
+        let file_manager = &file_manager;
+        let host_limits = &host_limits;
+        let failures: Vec<DownloadFailure> = stream::iter(jobs)
+            .map(|job| {
+                async move {
+                    let host_limit = host_limits[&get_host(&job.url)].clone();
+                    let result = match host_limit.acquire_owned().await {
+                        Ok(_permit) => file_manager.download_job(&job, tracker.download_callback()).await,
+                        Err(e) => Err(anyhow!("Download queue was closed: {}", e)),
+                    };
+                    tracker.finish_file(&job.url);
+
+                    result.err().filter(|error| !is_cancelled_error(error)).map(|error| {
+                        error!("Failed to download {}: {}", job.url, error);
+                        DownloadFailure { url: job.url, path: job.path, error }
+                    })
+                }
+            })
+            .buffer_unordered(max_concurrent)
+            .filter_map(|failure| async move { failure })
+            .collect()
+            .await;
+
+        if file_manager.cancellation.is_cancelled() {
+            info!("Download of {} files was cancelled", total);
+            Err(Cancelled.into())
+        } else if failures.is_empty() {
+            info!("Downloaded {} files", total);
+            Ok(())
+        } else {
+            error!("{} of {} downloads failed", failures.len(), total);
+            Err(DownloadBatchError { failures }.into())
+        }
+    }
+}
+
+/// Gets the URLs to try for `url` in order, using the mirror with the longest matching prefix
+pub fn get_mirror_urls(mirrors: &[Mirror], url: &str) -> Vec<String> {
+    let mirror = mirrors.iter()
+        .filter(|mirror| !mirror.prefix.is_empty() && url.starts_with(&mirror.prefix))
+        .max_by_key(|mirror| mirror.prefix.len());
+
+    match mirror {
+        Some(mirror) => {
+            let rest = &url[mirror.prefix.len()..];
+            let mut urls: Vec<String> = mirror.urls.iter()
+                .map(|base| format!("{}{}", base, rest))
+                .collect();
+            if mirror.fallback_to_original || urls.is_empty() {
+                urls.push(url.to_string());
+            }
+            urls
+        },
+        None => vec![url.to_string()],
+    }
+}
+
+/// Gets the `.part` file a download is written to before it is complete
+fn get_part_path(path: &Path) -> PathBuf {
+    let mut part_name = path.file_name().unwrap_or_default().to_os_string();
+    part_name.push(".part");
+    path.with_file_name(part_name)
+}
+
+/// Gets the first byte of a `Content-Range: bytes <start>-<end>/<total>` response
+fn get_content_range_start(response: &reqwest::Response) -> Option<u64> {
+    let value = response.headers().get(reqwest::header::CONTENT_RANGE)?.to_str().ok()?;
+    let range = value.trim().strip_prefix("bytes")?.trim_start();
+    range.split('-').next()?.trim().parse().ok()
+}
+
+/// Gets the host a URL connects to, used to group connections
+fn get_host(url: &str) -> String {
+    Url::parse(url).ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .unwrap_or_default()
+}
+
 #[derive(Clone)]
 pub struct FileManager {
-    http_client: HttpClient,
+    /// The error instead if the proxy settings are invalid
+    http_client: std::result::Result<HttpClient, String>,
+    download_settings: DownloadSettings,
+    mirrors: Vec<Mirror>,
+    cancellation: CancellationToken,
 }
 
 impl FileManager {
     pub fn new() -> Self {
         Self {
-            http_client: HttpClient::new(),
+            http_client: build_client(&ProxySettings::default()).map_err(|e| e.to_string()),
+            download_settings: DownloadSettings::default(),
+            mirrors: Vec::new(),
+            cancellation: CancellationToken::new(),
         }
     }
 
+    /// Applies the download limits, mirrors and proxy from the config
+    ///
+    /// The speed limit is shared by all file managers, so it applies process-wide.
+    pub fn configure(&mut self, config: &Config) {
+        self.http_client = build_client(&config.proxy).map_err(|e| {
+            warn!("Invalid proxy settings, downloads will fail: {}", e);
+            e.to_string()
+        });
+        set_bandwidth_limit(config.downloads.max_bytes_per_second);
+        self.download_settings = config.downloads.clone();
+        self.mirrors = config.mirrors.clone();
+    }
+
+    /// Gets a file manager whose downloads stop when `cancellation` is cancelled
+    ///
+    /// A cancelled download removes its partial file and fails with `Cancelled`.
+    pub fn with_cancellation(&self, cancellation: &CancellationToken) -> FileManager {
+        let mut file_manager = self.clone();
+        file_manager.cancellation = cancellation.clone();
+        file_manager
+    }
+
+    /// Downloads a queued job, waiting for any other download of the same path
+    async fn download_job(
+        &self,
+        job: &DownloadJob,
+        progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static + Clone,
+    ) -> Result<()> {
+        self.cancellation.check()?;
+
+        let lock = in_flight_downloads().lock().unwrap()
+            .entry(job.path.clone())
+            .or_default()
+            .clone();
+
+        let result = tokio::select! {
+            _guard = lock.lock() => {
+                self.download_file(&job.url, &job.path, job.sha1.as_deref(), progress_callback).await
+            },
+            _ = self.cancellation.cancelled() => Err(Cancelled.into()),
+        };
+
+        // Forget the lock once nobody else is waiting on it
+        let mut in_flight = in_flight_downloads().lock().unwrap();
+        if Arc::strong_count(&lock) <= 2 {
+            in_flight.remove(&job.path);
+        }
+
+        result
+    }
+
+    /// Runs `request` for each mirror URL of `url` until one succeeds
+    async fn with_mirrors<T, F, Fut>(&self, url: &str, request: F) -> Result<T>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut last_error = None;
+
+        for candidate in get_mirror_urls(&self.mirrors, url) {
+            match request(candidate.clone()).await {
+                Ok(value) => return Ok(value),
+                Err(e) if is_cancelled_error(&e) => return Err(e),
+                Err(e) => {
+                    if candidate != url {
+                        warn!("Mirror {} failed for {}: {}", candidate, url, e);
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("No URL to download {} from", url)))
+    }
+
     /// Downloads a string from a URL
     pub async fn download_string(&self, url: &str) -> Result<String> {
+        self.with_mirrors(url, |candidate| async move {
+            self.download_string_from(&candidate).await
+        }).await
+    }
+
+    async fn download_string_from(&self, url: &str) -> Result<String> {
         info!("Downloading string from {}", url);
 
-        let response = self.http_client.get(url)
+        let response = get_client(&self.http_client)?.get(url)
             .send()
             .await?
             .error_for_status()?;
@@ -47,6 +529,54 @@ impl FileManager {
         Ok(text)
     }
 
+    /// Downloads a string from a URL unless it is unchanged since the given validators
+    pub async fn download_string_if_modified(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<ConditionalResponse> {
+        self.with_mirrors(url, |candidate| async move {
+            self.download_string_if_modified_from(&candidate, etag, last_modified).await
+        }).await
+    }
+
+    async fn download_string_if_modified_from(
+        &self,
+        url: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> Result<ConditionalResponse> {
+        info!("Revalidating {}", url);
+
+        let mut request = get_client(&self.http_client)?.get(url);
+        if let Some(etag) = etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let response = request.send().await?;
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            debug!("{} is not modified", url);
+            return Ok(ConditionalResponse::NotModified);
+        }
+
+        let response = response.error_for_status()?;
+        let header = |name: reqwest::header::HeaderName| {
+            response.headers().get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let etag = header(reqwest::header::ETAG);
+        let last_modified = header(reqwest::header::LAST_MODIFIED);
+
+        let body = response.text().await?;
+
+        Ok(ConditionalResponse::Modified { body, etag, last_modified })
+    }
+
     pub async fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         fs::create_dir_all(path).await?;
         Ok(())
@@ -76,16 +606,42 @@ impl FileManager {
             }
         }
 
+        // Mirrors report progress under the original URL
+        self.with_mirrors(url, |candidate| {
+            let progress_callback = progress_callback.clone();
+            let report_url = url.to_string();
+            async move {
+                self.download_file_with_retries(&candidate, path, expected_hash, move |mut progress: DownloadProgress| {
+                    progress.url = report_url.clone();
+                    progress_callback(progress);
+                }).await
+            }
+        }).await
+    }
+
+    /// Downloads a file from one URL, retrying a few times
+    async fn download_file_with_retries(
+        &self,
+        url: &str,
+        path: &Path,
+        expected_hash: Option<&str>,
+        progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static + Clone,
+    ) -> Result<()> {
         // Maximum number of retry attempts
         const MAX_RETRIES: usize = 3;
         let mut retry_count = 0;
         let mut last_error = None;
 
         while retry_count < MAX_RETRIES {
+            self.cancellation.check()?;
+
             if retry_count > 0 {
                 info!("Retry attempt {} of {} for downloading {}", retry_count, MAX_RETRIES, url);
                 // Wait a bit before retrying
-                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(tokio::time::Duration::from_secs(2)) => {},
+                    _ = self.cancellation.cancelled() => return Err(Cancelled.into()),
+                }
             }
 
             // Start the download
@@ -96,6 +652,10 @@ impl FileManager {
                     info!("Download completed: {:?}", path);
                     return Ok(());
                 },
+                Err(e) if is_cancelled_error(&e) => {
+                    info!("Download of {} was cancelled", url);
+                    return Err(e);
+                },
                 Err(e) => {
                     warn!("Download attempt {} failed: {}", retry_count + 1, e);
                     last_error = Some(e);
@@ -116,36 +676,82 @@ impl FileManager {
         progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static,
     ) -> Result<()> {
         let path = path.as_ref();
+        let part_path = get_part_path(path);
 
-        // If the file exists from a previous attempt, remove it
-        if path.exists() {
-            fs::remove_file(path).await?;
+        // Continue from whatever an earlier attempt left behind
+        let resume_from = match fs::metadata(&part_path).await {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+
+        let mut request = get_client(&self.http_client)?.get(url);
+        if resume_from > 0 {
+            info!("Resuming download of {} from byte {}", url, resume_from);
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", resume_from));
         }
+        let response = tokio::select! {
+            response = request.send() => response?,
+            _ = self.cancellation.cancelled() => return Err(Cancelled.into()),
+        };
 
-        // Start the download
-        let response = self.http_client.get(url).send().await?;
+        // The partial file is stale or already complete, start over on the next attempt
+        if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            fs::remove_file(&part_path).await?;
+            return Err(anyhow!("Server rejected resuming {} at byte {}", url, resume_from));
+        }
 
         // Check if the response is successful
         if !response.status().is_success() {
             return Err(anyhow!("Server returned error status: {}", response.status()));
         }
 
-        let total_size = response.content_length();
+        // Servers without range support send the whole file again
+        let resumed = resume_from > 0
+            && response.status() == reqwest::StatusCode::PARTIAL_CONTENT
+            && get_content_range_start(&response) == Some(resume_from);
+        if resume_from > 0 && !resumed {
+            debug!("Server did not resume {}, downloading it from the start", url);
+        }
+        let resumed_from = if resumed { resume_from } else { 0 };
+        let mut downloaded_size = resumed_from;
+
+        let total_size = response.content_length().map(|length| length + downloaded_size);
 
         let file_name = path.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("unknown")
             .to_string();
 
-        // Create the file
-        let mut file = fs::File::create(path).await?;
+        // Append to the partial file, or replace it
+        let mut file = if resumed {
+            fs::OpenOptions::new().append(true).open(&part_path).await?
+        } else {
+            fs::File::create(&part_path).await?
+        };
 
         // Download the file in chunks and report progress
         let mut stream = response.bytes_stream();
-        let mut downloaded_size: u64 = 0;
 
-        while let Some(chunk_result) = stream.next().await {
-            let chunk = chunk_result?;
+        loop {
+            let chunk = tokio::select! {
+                chunk = async {
+                    let chunk = stream.next().await;
+                    // Hold the chunk back while all downloads together are over the speed limit
+                    if let Some(Ok(chunk)) = &chunk {
+                        bandwidth_limiter().acquire(chunk.len()).await;
+                    }
+                    chunk
+                } => match chunk {
+                    Some(chunk) => chunk?,
+                    None => break,
+                },
+                _ = self.cancellation.cancelled() => {
+                    // Don't leave half a file behind for a cancelled install
+                    drop(file);
+                    let _ = fs::remove_file(&part_path).await;
+                    return Err(Cancelled.into());
+                },
+            };
             file.write_all(&chunk).await?;
 
             downloaded_size += chunk.len() as u64;
@@ -160,32 +766,38 @@ impl FileManager {
                 file_name: file_name.clone(),
                 total_size,
                 downloaded_size,
+                resumed_from,
                 percentage,
             });
         }
 
         // Flush the file to ensure all data is written to disk
         file.flush().await?;
+        drop(file);
 
-        // Verify the file size if total_size is known
+        // Verify the file size if total_size is known, a short file is kept to resume
         if let Some(expected_size) = total_size {
-            let metadata = fs::metadata(path).await?;
-            let actual_size = metadata.len();
+            let actual_size = fs::metadata(&part_path).await?.len();
 
+            if actual_size > expected_size {
+                fs::remove_file(&part_path).await?;
+            }
             if actual_size != expected_size {
-                fs::remove_file(path).await?;
                 return Err(anyhow!("File size mismatch: expected {} bytes, got {} bytes", expected_size, actual_size));
             }
         }
 
         // Verify hash if provided
         if let Some(hash) = expected_hash {
-            if !self.verify_file_hash(path, hash).await? {
-                fs::remove_file(path).await?;
+            if !self.verify_file_hash(&part_path, hash).await? {
+                fs::remove_file(&part_path).await?;
                 return Err(anyhow!("Hash verification failed for file: {:?}", path));
             }
         }
 
+        // Only a complete, verified file ever appears at the final path
+        fs::rename(&part_path, path).await?;
+
         Ok(())
     }
 
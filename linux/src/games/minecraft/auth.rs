@@ -6,10 +6,15 @@ use oauth2::{
 };
 use reqwest::blocking::Client as HttpClient;
 use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use log::{info, error, debug, warn};
 use uuid::Uuid;
 
+use crate::config::ProxySettings;
+use crate::network::{build_blocking_client, build_oauth_client, get_client, send_oauth_request};
+
 const MS_CLIENT_ID: &str = "2533faf1-dfcc-4f08-9520-83d421e91426";
 const MS_AUTH_URL: &str = "https://login.microsoftonline.com/common/oauth2/v2.0/authorize";
 const MS_TOKEN_URL: &str = "https://login.microsoftonline.com/common/oauth2/v2.0/token";
@@ -20,6 +25,9 @@ const MC_LOGIN_URL: &str = "https://api.minecraftservices.com/authentication/log
 const MC_PROFILE_URL: &str = "https://api.minecraftservices.com/minecraft/profile";
 const MC_OWNERSHIP_URL: &str = "https://api.minecraftservices.com/entitlements/mcstore";
 
+// Minecraft access tokens are valid for 24 hours
+const MC_TOKEN_LIFETIME_SECS: u64 = 24 * 60 * 60;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MinecraftProfile {
     pub id: String,
@@ -52,15 +60,36 @@ pub struct AuthSession {
     pub minecraft_token: Option<String>,
     pub minecraft_profile: Option<MinecraftProfile>,
     pub is_offline: bool,
+    #[serde(default)]
+    pub minecraft_token_expires_at: Option<u64>,
+}
+
+impl AuthSession {
+    /// Returns true if the session can still be used to launch the game
+    pub fn is_usable(&self) -> bool {
+        if self.is_offline {
+            return true;
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        self.minecraft_token.is_some()
+            && self.minecraft_token_expires_at.map(|expires_at| expires_at > now).unwrap_or(false)
+    }
 }
 
 pub struct AuthManager {
-    http_client: HttpClient,
+    // Hold the proxy error instead if the settings are invalid
+    http_client: std::result::Result<HttpClient, String>,
+    oauth_http_client: std::result::Result<HttpClient, String>, // Doesn't follow redirects, as oauth2 requires
     oauth_client: BasicClient,
 }
 
 impl AuthManager {
-    pub fn new() -> Self {
+    pub fn new(proxy: &ProxySettings) -> Self {
         let oauth_client = BasicClient::new(
             ClientId::new(MS_CLIENT_ID.to_string()),
             None,
@@ -70,7 +99,8 @@ impl AuthManager {
             .set_device_authorization_url(DeviceAuthorizationUrl::new(MS_DEVICE_CODE_URL.to_string()).unwrap());
 
         Self {
-            http_client: HttpClient::new(),
+            http_client: build_blocking_client(proxy).map_err(|e| e.to_string()),
+            oauth_http_client: build_oauth_client(proxy).map_err(|e| e.to_string()),
             oauth_client,
         }
     }
@@ -81,11 +111,12 @@ impl AuthManager {
             "XboxLive.offline_access".to_string(),
         ];
 
+        let oauth_http_client = get_client(&self.oauth_http_client)?;
         let device_auth = self.oauth_client
             .exchange_device_code()
             .map_err(|e| anyhow!("Failed to create device code request: {}", e))?
             .add_scopes(scopes.iter().map(|s| Scope::new(s.clone())))
-            .request(oauth2::reqwest::http_client)?;
+            .request(|request| send_oauth_request(oauth_http_client, request))?;
 
         Ok(device_auth)
     }
@@ -94,6 +125,7 @@ impl AuthManager {
         &self,
         device_auth: DeviceAuthorizationResponse<EmptyExtraDeviceAuthorizationFields>,
     ) -> Result<CustomTokenResponse> {
+        let oauth_http_client = get_client(&self.oauth_http_client)?;
         let start_time = Instant::now();
         let expires_in = device_auth.expires_in();
 
@@ -105,7 +137,7 @@ impl AuthManager {
             match self.oauth_client
                 .exchange_device_access_token(&device_auth)
                 .request(
-                    oauth2::reqwest::http_client,
+                    |request| send_oauth_request(oauth_http_client, request),
                     std::thread::sleep,
                     None
                 )
@@ -164,7 +196,7 @@ impl AuthManager {
             TokenType: "JWT".to_string(),
         };
 
-        let response = self.http_client
+        let response = get_client(&self.http_client)?
             .post(MC_XBOX_AUTH_URL)
             .json(&request)
             .send()?
@@ -210,7 +242,7 @@ impl AuthManager {
             TokenType: "JWT".to_string(),
         };
 
-        let response = self.http_client
+        let response = get_client(&self.http_client)?
             .post(MC_XSTS_AUTH_URL)
             .json(&request)
             .send()?
@@ -241,7 +273,7 @@ impl AuthManager {
             identityToken: identity_token,
         };
 
-        let response = self.http_client
+        let response = get_client(&self.http_client)?
             .post(MC_LOGIN_URL)
             .json(&request)
             .send()?
@@ -261,7 +293,7 @@ impl AuthManager {
             name: String,
         }
 
-        let response = self.http_client
+        let response = get_client(&self.http_client)?
             .get(MC_OWNERSHIP_URL)
             .header("Authorization", format!("Bearer {}", minecraft_token))
             .send()?
@@ -276,7 +308,7 @@ impl AuthManager {
     }
 
     pub fn get_minecraft_profile(&self, minecraft_token: &str) -> Result<MinecraftProfile> {
-        let response = self.http_client
+        let response = get_client(&self.http_client)?
             .get(MC_PROFILE_URL)
             .header("Authorization", format!("Bearer {}", minecraft_token))
             .send()?
@@ -305,6 +337,11 @@ impl AuthManager {
         // Step 6: Get Minecraft profile
         let minecraft_profile = self.get_minecraft_profile(&minecraft_token)?;
 
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
         // Create and return the auth session
         Ok(AuthSession {
             access_token: token_response.access_token,
@@ -313,9 +350,63 @@ impl AuthManager {
             minecraft_token: Some(minecraft_token),
             minecraft_profile: Some(minecraft_profile),
             is_offline: false,
+            minecraft_token_expires_at: Some(now + MC_TOKEN_LIFETIME_SECS),
         })
     }
 
+    /// Gets the path of the stored auth session
+    fn get_session_file() -> Result<PathBuf> {
+        Ok(crate::config::get_config_dir()?.join("session.json"))
+    }
+
+    /// Stores the session so later launches can skip the login view
+    pub fn save_session(&self, session: &AuthSession) -> Result<()> {
+        let session_file = Self::get_session_file()?;
+        let session_str = serde_json::to_string_pretty(session)?;
+        // The file holds access tokens, keep it private from the start
+        crate::utils::write_file_atomically_with_mode(&session_file, session_str.as_bytes(), 0o600)?;
+
+        info!("Saved auth session to {:?}", session_file);
+        Ok(())
+    }
+
+    /// Loads the stored session if there is one and it is still usable
+    pub fn load_session(&self) -> Option<AuthSession> {
+        let session_file = Self::get_session_file().ok()?;
+        if !session_file.exists() {
+            return None;
+        }
+
+        let session: AuthSession = match fs::read_to_string(&session_file)
+            .map_err(anyhow::Error::from)
+            .and_then(|s| serde_json::from_str(&s).map_err(anyhow::Error::from))
+        {
+            Ok(session) => session,
+            Err(e) => {
+                warn!("Failed to read stored auth session: {}", e);
+                return None;
+            }
+        };
+
+        if !session.is_usable() {
+            info!("Stored auth session has expired");
+            return None;
+        }
+
+        debug!("Loaded stored auth session");
+        Some(session)
+    }
+
+    /// Removes the stored session
+    pub fn clear_session(&self) -> Result<()> {
+        let session_file = Self::get_session_file()?;
+        if session_file.exists() {
+            fs::remove_file(&session_file)?;
+            info!("Removed stored auth session");
+        }
+        Ok(())
+    }
+
     // Create an offline session with a custom username
     pub fn create_offline_session(&self, username: &str) -> Result<AuthSession> {
         info!("Creating offline session for username: {}", username);
@@ -345,6 +436,7 @@ impl AuthManager {
             minecraft_token: Some("offline".to_string()),
             minecraft_profile: Some(minecraft_profile),
             is_offline: true,
+            minecraft_token_expires_at: None,
         })
     }
 }
@@ -0,0 +1,339 @@
+// Removal of versions, libraries and assets no profile uses anymore
+//
+// A file is in use while a version some profile launches, or one it inherits
+// from, names it. Forge and NeoForge also use jars their installer generated,
+// found from the installer's processors and the `--fml.*` arguments.
+// Everything else under versions/, libraries/ and the asset objects and
+// indexes can go. Partial downloads are left alone since an install may be
+// writing them right now.
+
+use anyhow::Result;
+use log::{info, warn, debug};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::modloaders;
+use super::versions;
+
+/// A version directory or file that nothing uses
+#[derive(Debug, Clone)]
+pub struct UnusedEntry {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// What a cleanup removes, found without changing anything
+#[derive(Debug, Clone, Default)]
+pub struct CleanupPlan {
+    /// Version directories, removed as a whole
+    pub versions: Vec<UnusedEntry>,
+    pub libraries: Vec<UnusedEntry>,
+    /// Asset objects and indexes
+    pub assets: Vec<UnusedEntry>,
+}
+
+impl CleanupPlan {
+    /// Lists every entry, versions first
+    pub fn entries(&self) -> impl Iterator<Item = &UnusedEntry> {
+        self.versions.iter().chain(&self.libraries).chain(&self.assets)
+    }
+
+    /// Gets the space the cleanup frees
+    pub fn total_size(&self) -> u64 {
+        self.entries().map(|entry| entry.size).sum()
+    }
+
+    /// Returns true if there is nothing to remove
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty() && self.libraries.is_empty() && self.assets.is_empty()
+    }
+}
+
+/// Finds what none of `version_ids` use in a Minecraft directory
+///
+/// Fails if the JSON or asset index of a version in use can't be read,
+/// rather than risk removing files it needs.
+pub fn find_unused_files(minecraft_dir: &Path, version_ids: &[String]) -> Result<CleanupPlan> {
+    let mut used_versions = HashSet::new();
+    let mut used_files = HashSet::new();
+    let mut used_dirs = HashSet::new();
+
+    for version_id in version_ids {
+        used_versions.insert(version_id.clone());
+
+        for version_details in versions::read_version_chain(minecraft_dir, version_id)? {
+            used_versions.insert(version_details.id.clone());
+            used_versions.insert(version_details.get_jar_id().to_string());
+            used_files.extend(versions::get_version_files(minecraft_dir, &version_details)?);
+            used_dirs.extend(versions::get_generated_library_dirs(minecraft_dir, &version_details));
+        }
+    }
+    used_files.extend(get_installer_outputs(minecraft_dir, &used_versions));
+    debug!(
+        "{} versions, {} files and {} library directories are in use",
+        used_versions.len(), used_files.len(), used_dirs.len()
+    );
+
+    let mut plan = CleanupPlan::default();
+
+    // Version directories go as a whole, with their jar and natives
+    if let Ok(entries) = fs::read_dir(minecraft_dir.join("versions")) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let used = entry.file_name().to_str().map_or(true, |name| used_versions.contains(name));
+            if used || !path.is_dir() {
+                continue;
+            }
+
+            let (size, downloading) = get_dir_size(&path);
+            if downloading {
+                debug!("Keeping {:?}, it is being downloaded", path);
+                continue;
+            }
+            plan.versions.push(UnusedEntry { path, size });
+        }
+    }
+
+    let no_dirs = HashSet::new();
+    collect_unused(&minecraft_dir.join("libraries"), &used_files, &used_dirs, &mut plan.libraries);
+    collect_unused(&minecraft_dir.join("assets").join("objects"), &used_files, &no_dirs, &mut plan.assets);
+    collect_unused(&minecraft_dir.join("assets").join("indexes"), &used_files, &no_dirs, &mut plan.assets);
+
+    info!(
+        "Found {} unused versions, {} libraries and {} assets ({} bytes)",
+        plan.versions.len(), plan.libraries.len(), plan.assets.len(), plan.total_size()
+    );
+    Ok(plan)
+}
+
+/// Removes everything in a plan and the directories that end up empty
+///
+/// Entries that can't be removed are skipped with a warning. Returns the space freed.
+pub fn remove_unused_files(minecraft_dir: &Path, plan: &CleanupPlan) -> u64 {
+    let mut freed = 0;
+
+    for entry in plan.entries() {
+        let result = if entry.path.is_dir() {
+            fs::remove_dir_all(&entry.path)
+        } else {
+            fs::remove_file(&entry.path)
+        };
+
+        match result {
+            Ok(()) => freed += entry.size,
+            Err(e) => warn!("Failed to remove {:?}: {}", entry.path, e),
+        }
+    }
+
+    remove_empty_dirs(&minecraft_dir.join("libraries"));
+    remove_empty_dirs(&minecraft_dir.join("assets").join("objects"));
+
+    info!("Cleanup freed {} bytes", freed);
+    freed
+}
+
+/// Lists the library files the processors of the installers for `used_versions` wrote
+///
+/// Only installers still in the Minecraft directory can be read; the
+/// `--fml.*` arguments cover the usual outputs of the others.
+fn get_installer_outputs(minecraft_dir: &Path, used_versions: &HashSet<String>) -> Vec<PathBuf> {
+    let mut outputs = Vec::new();
+
+    for installers_dir in ["forge", "neoforge"] {
+        let entries = match fs::read_dir(minecraft_dir.join(installers_dir)) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "jar") {
+                continue;
+            }
+
+            match modloaders::read_installer_outputs(&path) {
+                Ok((version_id, paths)) if used_versions.contains(&version_id) => {
+                    outputs.extend(paths.iter().map(|path| minecraft_dir.join("libraries").join(path)));
+                },
+                Ok(_) => {},
+                Err(e) => debug!("Can't read the processors of {:?}: {}", path, e),
+            }
+        }
+    }
+
+    outputs
+}
+
+/// Adds the files below `dir` that aren't in `used_files` or `used_dirs` to `unused`
+fn collect_unused(dir: &Path, used_files: &HashSet<PathBuf>, used_dirs: &HashSet<PathBuf>, unused: &mut Vec<UnusedEntry>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+
+        if metadata.is_dir() {
+            if !used_dirs.contains(&path) {
+                collect_unused(&path, used_files, used_dirs, unused);
+            }
+        } else if !is_part_file(&path) && !used_files.contains(&path) {
+            unused.push(UnusedEntry { path, size: metadata.len() });
+        }
+    }
+}
+
+/// Gets the size of a directory, and whether a download is writing to it
+fn get_dir_size(dir: &Path) -> (u64, bool) {
+    let mut size = 0;
+    let mut downloading = false;
+
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => {
+                    let (dir_size, dir_downloading) = get_dir_size(&path);
+                    size += dir_size;
+                    downloading |= dir_downloading;
+                },
+                Ok(metadata) => {
+                    size += metadata.len();
+                    downloading |= is_part_file(&path);
+                },
+                Err(_) => {},
+            }
+        }
+    }
+
+    (size, downloading)
+}
+
+/// Removes the empty directories below `dir`, returns true if `dir` itself is empty afterwards
+fn remove_empty_dirs(dir: &Path) -> bool {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+
+    let mut empty = true;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() && remove_empty_dirs(&path) {
+            if fs::remove_dir(&path).is_ok() {
+                continue;
+            }
+        }
+        empty = false;
+    }
+
+    empty
+}
+
+/// Checks for the `.part` file of a download that isn't finished
+fn is_part_file(path: &Path) -> bool {
+    path.extension().map_or(false, |extension| extension == "part")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // Trimmed from the 1.20.1-forge-47.2.0 launch JSON
+    const FORGE_JSON: &str = r#"{
+        "id": "1.20.1-forge-47.2.0",
+        "inheritsFrom": "1.20.1",
+        "mainClass": "cpw.mods.bootstraplauncher.BootstrapLauncher",
+        "arguments": {
+            "game": ["--launchTarget", "forgeclient", "--fml.forgeVersion", "47.2.0", "--fml.mcVersion", "1.20.1",
+                     "--fml.forgeGroup", "net.minecraftforge", "--fml.mcpVersion", "20230612.114412"],
+            "jvm": ["-DlibraryDirectory=${library_directory}",
+                    "-p", "${library_directory}/cpw/mods/bootstraplauncher/1.1.2/bootstraplauncher-1.1.2.jar${classpath_separator}${library_directory}/cpw/mods/securejarhandler/2.1.10/securejarhandler-2.1.10.jar",
+                    "--add-modules", "ALL-MODULE-PATH"]
+        },
+        "libraries": [{
+            "name": "net.minecraftforge:fmlloader:1.20.1-47.2.0",
+            "downloads": {"artifact": {
+                "path": "net/minecraftforge/fmlloader/1.20.1-47.2.0/fmlloader-1.20.1-47.2.0.jar",
+                "url": "https://maven.minecraftforge.net/net/minecraftforge/fmlloader/1.20.1-47.2.0/fmlloader-1.20.1-47.2.0.jar",
+                "sha1": "a", "size": 1
+            }}
+        }]
+    }"#;
+
+    const VANILLA_JSON: &str = r#"{
+        "id": "1.20.1",
+        "mainClass": "net.minecraft.client.main.Main",
+        "downloads": {"client": {"sha1": "b", "size": 1, "url": "https://piston-data.mojang.com/v1/objects/b/client.jar"}},
+        "libraries": []
+    }"#;
+
+    // Trimmed from the installer's install_profile.json
+    const INSTALL_PROFILE: &str = r#"{
+        "spec": 1,
+        "version": "1.20.1-forge-47.2.0",
+        "data": {
+            "MOJMAPS": {"client": "[net.minecraft:client:1.20.1-20230612.114412:mappings@txt]", "server": "[net.minecraft:server:1.20.1-20230612.114412:mappings@txt]"},
+            "MERGED_MAPPINGS": {"client": "[de.oceanlabs.mcp:mcp_config:1.20.1-20230612.114412:mappings-merged@txt]", "server": "[de.oceanlabs.mcp:mcp_config:1.20.1-20230612.114412:mappings-merged@txt]"}
+        },
+        "processors": [
+            {"sides": ["server"], "jar": "net.minecraftforge:installertools:1.3.0", "outputs": {"[net.minecraft:server:1.20.1:extra]": "'x'"}},
+            {"jar": "net.minecraftforge:installertools:1.3.0", "outputs": {"{MERGED_MAPPINGS}": "'y'"}}
+        ]
+    }"#;
+
+    fn write(path: &Path, content: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn keeps_forge_generated_libraries() {
+        let dir = std::env::temp_dir().join(format!("mosaic-cleanup-forge-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let libraries = dir.join("libraries");
+
+        write(&dir.join("versions/1.20.1/1.20.1.json"), VANILLA_JSON.as_bytes());
+        write(&dir.join("versions/1.20.1-forge-47.2.0/1.20.1-forge-47.2.0.json"), FORGE_JSON.as_bytes());
+
+        fs::create_dir_all(dir.join("forge")).unwrap();
+        let mut installer = zip::ZipWriter::new(fs::File::create(dir.join("forge/forge-1.20.1-47.2.0-installer.jar")).unwrap());
+        installer.start_file("install_profile.json", zip::write::FileOptions::default()).unwrap();
+        installer.write_all(INSTALL_PROFILE.as_bytes()).unwrap();
+        installer.finish().unwrap();
+
+        let used = [
+            "net/minecraftforge/fmlloader/1.20.1-47.2.0/fmlloader-1.20.1-47.2.0.jar",
+            "cpw/mods/bootstraplauncher/1.1.2/bootstraplauncher-1.1.2.jar",
+            "cpw/mods/securejarhandler/2.1.10/securejarhandler-2.1.10.jar",
+            "net/minecraft/client/1.20.1-20230612.114412/client-1.20.1-20230612.114412-srg.jar",
+            "net/minecraft/client/1.20.1-20230612.114412/client-1.20.1-20230612.114412-extra.jar",
+            "net/minecraftforge/forge/1.20.1-47.2.0/forge-1.20.1-47.2.0-client.jar",
+            "de/oceanlabs/mcp/mcp_config/1.20.1-20230612.114412/mcp_config-1.20.1-20230612.114412-mappings-merged.txt",
+        ];
+        let unused = [
+            "net/minecraft/server/1.20.1/server-1.20.1-extra.jar",
+            "net/minecraft/client/1.19.4-20230314.122934/client-1.19.4-20230314.122934-srg.jar",
+        ];
+        for path in used.iter().chain(&unused) {
+            write(&libraries.join(path), b"jar");
+        }
+
+        let plan = find_unused_files(&dir, &["1.20.1-forge-47.2.0".to_string()]).unwrap();
+        let mut found: Vec<PathBuf> = plan.libraries.iter().map(|entry| entry.path.clone()).collect();
+        found.sort();
+        let mut expected: Vec<PathBuf> = unused.iter().map(|path| libraries.join(path)).collect();
+        expected.sort();
+        assert_eq!(found, expected);
+        assert!(plan.versions.is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
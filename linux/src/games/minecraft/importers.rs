@@ -0,0 +1,620 @@
+// Importers that turn other launchers' profiles into Mosaic profiles
+
+use anyhow::{Result, anyhow};
+use log::{info, warn, debug};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::config::{Profile, ModLoader, LaunchSettings};
+use crate::utils::{copy_dir_all, sanitize_file_name, split_shell_words};
+use super::models::LatestVersions;
+
+/// A profile read from another launcher, ready to be previewed and imported
+#[derive(Debug, Clone)]
+pub struct ImportedProfile {
+    /// The profile that would be added to the config
+    pub profile: Profile,
+    /// Where the profile came from (profile key or instance folder)
+    pub source: String,
+    /// Settings that could not be carried over
+    pub warnings: Vec<String>,
+}
+
+/// `launcher_profiles.json` as written by the official launcher
+#[derive(Debug, Deserialize)]
+struct LauncherProfilesFile {
+    #[serde(default)]
+    profiles: HashMap<String, LauncherProfile>,
+}
+
+/// A single profile entry in `launcher_profiles.json`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LauncherProfile {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    profile_type: Option<String>,
+    last_version_id: Option<String>,
+    game_dir: Option<PathBuf>,
+    java_dir: Option<PathBuf>,
+    java_args: Option<String>,
+    resolution: Option<LauncherResolution>,
+    icon: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LauncherResolution {
+    width: u32,
+    height: u32,
+}
+
+/// Reads the official launcher's `launcher_profiles.json` from a Minecraft directory
+///
+/// `latest` is used to resolve the "Latest release" and "Latest snapshot" profiles;
+/// without it those profiles are skipped.
+pub fn read_launcher_profiles(minecraft_dir: &Path, latest: Option<&LatestVersions>) -> Result<Vec<ImportedProfile>> {
+    let profiles_path = minecraft_dir.join("launcher_profiles.json");
+    info!("Reading official launcher profiles from {:?}", profiles_path);
+
+    let content = fs::read_to_string(&profiles_path)
+        .map_err(|e| anyhow!("Failed to read {:?}: {}", profiles_path, e))?;
+    let file: LauncherProfilesFile = serde_json::from_str(&content)
+        .map_err(|e| anyhow!("Failed to parse {:?}: {}", profiles_path, e))?;
+
+    let mut imported = Vec::new();
+    for (key, launcher_profile) in file.profiles {
+        match convert_launcher_profile(&key, launcher_profile, latest) {
+            Some(profile) => imported.push(profile),
+            None => debug!("Skipping launcher profile {}", key),
+        }
+    }
+
+    // HashMap order is random, keep the preview stable
+    imported.sort_by_key(|a| a.profile.name.to_lowercase());
+
+    info!("Found {} importable profiles in {:?}", imported.len(), profiles_path);
+    Ok(imported)
+}
+
+/// Converts one `launcher_profiles.json` entry into a profile
+fn convert_launcher_profile(key: &str, source: LauncherProfile, latest: Option<&LatestVersions>) -> Option<ImportedProfile> {
+    let mut warnings = Vec::new();
+
+    let profile_type = source.profile_type.as_deref().unwrap_or("custom");
+    let last_version_id = source.last_version_id.clone().unwrap_or_else(|| profile_type.to_string());
+
+    // The built-in profiles follow the latest release or snapshot
+    let version_id = match last_version_id.as_str() {
+        "latest-release" => match latest {
+            Some(latest) => latest.release.clone(),
+            None => {
+                warn!("Cannot resolve latest release for launcher profile {}", key);
+                return None;
+            }
+        },
+        "latest-snapshot" => match latest {
+            Some(latest) => latest.snapshot.clone(),
+            None => {
+                warn!("Cannot resolve latest snapshot for launcher profile {}", key);
+                return None;
+            }
+        },
+        other => other.to_string(),
+    };
+
+    let default_name = match profile_type {
+        "latest-release" => "Latest release".to_string(),
+        "latest-snapshot" => "Latest snapshot".to_string(),
+        _ => version_id.clone(),
+    };
+    let name = source.name.filter(|n| !n.is_empty()).unwrap_or(default_name);
+
+    let (version, mod_loader, mod_loader_version) = parse_version_id(&version_id, &mut warnings);
+
+    // Split the heap flags out of javaArgs, everything else stays a JVM argument
+    let mut launch = LaunchSettings::default();
+    if let Some(java_args) = &source.java_args {
+        let args = split_shell_words(java_args).unwrap_or_else(|e| {
+            warnings.push(format!("Could not parse the Java arguments, splitting them at spaces: {}", e));
+            java_args.split_whitespace().map(|arg| arg.to_string()).collect()
+        });
+        let mut jvm_arguments = Vec::new();
+        for arg in args {
+            if let Some(size) = arg.strip_prefix("-Xmx") {
+                match parse_memory_size(size) {
+                    Some(mb) => launch.max_memory = Some(mb),
+                    None => warnings.push(format!("Could not parse maximum memory '{}'", arg)),
+                }
+            } else if let Some(size) = arg.strip_prefix("-Xms") {
+                match parse_memory_size(size) {
+                    Some(mb) => launch.min_memory = Some(mb),
+                    None => warnings.push(format!("Could not parse minimum memory '{}'", arg)),
+                }
+            } else {
+                jvm_arguments.push(arg);
+            }
+        }
+        if !jvm_arguments.is_empty() {
+            launch.jvm_arguments = Some(jvm_arguments);
+        }
+    }
+
+    // javaDir points at the java executable itself
+    launch.java_path = source.java_dir;
+
+    let profile = Profile {
+        id: Uuid::new_v4().to_string(),
+        name,
+        version,
+        mod_loader: Some(mod_loader),
+        mod_loader_version,
+        mods: Vec::new(),
+        game_directory: source.game_dir,
+        resolution: source.resolution.map(|r| (r.width, r.height)),
+        icon: source.icon,
+        launch,
+    };
+
+    Some(ImportedProfile {
+        profile,
+        source: key.to_string(),
+        warnings,
+    })
+}
+
+/// Maps an official launcher version id to a Minecraft version and mod loader
+///
+/// Handles the ids written by the loader installers, e.g. `fabric-loader-0.15.7-1.20.4`,
+/// `quilt-loader-0.23.1-1.20.4`, `1.20.1-forge-47.2.0`, `1.12.2-forge-14.23.5.2859`
+/// and `neoforge-20.4.80-beta`.
+pub(crate) fn parse_version_id(version_id: &str, warnings: &mut Vec<String>) -> (String, ModLoader, Option<String>) {
+    // Fabric and Quilt: <loader>-loader-<loader version>-<minecraft version>
+    for (prefix, loader) in [("fabric-loader-", ModLoader::Fabric), ("quilt-loader-", ModLoader::Quilt)] {
+        if let Some(rest) = version_id.strip_prefix(prefix) {
+            if let Some((loader_version, minecraft_version)) = rest.split_once('-') {
+                return (minecraft_version.to_string(), loader, Some(loader_version.to_string()));
+            }
+        }
+    }
+
+    // NeoForge: neoforge-<major>.<minor>.<build>, where major.minor tracks 1.<major>.<minor>
+    if let Some(neoforge_version) = version_id.strip_prefix("neoforge-") {
+        let mut parts = neoforge_version.split('.');
+        if let (Some(major), Some(minor)) = (parts.next(), parts.next()) {
+            let minecraft_version = if minor == "0" {
+                format!("1.{}", major)
+            } else {
+                format!("1.{}.{}", major, minor)
+            };
+            return (minecraft_version, ModLoader::NeoForge, Some(neoforge_version.to_string()));
+        }
+    }
+
+    // Forge: <minecraft version>-forge-<forge version>, older installers use
+    // <minecraft version>-forge<minecraft version>-<forge version>
+    if let Some(index) = version_id.to_lowercase().find("-forge") {
+        let minecraft_version = &version_id[..index];
+        let rest = &version_id[index + "-forge".len()..];
+        let forge_version = rest
+            .trim_start_matches('-')
+            .trim_start_matches(minecraft_version)
+            .trim_start_matches('-');
+        // Some very old ids put the Minecraft version at the end as well
+        let forge_version = forge_version
+            .strip_suffix(&format!("-{}", minecraft_version))
+            .unwrap_or(forge_version);
+        if !forge_version.is_empty() {
+            return (
+                minecraft_version.to_string(),
+                ModLoader::Forge,
+                Some(format!("{}-{}", minecraft_version, forge_version)),
+            );
+        }
+    }
+
+    // OptiFine and other installers use <minecraft version>-<suffix>
+    if let Some((minecraft_version, suffix)) = version_id.split_once('-') {
+        if suffix.starts_with("OptiFine") {
+            warnings.push(format!("OptiFine ({}) is not supported, the profile will launch vanilla {}", suffix, minecraft_version));
+            return (minecraft_version.to_string(), ModLoader::None, None);
+        }
+    }
+
+    (version_id.to_string(), ModLoader::None, None)
+}
+
+/// Parses a JVM memory size such as `4G`, `2048M` or `2097152K` into megabytes
+pub(crate) fn parse_memory_size(size: &str) -> Option<u32> {
+    let size = size.trim();
+    let (number, unit) = match size.char_indices().last()? {
+        (i, c) if c.is_ascii_alphabetic() => (&size[..i], c.to_ascii_lowercase()),
+        _ => (size, 'b'),
+    };
+    let number: u64 = number.parse().ok()?;
+
+    let mb = match unit {
+        'g' => number * 1024,
+        'm' => number,
+        'k' => number / 1024,
+        'b' => number / (1024 * 1024),
+        _ => return None,
+    };
+
+    u32::try_from(mb).ok()
+}
+
+/// `mmc-pack.json` as written by MultiMC and Prism Launcher
+#[derive(Debug, Deserialize)]
+struct MmcPack {
+    #[serde(default)]
+    components: Vec<MmcComponent>,
+}
+
+/// A single component of an instance (Minecraft itself, a loader, LWJGL, ...)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MmcComponent {
+    uid: String,
+    version: Option<String>,
+    cached_name: Option<String>,
+}
+
+/// Components that are implied by the Minecraft version or loader and need no mapping
+const IMPLIED_COMPONENTS: &[&str] = &[
+    "org.lwjgl",
+    "org.lwjgl3",
+    "net.fabricmc.intermediary",
+    "org.quiltmc.hashed",
+];
+
+/// `instance.cfg` keys we have no equivalent for
+const UNSUPPORTED_INSTANCE_KEYS: &[(&str, &str)] = &[
+    ("PreLaunchCommand", "pre-launch command"),
+    ("PostExitCommand", "post-exit command"),
+    ("WrapperCommand", "wrapper command"),
+    ("JoinServerOnLaunchAddress", "join server on launch"),
+    ("ManagedPackID", "managed modpack updates"),
+];
+
+/// Reads every MultiMC / Prism instance in a folder
+///
+/// The folder may be a single instance (it contains `instance.cfg`) or an
+/// `instances` directory holding several of them.
+pub fn read_multimc_instances(dir: &Path) -> Result<Vec<ImportedProfile>> {
+    if dir.join("instance.cfg").exists() {
+        return Ok(vec![read_multimc_instance(dir)?]);
+    }
+
+    let mut imported = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.join("instance.cfg").exists() {
+            continue;
+        }
+
+        match read_multimc_instance(&path) {
+            Ok(profile) => imported.push(profile),
+            Err(e) => warn!("Skipping instance {:?}: {}", path, e),
+        }
+    }
+
+    if imported.is_empty() {
+        return Err(anyhow!("No MultiMC or Prism instances found in {:?}", dir));
+    }
+
+    imported.sort_by_key(|a| a.profile.name.to_lowercase());
+    Ok(imported)
+}
+
+/// Reads a single MultiMC / Prism instance folder
+///
+/// The returned profile points at the instance's own game directory; use
+/// `copy_instance_game_directory` to give it a private copy instead.
+pub fn read_multimc_instance(instance_dir: &Path) -> Result<ImportedProfile> {
+    info!("Reading MultiMC instance from {:?}", instance_dir);
+    let mut warnings = Vec::new();
+
+    let cfg_path = instance_dir.join("instance.cfg");
+    let cfg = parse_instance_cfg(&fs::read_to_string(&cfg_path)
+        .map_err(|e| anyhow!("Failed to read {:?}: {}", cfg_path, e))?);
+
+    let pack_path = instance_dir.join("mmc-pack.json");
+    let pack: MmcPack = serde_json::from_str(&fs::read_to_string(&pack_path)
+        .map_err(|e| anyhow!("Failed to read {:?}: {}", pack_path, e))?)
+        .map_err(|e| anyhow!("Failed to parse {:?}: {}", pack_path, e))?;
+
+    let (version, mod_loader, mod_loader_version) = map_pack_components(&pack, &mut warnings)
+        .ok_or_else(|| anyhow!("Instance {:?} has no net.minecraft component", instance_dir))?;
+
+    let is_true = |key: &str| cfg.get(key).map(|v| v == "true").unwrap_or(false);
+
+    // Memory, JVM arguments and Java only apply when the instance overrides them
+    let mut launch = LaunchSettings::default();
+    if is_true("OverrideMemory") {
+        launch.max_memory = cfg.get("MaxMemAlloc").and_then(|v| v.parse().ok());
+        launch.min_memory = cfg.get("MinMemAlloc").and_then(|v| v.parse().ok());
+    }
+    if is_true("OverrideJavaArgs") {
+        if let Some(args) = cfg.get("JvmArgs").filter(|a| !a.is_empty()) {
+            match split_shell_words(args) {
+                Ok(args) => launch.jvm_arguments = Some(args),
+                Err(e) => warnings.push(format!("The JVM arguments were not imported: {}", e)),
+            }
+        }
+    }
+    if is_true("OverrideJavaLocation") {
+        launch.java_path = cfg.get("JavaPath").filter(|p| !p.is_empty()).map(PathBuf::from);
+    }
+
+    let resolution = if is_true("OverrideWindow") && !is_true("LaunchMaximized") {
+        match (cfg.get("MinecraftWinWidth"), cfg.get("MinecraftWinHeight")) {
+            (Some(width), Some(height)) => width.parse().ok().zip(height.parse().ok()),
+            _ => None,
+        }
+    } else {
+        if is_true("LaunchMaximized") {
+            warnings.push("Launching maximized is not supported".to_string());
+        }
+        None
+    };
+
+    for (key, description) in UNSUPPORTED_INSTANCE_KEYS {
+        if cfg.get(*key).map(|v| !v.is_empty()).unwrap_or(false) {
+            warnings.push(format!("The {} setting was not imported", description));
+        }
+    }
+
+    // Prism uses "minecraft", older MultiMC versions ".minecraft"
+    let game_directory = [".minecraft", "minecraft"].iter()
+        .map(|name| instance_dir.join(name))
+        .find(|path| path.is_dir());
+    if game_directory.is_none() {
+        warnings.push("Instance has no game directory, a new one will be used".to_string());
+    }
+
+    let name = cfg.get("name").cloned().unwrap_or_else(|| {
+        instance_dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| version.clone())
+    });
+
+    let icon = cfg.get("iconKey").filter(|k| !k.is_empty() && k.as_str() != "default").cloned();
+
+    let profile = Profile {
+        id: Uuid::new_v4().to_string(),
+        name,
+        version,
+        mod_loader: Some(mod_loader),
+        mod_loader_version,
+        mods: Vec::new(),
+        game_directory,
+        resolution,
+        icon,
+        launch,
+    };
+
+    for warning in &warnings {
+        warn!("Instance {:?}: {}", instance_dir, warning);
+    }
+
+    Ok(ImportedProfile {
+        profile,
+        source: instance_dir.to_string_lossy().to_string(),
+        warnings,
+    })
+}
+
+/// Maps the components of an instance to a Minecraft version and mod loader
+///
+/// Returns None when the pack has no `net.minecraft` component.
+fn map_pack_components(pack: &MmcPack, warnings: &mut Vec<String>) -> Option<(String, ModLoader, Option<String>)> {
+    let mut version = None;
+    let mut loader: Option<(ModLoader, String)> = None;
+    for component in &pack.components {
+        let component_version = component.version.clone().unwrap_or_default();
+        let mapped_loader = match component.uid.as_str() {
+            "net.minecraft" => {
+                version = Some(component_version);
+                continue;
+            },
+            "net.fabricmc.fabric-loader" => ModLoader::Fabric,
+            "org.quiltmc.quilt-loader" => ModLoader::Quilt,
+            "net.minecraftforge" => ModLoader::Forge,
+            "net.neoforged" => ModLoader::NeoForge,
+            uid if IMPLIED_COMPONENTS.contains(&uid) => continue,
+            uid => {
+                let name = component.cached_name.clone().unwrap_or_else(|| uid.to_string());
+                warnings.push(format!("Component {} {} is not supported", name, component_version));
+                continue;
+            }
+        };
+
+        if let Some((existing, _)) = &loader {
+            warnings.push(format!("Multiple mod loaders found, ignoring {:?} in favour of {:?}", mapped_loader, existing));
+        } else {
+            loader = Some((mapped_loader, component_version));
+        }
+    }
+
+    let version = version?;
+    let (mod_loader, mod_loader_version) = match loader {
+        // Forge versions are stored as <minecraft>-<forge> everywhere else
+        Some((ModLoader::Forge, forge_version)) => (ModLoader::Forge, Some(format!("{}-{}", version, forge_version))),
+        Some((loader, loader_version)) => (loader, Some(loader_version)),
+        None => (ModLoader::None, None),
+    };
+
+    Some((version, mod_loader, mod_loader_version))
+}
+
+/// Copies an imported profile's game directory into `instances_dir` and points the profile at the copy
+pub fn copy_instance_game_directory(profile: &mut Profile, instances_dir: &Path) -> Result<()> {
+    let source = match &profile.game_directory {
+        Some(source) => source.clone(),
+        None => return Ok(()),
+    };
+
+    // Pick a free directory name based on the profile name
+    let base_name = sanitize_file_name(&profile.name);
+    let mut target = instances_dir.join(&base_name);
+    let mut suffix = 2;
+    while target.exists() {
+        target = instances_dir.join(format!("{} ({})", base_name, suffix));
+        suffix += 1;
+    }
+
+    info!("Copying game directory {:?} to {:?}", source, target);
+    let copied = copy_dir_all(&source, &target)?;
+    info!("Copied {} bytes for profile {}", copied, profile.name);
+
+    profile.game_directory = Some(target);
+    Ok(())
+}
+
+/// Parses the INI-style `instance.cfg` into a flat key/value map
+fn parse_instance_cfg(content: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') || line.starts_with('[') {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim();
+            let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+            values.insert(key.trim().to_string(), value.to_string());
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(version_id: &str) -> (String, ModLoader, Option<String>) {
+        parse_version_id(version_id, &mut Vec::new())
+    }
+
+    #[test]
+    fn parses_version_ids() {
+        assert_eq!(parse("1.20.4"), ("1.20.4".to_string(), ModLoader::None, None));
+        assert_eq!(parse("24w14a"), ("24w14a".to_string(), ModLoader::None, None));
+        assert_eq!(
+            parse("fabric-loader-0.15.7-1.20.4"),
+            ("1.20.4".to_string(), ModLoader::Fabric, Some("0.15.7".to_string())),
+        );
+        assert_eq!(
+            parse("quilt-loader-0.23.1-1.20.4"),
+            ("1.20.4".to_string(), ModLoader::Quilt, Some("0.23.1".to_string())),
+        );
+        assert_eq!(
+            parse("1.20.1-forge-47.2.0"),
+            ("1.20.1".to_string(), ModLoader::Forge, Some("1.20.1-47.2.0".to_string())),
+        );
+        assert_eq!(
+            parse("1.12.2-forge1.12.2-14.23.5.2859"),
+            ("1.12.2".to_string(), ModLoader::Forge, Some("1.12.2-14.23.5.2859".to_string())),
+        );
+        assert_eq!(
+            parse("1.7.10-Forge10.13.4.1614-1.7.10"),
+            ("1.7.10".to_string(), ModLoader::Forge, Some("1.7.10-10.13.4.1614".to_string())),
+        );
+        assert_eq!(
+            parse("neoforge-20.4.80-beta"),
+            ("1.20.4".to_string(), ModLoader::NeoForge, Some("20.4.80-beta".to_string())),
+        );
+        assert_eq!(
+            parse("neoforge-21.0.167"),
+            ("1.21".to_string(), ModLoader::NeoForge, Some("21.0.167".to_string())),
+        );
+    }
+
+    #[test]
+    fn warns_about_optifine() {
+        let mut warnings = Vec::new();
+        let parsed = parse_version_id("1.20.1-OptiFine_HD_U_I6", &mut warnings);
+        assert_eq!(parsed, ("1.20.1".to_string(), ModLoader::None, None));
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn parses_memory_sizes() {
+        assert_eq!(parse_memory_size("4G"), Some(4096));
+        assert_eq!(parse_memory_size("4g"), Some(4096));
+        assert_eq!(parse_memory_size("2048M"), Some(2048));
+        assert_eq!(parse_memory_size("2097152K"), Some(2048));
+        assert_eq!(parse_memory_size("1073741824"), Some(1024));
+        assert_eq!(parse_memory_size(""), None);
+        assert_eq!(parse_memory_size("G"), None);
+        assert_eq!(parse_memory_size("4T"), None);
+        assert_eq!(parse_memory_size("lots"), None);
+    }
+
+    #[test]
+    fn splits_quoted_java_args() {
+        let source: LauncherProfile = serde_json::from_str(r#"{
+            "name": "Modded",
+            "type": "custom",
+            "lastVersionId": "1.20.4",
+            "javaArgs": "-Xmx4G -Xms1G -Dlog4j.configurationFile=\"/home/me/My Configs/log4j.xml\" -XX:+UseG1GC"
+        }"#).unwrap();
+
+        let imported = convert_launcher_profile("modded", source, None).unwrap();
+        let launch = &imported.profile.launch;
+        assert_eq!(launch.max_memory, Some(4096));
+        assert_eq!(launch.min_memory, Some(1024));
+        assert_eq!(
+            launch.jvm_arguments.as_deref(),
+            Some(&["-Dlog4j.configurationFile=/home/me/My Configs/log4j.xml".to_string(), "-XX:+UseG1GC".to_string()][..]),
+        );
+        assert!(imported.warnings.is_empty());
+    }
+
+    fn map_pack(json: &str, warnings: &mut Vec<String>) -> Option<(String, ModLoader, Option<String>)> {
+        let pack: MmcPack = serde_json::from_str(json).unwrap();
+        map_pack_components(&pack, warnings)
+    }
+
+    #[test]
+    fn maps_pack_components() {
+        // Trimmed from an instance Prism Launcher created
+        let mut warnings = Vec::new();
+        let mapped = map_pack(r#"{
+            "components": [
+                {"cachedName": "LWJGL 3", "cachedVersion": "3.3.1", "dependencyOnly": true, "uid": "org.lwjgl3", "version": "3.3.1"},
+                {"cachedName": "Minecraft", "cachedVersion": "1.20.1", "important": true, "uid": "net.minecraft", "version": "1.20.1"},
+                {"cachedName": "Forge", "cachedVersion": "47.2.0", "uid": "net.minecraftforge", "version": "47.2.0"}
+            ],
+            "formatVersion": 1
+        }"#, &mut warnings);
+        assert_eq!(mapped, Some(("1.20.1".to_string(), ModLoader::Forge, Some("1.20.1-47.2.0".to_string()))));
+        assert!(warnings.is_empty());
+
+        let mapped = map_pack(r#"{
+            "components": [
+                {"cachedName": "Minecraft", "uid": "net.minecraft", "version": "1.20.4"},
+                {"cachedName": "Intermediary Mappings", "dependencyOnly": true, "uid": "net.fabricmc.intermediary", "version": "1.20.4"},
+                {"cachedName": "Fabric Loader", "uid": "net.fabricmc.fabric-loader", "version": "0.15.7"},
+                {"cachedName": "Quilt Loader", "uid": "org.quiltmc.quilt-loader", "version": "0.23.1"},
+                {"cachedName": "OptiFine", "uid": "optifine.OptiFine", "version": "1.20.4_HD_U_I7"}
+            ]
+        }"#, &mut warnings);
+        assert_eq!(mapped, Some(("1.20.4".to_string(), ModLoader::Fabric, Some("0.15.7".to_string()))));
+        assert_eq!(warnings.len(), 2);
+
+        let mapped = map_pack(r#"{"components": [
+            {"uid": "net.minecraft", "version": "1.20.4"},
+            {"uid": "net.neoforged", "version": "20.4.80-beta"}
+        ]}"#, &mut Vec::new());
+        assert_eq!(mapped, Some(("1.20.4".to_string(), ModLoader::NeoForge, Some("20.4.80-beta".to_string()))));
+
+        let mapped = map_pack(r#"{"components": [{"uid": "net.minecraft", "version": "1.8.9"}]}"#, &mut Vec::new());
+        assert_eq!(mapped, Some(("1.8.9".to_string(), ModLoader::None, None)));
+
+        let mapped = map_pack(r#"{"components": [{"uid": "net.fabricmc.fabric-loader", "version": "0.15.7"}]}"#, &mut Vec::new());
+        assert_eq!(mapped, None);
+    }
+}
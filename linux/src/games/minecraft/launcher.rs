@@ -6,11 +6,16 @@ use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio, Child};
 use std::collections::HashMap;
 
-use crate::config::Profile;
+use crate::config::{Profile, EffectiveLaunchSettings};
 use crate::games::minecraft::auth::AuthSession;
 use super::models::{VersionDetails, Arguments};
+use super::rules::{RuleEnvironment, argument_allowed};
 use super::versions;
 
+/// Brand reported to the game, e.g. in crash reports
+const LAUNCHER_NAME: &str = "MosaicLauncher";
+const LAUNCHER_VERSION: &str = "1.0.0";
+
 /// Launches Minecraft with the specified profile and authentication session
 pub async fn launch_game(
     minecraft_dir: &Path,
@@ -18,6 +23,7 @@ pub async fn launch_game(
     auth_session: &AuthSession,
     version_details: &VersionDetails,
     java_path: &Path,
+    launch_settings: &EffectiveLaunchSettings,
 ) -> Result<u32> {
     info!("Launching Minecraft with profile: {}", profile.name);
 
@@ -33,120 +39,70 @@ pub async fn launch_game(
 
     command.current_dir(&game_dir);
 
+    // Add environment variables from the launch settings
+    command.envs(&launch_settings.environment);
+
     // Add memory settings
-    let memory = profile.memory.unwrap_or(2048);
-    command.arg(format!("-Xmx{}M", memory));
-    command.arg(format!("-Xms{}M", memory));
-
-    // Add standard JVM arguments
-    command.arg("-XX:+UnlockExperimentalVMOptions");
-    command.arg("-XX:+UseG1GC");
-    command.arg("-XX:G1NewSizePercent=20");
-    command.arg("-XX:G1ReservePercent=20");
-    command.arg("-XX:MaxGCPauseMillis=50");
-    command.arg("-XX:G1HeapRegionSize=32M");
+    command.arg(format!("-Xmx{}M", launch_settings.max_memory));
+    command.arg(format!("-Xms{}M", launch_settings.min_memory));
+
+    // Add the configured JVM arguments
+    command.args(&launch_settings.jvm_arguments);
     command.arg("-Dfile.encoding=UTF-8");
     // Set the path to the natives directory
     // Use the full path to the natives directory to ensure LWJGL can find the native libraries
     // This fixes the "Failed to locate library: liblwjgl.so" error
     let natives_dir = minecraft_dir.join("versions").join(&version_details.id).join("natives");
-    command.arg(format!("-Djava.library.path={}", natives_dir.to_string_lossy()));
+
+    // Build classpath
+    let classpath = versions::build_classpath(minecraft_dir, version_details)?;
+
+    // Features decide which optional arguments of the version apply,
+    // quick play isn't supported so its arguments stay off
+    let environment = RuleEnvironment::current()
+        .with_feature("has_custom_resolution", profile.resolution.is_some())
+        .with_feature("is_demo_user", false);
+
+    // Versions with JVM arguments set the library path and classpath themselves,
+    // loaders like Forge add module options there as well
+    let jvm_arguments = version_details.arguments.as_ref()
+        .map(|arguments| arguments.jvm.as_slice())
+        .unwrap_or_default();
+    if jvm_arguments.is_empty() {
+        command.arg(format!("-Djava.library.path={}", natives_dir.to_string_lossy()));
+        // LWJGL 2 loads the controller natives of JInput from their own path
+        command.arg(format!("-Dnet.java.games.input.librarypath={}", natives_dir.to_string_lossy()));
+        command.arg(format!("-Dminecraft.launcher.brand={}", LAUNCHER_NAME));
+        command.arg(format!("-Dminecraft.launcher.version={}", LAUNCHER_VERSION));
+        command.arg("-cp").arg(&classpath);
+    } else {
+        let libraries_dir = minecraft_dir.join("libraries");
+        command.args(resolve_arguments(jvm_arguments, &environment, |arg| {
+            replace_jvm_placeholders(arg, &version_details.id, &natives_dir, &libraries_dir, &classpath)
+        }));
+    }
 
     // Also set the LWJGL library path as a backup
     command.arg(format!("-Dorg.lwjgl.librarypath={}", natives_dir.to_string_lossy()));
-    command.arg("-Dminecraft.launcher.brand=MosaicLauncher");
-    command.arg("-Dminecraft.launcher.version=1.0.0");
     command.arg("-Dorg.lwjgl.util.DebugLoader=true");
 
-    // Build classpath
-    let classpath = versions::build_classpath(minecraft_dir, version_details)?;
-    command.arg("-cp").arg(classpath);
-
-    // Determine main class based on modloader
-    let main_class = if let Some(mod_loader) = &profile.mod_loader {
-        match mod_loader {
-            crate::config::ModLoader::Forge => {
-                // For Forge, we need to use the forge main class
-                // The exact class name depends on the Forge version
-                let forge_version = profile.mod_loader_version.clone().unwrap_or_else(|| {
-                    // Get a known version for this Minecraft version
-                    match version_details.id.as_str() {
-                        "1.21.5" => "1.21.5-47.1.0".to_string(),
-                        "1.21.1" => "1.21.1-47.0.1".to_string(),
-                        "1.20.4" => "1.20.4-49.0.3".to_string(),
-                        "1.20.1" => "1.20.1-47.2.0".to_string(),
-                        "1.19.4" => "1.19.4-45.1.0".to_string(),
-                        "1.19.2" => "1.19.2-43.2.0".to_string(),
-                        "1.18.2" => "1.18.2-40.2.0".to_string(),
-                        "1.17.1" => "1.17.1-37.1.1".to_string(),
-                        "1.16.5" => "1.16.5-36.2.39".to_string(),
-                        _ => {
-                            warn!("No known Forge version for Minecraft {}, using a placeholder", version_details.id);
-                            format!("{}-unknown", version_details.id)
-                        }
-                    }
-                });
-
-                // Add Forge libraries to classpath
-                // This is handled by the build_classpath function
-
-                // Return the Forge main class
-                "net.minecraftforge.client.main.Main"
-            },
-            crate::config::ModLoader::NeoForge => {
-                // For NeoForge, we need to use the NeoForge main class
-                // The exact class name depends on the NeoForge version
-                let neoforge_version = profile.mod_loader_version.clone().unwrap_or_else(|| {
-                    // Get a known version for this Minecraft version
-                    match version_details.id.as_str() {
-                        "1.21.1" => "1.21.1-47.0.1".to_string(),
-                        "1.20.4" => "1.20.4-49.0.3".to_string(),
-                        "1.20.1" => "1.20.1-47.2.0".to_string(),
-                        "1.19.4" => "1.19.4-45.1.0".to_string(),
-                        "1.19.2" => "1.19.2-43.2.0".to_string(),
-                        _ => {
-                            warn!("No known NeoForge version for Minecraft {}, using a placeholder", version_details.id);
-                            format!("{}-unknown", version_details.id)
-                        }
-                    }
-                });
-
-                // Add NeoForge libraries to classpath
-                // This is handled by the build_classpath function
-
-                // Return the NeoForge main class
-                "net.neoforged.client.main.Main"
-            },
-            crate::config::ModLoader::Fabric => {
-                // For Fabric, we need to use the Fabric main class
-                // Add Fabric libraries to classpath
-                // This is handled by the build_classpath function
-
-                // Return the Fabric main class
-                "net.fabricmc.loader.impl.launch.knot.KnotClient"
-            },
-            crate::config::ModLoader::Quilt => {
-                // For Quilt, we need to use the Quilt main class
-                // Add Quilt libraries to classpath
-                // This is handled by the build_classpath function
-
-                // Return the Quilt main class
-                "org.quiltmc.loader.impl.launch.knot.KnotClient"
-            },
-            crate::config::ModLoader::None => {
-                // For vanilla, use the default main class
-                version_details.main_class.as_deref().unwrap_or("net.minecraft.client.main.Main")
+    // The version's log4j config, for 1.7 to 1.18 this is what patches Log4Shell
+    if let Some(logging) = &version_details.logging {
+        if let Some(config_path) = versions::get_logging_config_path(minecraft_dir, version_details) {
+            if !config_path.exists() {
+                return Err(anyhow!("Logging config {:?} of version {} is missing", config_path, version_details.id));
             }
+            command.arg(logging.client.argument.replace("${path}", &config_path.to_string_lossy()));
         }
-    } else {
-        // No modloader specified, use the default main class
-        version_details.main_class.as_deref().unwrap_or("net.minecraft.client.main.Main")
-    };
+    }
+
+    // The main class comes from the version, a loader's version names its own
+    let main_class = version_details.main_class.as_deref().unwrap_or("net.minecraft.client.main.Main");
 
     command.arg(main_class);
 
     // Add game arguments
-    add_game_arguments(&mut command, profile, auth_session, version_details, minecraft_dir)?;
+    add_game_arguments(&mut command, profile, auth_session, version_details, minecraft_dir, &environment)?;
 
     // Launch the game
     info!("Launching Minecraft with command: {:?}", command);
@@ -167,6 +123,7 @@ fn add_game_arguments(
     auth_session: &AuthSession,
     version_details: &VersionDetails,
     minecraft_dir: &Path,
+    environment: &RuleEnvironment,
 ) -> Result<()> {
     // Get the Minecraft profile from the auth session
     let minecraft_profile = auth_session.minecraft_profile.as_ref()
@@ -185,153 +142,75 @@ fn add_game_arguments(
     // Get the assets index
     let assets_index = version_details.assets.clone();
 
-    // Get the assets path
-    let assets_path = if assets_index == "legacy" {
-        minecraft_dir.join("assets").join("virtual").join("legacy").to_string_lossy().to_string()
-    } else {
-        minecraft_dir.join("assets").to_string_lossy().to_string()
-    };
+    // Versions before 1.7.10 read their assets by name rather than from the hashed store
+    let assets_path = versions::prepare_asset_layout(minecraft_dir, version_details, &game_dir)?
+        .unwrap_or_else(|| assets_dir.clone())
+        .to_string_lossy()
+        .to_string();
+
+    let replace = |arg: &str| replace_placeholders(
+        arg,
+        minecraft_profile,
+        auth_session,
+        version_details,
+        &game_dir,
+        &assets_dir,
+        &assets_index,
+        &assets_path,
+        profile.resolution,
+    );
 
     // Add arguments from the version details
     if let Some(arguments) = &version_details.arguments {
-        // Add game arguments
-        for arg in &arguments.game {
-            match arg {
-                serde_json::Value::String(s) => {
-                    let arg = replace_placeholders(
-                        s,
-                        minecraft_profile,
-                        auth_session,
-                        &version_details.id,
-                        &game_dir,
-                        &assets_dir,
-                        &assets_index,
-                        &assets_path,
-                    )?;
-                    command.arg(arg);
-                }
-                serde_json::Value::Object(obj) => {
-                    // Check if the argument should be included
-                    if should_include_argument(obj) {
-                        if let Some(value) = obj.get("value") {
-                            match value {
-                                serde_json::Value::String(s) => {
-                                    let arg = replace_placeholders(
-                                        s,
-                                        minecraft_profile,
-                                        auth_session,
-                                        &version_details.id,
-                                        &game_dir,
-                                        &assets_dir,
-                                        &assets_index,
-                                        &assets_path,
-                                    )?;
-                                    command.arg(arg);
-                                }
-                                serde_json::Value::Array(arr) => {
-                                    for val in arr {
-                                        if let serde_json::Value::String(s) = val {
-                                            let arg = replace_placeholders(
-                                                s,
-                                                minecraft_profile,
-                                                auth_session,
-                                                &version_details.id,
-                                                &game_dir,
-                                                &assets_dir,
-                                                &assets_index,
-                                                &assets_path,
-                                            )?;
-                                            command.arg(arg);
-                                        }
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
-                }
-                _ => {}
-            }
-        }
+        command.args(resolve_arguments(&arguments.game, environment, replace));
     } else if let Some(minecraft_arguments) = &version_details.minecraft_arguments {
         // For older versions, use minecraft_arguments
-        let args = minecraft_arguments.split(' ');
-        for arg in args {
-            let arg = replace_placeholders(
-                arg,
-                minecraft_profile,
-                auth_session,
-                &version_details.id,
-                &game_dir,
-                &assets_dir,
-                &assets_index,
-                &assets_path,
-            )?;
-            command.arg(arg);
+        for arg in minecraft_arguments.split_whitespace() {
+            command.arg(replace(arg));
+        }
+
+        // Older versions have no resolution arguments of their own
+        if let Some((width, height)) = profile.resolution {
+            command.arg("--width").arg(width.to_string());
+            command.arg("--height").arg(height.to_string());
         }
     }
 
     Ok(())
 }
 
-/// Checks if an argument should be included based on rules
-fn should_include_argument(obj: &serde_json::Map<String, serde_json::Value>) -> bool {
-    // If there are no rules, the argument is always included
-    if !obj.contains_key("rules") {
-        return true;
-    }
-
-    let rules = match obj.get("rules") {
-        Some(serde_json::Value::Array(arr)) => arr,
-        _ => return true,
-    };
-
-    let mut allowed = false;
-
-    for rule in rules {
-        let rule_obj = match rule {
-            serde_json::Value::Object(obj) => obj,
-            _ => continue,
-        };
-
-        let action = match rule_obj.get("action") {
-            Some(serde_json::Value::String(s)) => s.as_str(),
-            _ => continue,
-        };
-
-        let action_allowed = action == "allow";
-
-        // If there's no OS specified, the rule applies to all OSes
-        if !rule_obj.contains_key("os") {
-            allowed = action_allowed;
-            continue;
-        }
-
-        let os = match rule_obj.get("os") {
-            Some(serde_json::Value::Object(obj)) => obj,
-            _ => continue,
-        };
-
-        // Check if the rule applies to the current OS
-        let os_name = match os.get("name") {
-            Some(serde_json::Value::String(s)) => s.as_str(),
-            _ => continue,
-        };
-
-        let current_os = if cfg!(target_os = "windows") {
-            "windows"
-        } else if cfg!(target_os = "macos") {
-            "osx"
-        } else {
-            "linux"
-        };
-
-        if os_name == current_os {
-            allowed = action_allowed;
+/// Resolves a version JSON argument list, skipping entries whose rules don't apply
+///
+/// Entries are plain strings or `{ "rules": [...], "value": ... }` objects whose
+/// value is a string or a list of strings.
+fn resolve_arguments(
+    arguments: &[serde_json::Value],
+    environment: &RuleEnvironment,
+    replace: impl Fn(&str) -> String,
+) -> Vec<String> {
+    let mut resolved = Vec::new();
+
+    for arg in arguments {
+        match arg {
+            serde_json::Value::String(s) => resolved.push(replace(s)),
+            serde_json::Value::Object(obj) => {
+                // Check if the argument should be included
+                if !argument_allowed(obj, environment) {
+                    continue;
+                }
+                match obj.get("value") {
+                    Some(serde_json::Value::String(s)) => resolved.push(replace(s)),
+                    Some(serde_json::Value::Array(arr)) => {
+                        resolved.extend(arr.iter().filter_map(|val| val.as_str()).map(&replace));
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
         }
     }
 
-    allowed
+    resolved
 }
 
 /// Replaces placeholders in arguments
@@ -339,12 +218,13 @@ fn replace_placeholders(
     arg: &str,
     minecraft_profile: &super::auth::MinecraftProfile,
     auth_session: &super::auth::AuthSession,
-    version_id: &str,
+    version_details: &VersionDetails,
     game_dir: &Path,
     assets_dir: &Path,
     assets_index: &str,
     assets_path: &str,
-) -> Result<String> {
+    resolution: Option<(u32, u32)>,
+) -> String {
     // Determine the user type based on whether this is an offline session
     let user_type = if auth_session.is_offline {
         "mojang" // Use "mojang" for offline sessions to avoid demo mode
@@ -352,21 +232,47 @@ fn replace_placeholders(
         "msa" // Use "msa" for Microsoft Account sessions
     };
 
-    let arg = arg.replace("${auth_player_name}", &minecraft_profile.name)
-        .replace("${version_name}", version_id)
+    // Versions before 1.6 take the whole session as one token, "-" means none
+    let legacy_session = if auth_session.is_offline {
+        "-".to_string()
+    } else {
+        format!("token:{}:{}", auth_session.access_token, minecraft_profile.id)
+    };
+
+    let (width, height) = resolution.unwrap_or_default();
+
+    arg.replace("${auth_player_name}", &minecraft_profile.name)
+        .replace("${version_name}", &version_details.id)
         .replace("${game_directory}", &game_dir.to_string_lossy())
         .replace("${assets_root}", &assets_dir.to_string_lossy())
         .replace("${assets_index_name}", assets_index)
         .replace("${auth_uuid}", &minecraft_profile.id)
         .replace("${auth_access_token}", &auth_session.access_token)
         .replace("${user_type}", user_type)
-        .replace("${version_type}", "release")
+        .replace("${version_type}", &version_details.r#type)
         .replace("${user_properties}", "{}")
-        .replace("${auth_session}", &format!("token:{}", auth_session.access_token))
+        .replace("${auth_session}", &legacy_session)
         .replace("${game_assets}", assets_path)
-        .replace("${auth_xuid}", "");
+        .replace("${auth_xuid}", "")
+        .replace("${resolution_width}", &width.to_string())
+        .replace("${resolution_height}", &height.to_string())
+}
 
-    Ok(arg)
+/// Replaces placeholders in JVM arguments
+fn replace_jvm_placeholders(
+    arg: &str,
+    version_id: &str,
+    natives_dir: &Path,
+    libraries_dir: &Path,
+    classpath: &str,
+) -> String {
+    arg.replace("${natives_directory}", &natives_dir.to_string_lossy())
+        .replace("${library_directory}", &libraries_dir.to_string_lossy())
+        .replace("${classpath_separator}", if cfg!(windows) { ";" } else { ":" })
+        .replace("${classpath}", classpath)
+        .replace("${launcher_name}", LAUNCHER_NAME)
+        .replace("${launcher_version}", LAUNCHER_VERSION)
+        .replace("${version_name}", version_id)
 }
 
 /// Gets the Java path
@@ -436,6 +342,134 @@ pub fn get_java_path() -> Result<PathBuf> {
     }
 }
 
+/// Main class of the launchwrapper that alpha, beta and pre-1.6 versions start through
+const LAUNCHWRAPPER_MAIN_CLASS: &str = "net.minecraft.launchwrapper.Launch";
+
+/// Gets the Java major version a Minecraft version needs
+///
+/// Versions from before 1.17 don't name one and run on Java 8.
+pub fn get_required_java_version(version_details: &VersionDetails) -> u32 {
+    version_details.java_version.as_ref().map_or(8, |java_version| java_version.major_version)
+}
+
+/// Checks whether a Java major version can run a Minecraft version
+///
+/// The launchwrapper casts the system class loader, which fails from Java 9
+/// on, so its versions need Java 8 or older. Other versions run on any Java
+/// at least as new as the one they name.
+pub fn is_java_compatible(version_details: &VersionDetails, java_version: u32) -> bool {
+    if version_details.main_class.as_deref() == Some(LAUNCHWRAPPER_MAIN_CLASS) {
+        java_version <= 8
+    } else {
+        java_version >= get_required_java_version(version_details)
+    }
+}
+
+/// Finds an installed Java that can run a Minecraft version
+///
+/// Prefers the newest Java for launchwrapper versions and the oldest that is
+/// new enough for the rest, which is closest to what the version was built
+/// for. Without a match the Java on the PATH is used, except for
+/// launchwrapper versions that can't start on it.
+pub fn find_java(version_details: &VersionDetails) -> Result<PathBuf> {
+    let legacy = version_details.main_class.as_deref() == Some(LAUNCHWRAPPER_MAIN_CLASS);
+    let mut best: Option<(PathBuf, u32)> = None;
+
+    for path in get_java_candidates() {
+        let java_version = match get_java_major_version(&path) {
+            Some(java_version) => java_version,
+            None => continue,
+        };
+        debug!("Java {} at {}", java_version, path.display());
+
+        if !is_java_compatible(version_details, java_version) {
+            continue;
+        }
+
+        let better = match &best {
+            None => true,
+            Some((_, best_version)) if legacy => java_version > *best_version,
+            Some((_, best_version)) => java_version < *best_version,
+        };
+        if better {
+            best = Some((path, java_version));
+        }
+    }
+
+    match best {
+        Some((path, java_version)) => {
+            info!("Using Java {} at {} for {}", java_version, path.display(), version_details.id);
+            Ok(path)
+        },
+        None if legacy => Err(anyhow!(
+            "Minecraft {} needs Java 8. Install it or set the Java path of the profile.",
+            version_details.id
+        )),
+        None => {
+            warn!(
+                "No Java {} or newer found for {}, trying the default Java",
+                get_required_java_version(version_details), version_details.id
+            );
+            get_java_path()
+        },
+    }
+}
+
+/// Gets the major version of a Java executable, e.g. 8 for `1.8.0_392` or 17 for `17.0.2`
+pub fn get_java_major_version(java_path: &Path) -> Option<u32> {
+    let output = Command::new(java_path).arg("-version").output().ok()?;
+
+    // `java -version` prints e.g. `openjdk version "17.0.2" 2022-01-18` to stderr
+    let version_output = String::from_utf8_lossy(&output.stderr);
+    let version = version_output.split('"').nth(1)?;
+
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    match parts.next()?.parse().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+/// Lists the Java executables worth checking: the default one, `JAVA_HOME` and the usual install directories
+fn get_java_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+
+    if let Ok(path) = get_java_path() {
+        candidates.push(path);
+    }
+
+    let java_name = if cfg!(target_os = "windows") { "javaw.exe" } else { "java" };
+    if let Some(java_home) = std::env::var_os("JAVA_HOME") {
+        candidates.push(PathBuf::from(java_home).join("bin").join(java_name));
+    }
+
+    let (install_dirs, home_suffix): (&[&str], &str) = if cfg!(target_os = "windows") {
+        (&["C:\\Program Files\\Java", "C:\\Program Files\\Eclipse Adoptium", "C:\\Program Files (x86)\\Java"], "")
+    } else if cfg!(target_os = "macos") {
+        (&["/Library/Java/JavaVirtualMachines"], "Contents/Home")
+    } else {
+        (&["/usr/lib/jvm", "/usr/lib64/jvm", "/usr/java"], "")
+    };
+
+    for install_dir in install_dirs {
+        let entries = match std::fs::read_dir(install_dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        let mut homes: Vec<PathBuf> = entries.flatten().map(|entry| entry.path().join(home_suffix)).collect();
+        homes.sort();
+        candidates.extend(homes.into_iter()
+            .map(|home| home.join("bin").join(java_name))
+            .filter(|path| path.exists()));
+    }
+
+    // The same Java is often reachable through several links
+    let mut seen = std::collections::HashSet::new();
+    candidates.retain(|path| seen.insert(std::fs::canonicalize(path).unwrap_or_else(|_| path.clone())));
+    candidates
+}
+
 /// Validates the Java installation
 pub fn validate_java() -> Result<String> {
     let java_path = get_java_path()?;
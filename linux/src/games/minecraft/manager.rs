@@ -1,28 +1,26 @@
 // Minecraft manager for the Minecraft game plugin
 
 use anyhow::{Result, anyhow};
-use reqwest::blocking::Client as HttpClient;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::rc::Rc;
 use log::{info, warn, error, debug};
 
 use crate::games::minecraft::auth::AuthSession;
-use crate::config::{Config, Profile, ModLoader};
-use crate::file_manager::{FileManager, DownloadProgress};
+use crate::config::{Config, Game, Profile, ModLoader, EffectiveLaunchSettings, resolve_launch_settings};
+use crate::file_manager::{FileManager, CancellationToken};
+use crate::progress::{InstallProgress, InstallPhase, ProgressTracker};
 
 use super::models::{VersionManifest, VersionDetails, VersionInfo};
+use super::manifest;
 use super::modloaders;
-use super::versions;
+use super::versions::{self, VerifyReport};
 use super::launcher;
-
-// Minecraft version manifest URL
-const VERSION_MANIFEST_URL: &str = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
+use super::cleanup::{self, CleanupPlan};
 
 /// Minecraft manager
 pub struct MinecraftManager {
-    http_client: HttpClient,
     file_manager: FileManager,
     config: Config,
     // Cache for modloader versions
@@ -36,9 +34,12 @@ impl MinecraftManager {
         // Get the Minecraft directory from the config
         let minecraft_directory = Self::get_minecraft_directory_from_config(&config);
 
+        // Downloads use the configured parallelism and mirrors
+        let mut file_manager = (*file_manager).clone();
+        file_manager.configure(&config);
+
         Self {
-            http_client: HttpClient::new(),
-            file_manager: (*file_manager).clone(),
+            file_manager,
             config,
             modloader_versions_cache: HashMap::new(),
             minecraft_directory,
@@ -181,26 +182,24 @@ impl MinecraftManager {
         Ok(())
     }
 
-    /// Get the version manifest
-    pub fn get_version_manifest(&self) -> Result<VersionManifest> {
-        info!("Fetching Minecraft version manifest");
-        let manifest = self.http_client
-            .get(VERSION_MANIFEST_URL)
-            .send()?
-            .json::<VersionManifest>()?;
+    /// Check if offline mode is on
+    pub fn is_offline_mode(&self) -> bool {
+        self.config.offline_mode
+    }
 
-        Ok(manifest)
+    /// Turn offline mode on or off
+    pub fn set_offline_mode(&mut self, offline: bool) {
+        self.config.offline_mode = offline;
     }
 
-    /// Get version details
-    pub fn get_version_details(&self, version_info: &VersionInfo) -> Result<VersionDetails> {
-        info!("Fetching details for Minecraft version {}", version_info.id);
-        let details = self.http_client
-            .get(&version_info.url)
-            .send()?
-            .json::<VersionDetails>()?;
+    /// Get the version manifest, from the cache when it is fresh or the network is down
+    pub async fn get_version_manifest(&self) -> Result<VersionManifest> {
+        manifest::get_version_manifest(&self.file_manager, self.config.offline_mode).await
+    }
 
-        Ok(details)
+    /// Get version details, verified against the manifest's hash
+    pub async fn get_version_details(&self, version_info: &VersionInfo) -> Result<VersionDetails> {
+        manifest::fetch_version_details(&self.file_manager, version_info).await
     }
 
     /// Check if a version is installed
@@ -209,27 +208,225 @@ impl MinecraftManager {
     }
 
     /// Install a version
-    pub async fn install_version(&self, version_id: &str, progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static + Clone) -> Result<()> {
+    ///
+    /// Installs the versions it inherits from as well, and only downloads what
+    /// is missing. Stops with a `Cancelled` error once `cancel` is cancelled.
+    pub async fn install_version(&self, version_id: &str, progress_callback: impl Fn(InstallProgress) + Send + Sync + 'static, cancel: &CancellationToken) -> Result<()> {
+        self.install_version_tracked(version_id, &ProgressTracker::new(progress_callback), cancel).await?;
+        Ok(())
+    }
+
+    /// Install a version, reporting to an existing tracker
+    ///
+    /// Returns the version merged with its parents, ready to launch. Versions
+    /// in the chain without a JSON come from the manifest; the network is
+    /// only used for files that are missing.
+    async fn install_version_tracked(&self, version_id: &str, tracker: &ProgressTracker, cancel: &CancellationToken) -> Result<VersionDetails> {
+        let mut installed = HashSet::new();
+        while let Some(missing_id) = versions::find_missing_version(&self.minecraft_directory, version_id)? {
+            if !installed.insert(missing_id.clone()) {
+                return Err(anyhow!("Version {} is still missing after installing it", missing_id));
+            }
+
+            info!("Version {} is not installed, installing it", missing_id);
+            self.install_from_manifest(&missing_id, tracker, cancel).await?;
+        }
+
+        // Installed versions launch from the JSON saved next to the jar
+        let version_details = versions::load_version(&self.minecraft_directory, version_id)?;
+
+        let missing = versions::get_missing_files(&self.minecraft_directory, &version_details);
+        let complete = versions::is_version_installed(&self.minecraft_directory, version_id);
+        if missing.is_empty() && complete {
+            info!("All files of version {} are present", version_id);
+            return Ok(version_details);
+        }
+
+        // Installs from before the marker, or whose JSON changed, are checked once more
+        if missing.is_empty() {
+            if self.config.offline_mode {
+                warn!("Version {} has no install marker, launching it unchecked while offline", version_id);
+                return Ok(version_details);
+            }
+
+            info!("Version {} has no current install marker, checking its files", version_id);
+            cancel.check()?;
+            versions::download_version(&self.file_manager, &self.minecraft_directory, &version_details, None, tracker, cancel).await?;
+            return Ok(version_details);
+        }
+
+        if self.config.offline_mode {
+            return Err(anyhow!(
+                "{} files of version {} are missing (e.g. {:?}) and offline mode is on",
+                missing.len(), version_id, missing[0]
+            ));
+        }
+
+        // The saved JSONs have all download URLs, so the manifest isn't needed
+        info!("{} files of version {} are missing, downloading them", missing.len(), version_id);
+        cancel.check()?;
+        versions::download_version(&self.file_manager, &self.minecraft_directory, &version_details, None, tracker, cancel).await?;
+
+        Ok(version_details)
+    }
+
+    /// Downloads a vanilla version listed in the manifest
+    async fn install_from_manifest(&self, version_id: &str, tracker: &ProgressTracker, cancel: &CancellationToken) -> Result<()> {
+        if self.config.offline_mode {
+            return Err(anyhow!("Cannot install version {} while offline mode is on", version_id));
+        }
+
         // Get the version manifest
-        let manifest = self.get_version_manifest()?;
+        tracker.set_phase(InstallPhase::Manifest);
+        let manifest = self.get_version_manifest().await?;
 
         // Find the version
         let version_info = manifest.versions.iter()
             .find(|v| v.id == version_id)
             .ok_or_else(|| anyhow!("Version {} not found in manifest", version_id))?;
 
-        // Get the version details
-        let version_details = self.get_version_details(version_info)?;
+        // Get the version details, with the JSON as downloaded to save
+        cancel.check()?;
+        let (version_details, version_json) = manifest::fetch_version_json(&self.file_manager, version_info).await?;
 
         // Download the version
+        cancel.check()?;
         versions::download_version(
             &self.file_manager,
             &self.minecraft_directory,
             &version_details,
-            progress_callback,
+            Some(&version_json),
+            tracker,
+            cancel,
         ).await
     }
 
+    /// Verifies the files of the version a profile launches and downloads the damaged ones again
+    ///
+    /// Returns what the verification found. Files a mod loader installer
+    /// generated are reported but can't be repaired this way.
+    pub async fn verify_profile(&self, profile: &Profile, progress_callback: impl Fn(InstallProgress) + Send + Sync + 'static, cancel: &CancellationToken) -> Result<VerifyReport> {
+        let version_id = self.get_launch_version(profile)?;
+        let version_details = versions::load_version(&self.minecraft_directory, &version_id)?;
+
+        let tracker = ProgressTracker::new(progress_callback);
+
+        // Hashing every file takes a while, keep it off the async workers
+        let report = {
+            let minecraft_dir = self.minecraft_directory.clone();
+            let version_details = version_details.clone();
+            let tracker = tracker.clone();
+            let cancel = cancel.clone();
+            tokio::task::spawn_blocking(move || {
+                versions::verify_version(&minecraft_dir, &version_details, &tracker, &cancel)
+            }).await??
+        };
+        if report.is_intact() {
+            info!("All {} files of version {} are intact", report.checked, version_id);
+            return Ok(report);
+        }
+
+        let downloadable = report.damaged.iter().filter(|file| file.url.is_some()).count();
+        if downloadable > 0 && self.config.offline_mode {
+            return Err(anyhow!(
+                "{} files of version {} are damaged and offline mode is on",
+                downloadable, version_id
+            ));
+        }
+
+        cancel.check()?;
+        versions::repair_version(&self.file_manager, &self.minecraft_directory, &version_details, &report, &tracker, cancel).await?;
+
+        Ok(report)
+    }
+
+    /// Gets the version a profile launches
+    ///
+    /// Profiles with a mod loader launch the version its installer created,
+    /// which inherits from the profile's Minecraft version.
+    pub fn get_launch_version(&self, profile: &Profile) -> Result<String> {
+        let mod_loader = match &profile.mod_loader {
+            Some(mod_loader) if *mod_loader != ModLoader::None => mod_loader,
+            _ => return Ok(profile.version.clone()),
+        };
+
+        versions::find_loader_version(
+            &self.minecraft_directory,
+            &profile.version,
+            mod_loader,
+            profile.mod_loader_version.as_deref(),
+        ).ok_or_else(|| anyhow!(
+            "{:?} {}is not installed for Minecraft {}",
+            mod_loader,
+            profile.mod_loader_version.as_ref().map(|version| format!("{} ", version)).unwrap_or_default(),
+            profile.version
+        ))
+    }
+
+    /// Gets the version a profile launches, running its mod loader's installer if needed
+    ///
+    /// The installers build on the vanilla version, which is installed first.
+    async fn install_launch_version(&self, profile: &Profile, tracker: &ProgressTracker, cancel: &CancellationToken) -> Result<String> {
+        let not_installed = match self.get_launch_version(profile) {
+            Ok(version_id) => return Ok(version_id),
+            Err(e) => e,
+        };
+
+        if self.config.offline_mode {
+            return Err(anyhow!("{} and offline mode is on", not_installed));
+        }
+
+        let mod_loader = profile.mod_loader.clone().unwrap_or(ModLoader::None);
+        info!("{}, running the {:?} installer", not_installed, mod_loader);
+
+        let version_details = self.install_version_tracked(&profile.version, tracker, cancel).await?;
+        let launch_settings = resolve_launch_settings(&self.config, self.get_selected_game(), profile);
+        let java_path = get_java_path(&launch_settings, &version_details)?;
+
+        cancel.check()?;
+        let (file_manager, minecraft_dir) = (&self.file_manager, &self.minecraft_directory);
+        match mod_loader {
+            ModLoader::Forge => modloaders::install_forge(file_manager, minecraft_dir, profile, &version_details, tracker, &java_path, cancel).await?,
+            ModLoader::Fabric => modloaders::install_fabric(file_manager, minecraft_dir, profile, &version_details, tracker, &java_path, cancel).await?,
+            ModLoader::Quilt => modloaders::install_quilt(file_manager, minecraft_dir, profile, &version_details, tracker, &java_path, cancel).await?,
+            ModLoader::NeoForge => modloaders::install_neoforge(file_manager, minecraft_dir, profile, &version_details, tracker, &java_path, cancel).await?,
+            ModLoader::None => return Err(not_installed),
+        }
+
+        self.get_launch_version(profile)
+            .map_err(|e| anyhow!("The {:?} installer finished, but {}", mod_loader, e))
+    }
+
+    /// Finds the versions, libraries and assets no profile uses anymore
+    ///
+    /// Profiles of every game sharing this Minecraft directory count, with the
+    /// version each would launch and the versions it inherits from.
+    pub fn find_unused_files(&self) -> Result<CleanupPlan> {
+        let mut version_ids = Vec::new();
+
+        let profiles = self.config.games.iter()
+            .filter(|game| game.game_directory == self.minecraft_directory)
+            .flat_map(|game| game.profiles.iter());
+        for profile in profiles {
+            version_ids.push(profile.version.clone());
+            match self.get_launch_version(profile) {
+                Ok(version_id) => version_ids.push(version_id),
+                Err(e) => debug!("Profile {} keeps only {}: {}", profile.name, profile.version, e),
+            }
+        }
+
+        cleanup::find_unused_files(&self.minecraft_directory, &version_ids)
+    }
+
+    /// Removes the files no profile uses anymore, returns the space freed
+    ///
+    /// Looks for unused files again rather than trusting an older plan, so
+    /// nothing installed in the meantime is removed.
+    pub fn remove_unused_files(&self) -> Result<u64> {
+        let plan = self.find_unused_files()?;
+        Ok(cleanup::remove_unused_files(&self.minecraft_directory, &plan))
+    }
+
     /// Get available modloader versions for a specific Minecraft version
     pub fn get_modloader_versions(&mut self, mod_loader_type: &str, game_version: &str) -> Result<Vec<String>> {
         // Create a cache key
@@ -256,20 +453,18 @@ impl MinecraftManager {
     }
 
     /// Launch the game
-    pub async fn launch_game(&self, profile: &Profile, auth_session: &AuthSession, progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static + Clone) -> Result<u32> {
-        // Get the version manifest
-        let manifest = self.get_version_manifest()?;
-
-        // Find the version
-        let version_info = manifest.versions.iter()
-            .find(|v| v.id == profile.version)
-            .ok_or_else(|| anyhow!("Version {} not found in manifest", profile.version))?;
+    pub async fn launch_game(&self, profile: &Profile, auth_session: &AuthSession, progress_callback: impl Fn(InstallProgress) + Send + Sync + 'static, cancel: &CancellationToken) -> Result<u32> {
+        // Get the version details, installing whatever is missing
+        let tracker = ProgressTracker::new(progress_callback);
+        let version_id = self.install_launch_version(profile, &tracker, cancel).await?;
+        let version_details = self.install_version_tracked(&version_id, &tracker, cancel).await?;
 
-        // Get the version details
-        let version_details = self.get_version_details(version_info)?;
+        // Last chance to stop before the game process starts
+        cancel.check()?;
 
-        // Get the Java path
-        let java_path = launcher::get_java_path()?;
+        // Resolve the global, game and profile launch settings
+        let launch_settings = resolve_launch_settings(&self.config, self.get_selected_game(), profile);
+        let java_path = get_java_path(&launch_settings, &version_details)?;
 
         // Launch the game
         launcher::launch_game(
@@ -278,9 +473,16 @@ impl MinecraftManager {
             auth_session,
             &version_details,
             &java_path,
+            &launch_settings,
         ).await
     }
 
+    /// Get the selected game entry from the config
+    fn get_selected_game(&self) -> Option<&Game> {
+        let selected_game_id = self.config.selected_game.clone().unwrap_or_else(|| "minecraft".to_string());
+        self.config.games.iter().find(|g| g.id == selected_game_id)
+    }
+
     /// Helper function to get the Minecraft directory from the config
     fn get_minecraft_directory_from_config(config: &Config) -> PathBuf {
         // Get the selected game ID
@@ -307,3 +509,24 @@ impl MinecraftManager {
         game.game_directory.clone()
     }
 }
+
+/// Gets the Java to run a version with, preferring the configured one
+fn get_java_path(launch_settings: &EffectiveLaunchSettings, version_details: &VersionDetails) -> Result<PathBuf> {
+    let path = match &launch_settings.java_path {
+        Some(path) => path,
+        None => return launcher::find_java(version_details),
+    };
+
+    if !path.exists() {
+        return Err(anyhow!("Configured Java executable does not exist: {:?}", path));
+    }
+    if let Some(java_version) = launcher::get_java_major_version(path) {
+        if !launcher::is_java_compatible(version_details, java_version) {
+            warn!(
+                "Configured Java {} may not run Minecraft {}, which was built for Java {}",
+                java_version, version_details.id, launcher::get_required_java_version(version_details)
+            );
+        }
+    }
+    Ok(path.clone())
+}
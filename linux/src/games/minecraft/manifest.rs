@@ -0,0 +1,210 @@
+// Cached Minecraft version manifest for the Minecraft game plugin
+//
+// The manifest is stored on disk and revalidated with ETag/If-Modified-Since,
+// so launching keeps working without a network connection.
+
+use anyhow::{Result, anyhow};
+use directories::ProjectDirs;
+use log::{info, warn, debug};
+use serde::{Deserialize, Serialize};
+use sha1::{Sha1, Digest};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::file_manager::{FileManager, ConditionalResponse};
+use crate::utils::write_file_atomically;
+use super::models::{VersionManifest, VersionDetails, VersionInfo};
+
+// Minecraft version manifest URL
+const VERSION_MANIFEST_URL: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+/// How long a cached manifest is used without asking the server again
+const MANIFEST_MAX_AGE_SECS: u64 = 10 * 60;
+
+/// Validators and fetch time stored next to the cached manifest
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ManifestCacheMeta {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Unix time of the last successful fetch or revalidation
+    fetched_at: u64,
+}
+
+/// Gets the directory the manifest is cached in
+fn get_cache_dir() -> Result<PathBuf> {
+    let proj_dirs = ProjectDirs::from("com", "mosaic", "launcher")
+        .ok_or_else(|| anyhow!("Could not determine cache directory"))?;
+
+    Ok(proj_dirs.cache_dir().join("minecraft"))
+}
+
+/// Gets the path of the cached manifest
+fn get_manifest_file() -> Result<PathBuf> {
+    Ok(get_cache_dir()?.join("version_manifest_v2.json"))
+}
+
+/// Gets the path of the cached manifest's validators
+fn get_manifest_meta_file() -> Result<PathBuf> {
+    Ok(get_cache_dir()?.join("version_manifest_v2.meta.json"))
+}
+
+/// Gets the current Unix time in seconds
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Reads the cached manifest and its validators, if both are usable
+fn read_cached_manifest() -> Option<(VersionManifest, ManifestCacheMeta)> {
+    let manifest_json = fs::read_to_string(get_manifest_file().ok()?).ok()?;
+    let manifest = match serde_json::from_str::<VersionManifest>(&manifest_json) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            warn!("Ignoring unreadable cached version manifest: {}", e);
+            return None;
+        }
+    };
+
+    // Without validators the manifest is still usable, it just can't be revalidated
+    let meta = get_manifest_meta_file().ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str::<ManifestCacheMeta>(&json).ok())
+        .unwrap_or_default();
+
+    Some((manifest, meta))
+}
+
+/// Writes the validators for the cached manifest
+fn write_manifest_meta(meta: &ManifestCacheMeta) -> Result<()> {
+    let meta_json = serde_json::to_string_pretty(meta)?;
+    write_file_atomically(get_manifest_meta_file()?, meta_json.as_bytes())
+}
+
+/// Gets the Minecraft version manifest
+///
+/// A recently fetched copy is used as-is, an older one is revalidated with the
+/// server. When the server can't be reached, or `offline` is set, the cached
+/// copy is used no matter how old it is.
+pub async fn get_version_manifest(file_manager: &FileManager, offline: bool) -> Result<VersionManifest> {
+    let cached = read_cached_manifest();
+
+    if offline {
+        return match cached {
+            Some((manifest, _)) => {
+                debug!("Offline mode, using cached version manifest");
+                Ok(manifest)
+            },
+            None => Err(anyhow!("Offline mode is on and no version manifest is cached")),
+        };
+    }
+
+    if let Some((manifest, meta)) = &cached {
+        let age = now_secs().saturating_sub(meta.fetched_at);
+        if age < MANIFEST_MAX_AGE_SECS {
+            debug!("Using cached version manifest ({}s old)", age);
+            return Ok(manifest.clone());
+        }
+    }
+
+    info!("Fetching Minecraft version manifest");
+
+    // Only send validators when there is a cached copy to fall back on
+    let (etag, last_modified) = match &cached {
+        Some((_, meta)) => (meta.etag.as_deref(), meta.last_modified.as_deref()),
+        None => (None, None),
+    };
+
+    let response = file_manager.download_string_if_modified(VERSION_MANIFEST_URL, etag, last_modified).await;
+
+    match response {
+        Ok(ConditionalResponse::NotModified) => {
+            let (manifest, mut meta) = cached
+                .ok_or_else(|| anyhow!("Server reported an unchanged manifest but none is cached"))?;
+
+            meta.fetched_at = now_secs();
+            if let Err(e) = write_manifest_meta(&meta) {
+                warn!("Failed to update version manifest cache: {}", e);
+            }
+
+            info!("Cached version manifest is up to date");
+            Ok(manifest)
+        },
+        Ok(ConditionalResponse::Modified { body, etag, last_modified }) => {
+            let manifest = match serde_json::from_str::<VersionManifest>(&body) {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    return match cached {
+                        Some((manifest, _)) => {
+                            warn!("Downloaded version manifest is invalid ({}), using the cached copy", e);
+                            Ok(manifest)
+                        },
+                        None => Err(anyhow!("Failed to parse version manifest: {}", e)),
+                    };
+                }
+            };
+
+            // Store the manifest before its validators so they never describe a different copy
+            let meta = ManifestCacheMeta { etag, last_modified, fetched_at: now_secs() };
+            let stored = get_manifest_file()
+                .and_then(|path| write_file_atomically(path, body.as_bytes()))
+                .and_then(|_| write_manifest_meta(&meta));
+            if let Err(e) = stored {
+                warn!("Failed to cache version manifest: {}", e);
+            }
+
+            info!("Fetched version manifest with {} versions", manifest.versions.len());
+            Ok(manifest)
+        },
+        Err(e) => match cached {
+            Some((manifest, meta)) => {
+                warn!(
+                    "Failed to fetch version manifest ({}), using the cached copy from {} seconds ago",
+                    e,
+                    now_secs().saturating_sub(meta.fetched_at)
+                );
+                Ok(manifest)
+            },
+            None => Err(anyhow!("Failed to fetch version manifest and no cached copy is available: {}", e)),
+        },
+    }
+}
+
+/// Fetches the details for a version listed in the manifest
+///
+/// The JSON is checked against the manifest's `sha1` before it is parsed.
+pub async fn fetch_version_details(file_manager: &FileManager, version_info: &VersionInfo) -> Result<VersionDetails> {
+    Ok(fetch_version_json(file_manager, version_info).await?.0)
+}
+
+/// Fetches the details for a version together with its JSON as downloaded
+///
+/// The JSON is what gets saved, it is the one that matches the manifest's `sha1`.
+pub async fn fetch_version_json(file_manager: &FileManager, version_info: &VersionInfo) -> Result<(VersionDetails, String)> {
+    info!("Fetching details for Minecraft version {}", version_info.id);
+
+    let details_json = file_manager.download_string(&version_info.url).await?;
+
+    match &version_info.sha1 {
+        Some(expected_hash) => {
+            let mut hasher = Sha1::new();
+            hasher.update(details_json.as_bytes());
+            let hash = format!("{:x}", hasher.finalize());
+
+            if !hash.eq_ignore_ascii_case(expected_hash) {
+                return Err(anyhow!(
+                    "Version JSON for {} failed verification: expected sha1 {}, got {}",
+                    version_info.id, expected_hash, hash
+                ));
+            }
+        },
+        None => warn!("Manifest has no sha1 for version {}, skipping verification", version_info.id),
+    }
+
+    let details: VersionDetails = serde_json::from_str(&details_json)
+        .map_err(|e| anyhow!("Failed to parse version JSON for {}: {}", version_info.id, e))?;
+
+    Ok((details, details_json))
+}
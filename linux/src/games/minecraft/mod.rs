@@ -5,10 +5,16 @@ mod manager;
 mod modloaders;
 mod versions;
 mod launcher;
+mod manifest;
+mod rules;
+mod cleanup;
+pub mod importers;
+pub mod modpacks;
 pub mod auth;
 pub mod ui;
 
 use anyhow::Result;
+use log::info;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::rc::Rc;
@@ -16,14 +22,17 @@ use std::cell::RefCell;
 use gtk4 as gtk;
 use libadwaita as adw;
 use crate::config::{Config, Profile};
-use crate::file_manager::{DownloadProgress, FileManager};
+use crate::file_manager::{FileManager, CancellationToken};
+use crate::progress::InstallProgress;
 use self::auth::{AuthManager, AuthSession};
-use crate::games::{GamePlugin, GamePluginFactory, GamePluginUIType};
+use crate::games::{GamePlugin, GamePluginFactory, GamePluginManager, GamePluginUIType};
 use crate::mods::ModManager;
 use crate::games::minecraft::ui::login::build_login_view;
 
 pub use self::manager::MinecraftManager;
 pub use self::models::VersionManifest;
+pub use self::versions::{VerifyReport, DamagedFile, FileIssue};
+pub use self::cleanup::{CleanupPlan, UnusedEntry};
 
 /// Minecraft game plugin
 pub struct MinecraftPlugin {
@@ -88,16 +97,17 @@ impl GamePlugin for MinecraftPlugin {
         &self,
         profile: &Profile,
         auth_session: &AuthSession,
-        progress_callback: Box<dyn Fn(DownloadProgress) + Send + Sync + 'static>,
+        progress_callback: Box<dyn Fn(InstallProgress) + Send + Sync + 'static>,
+        cancel: &CancellationToken,
     ) -> Result<u32> {
         let callback = Arc::new(progress_callback);
         let callback_clone = Arc::clone(&callback);
-        let wrapper = move |progress: DownloadProgress| {
+        let wrapper = move |progress: InstallProgress| {
             callback_clone(progress);
         };
         // Create a tokio runtime to run the async method
         let rt = tokio::runtime::Runtime::new()?;
-        rt.block_on(self.manager.launch_game(profile, auth_session, wrapper))
+        rt.block_on(self.manager.launch_game(profile, auth_session, wrapper, cancel))
     }
 
     fn is_version_installed(&self, version_id: &str) -> bool {
@@ -105,7 +115,9 @@ impl GamePlugin for MinecraftPlugin {
     }
 
     fn get_available_versions(&self) -> Result<Vec<String>> {
-        let manifest = self.manager.get_version_manifest()?;
+        // Create a tokio runtime to run the async method
+        let rt = tokio::runtime::Runtime::new()?;
+        let manifest = rt.block_on(self.manager.get_version_manifest())?;
         let versions = manifest.versions.iter().map(|v| v.id.clone()).collect();
         Ok(versions)
     }
@@ -117,17 +129,18 @@ impl GamePlugin for MinecraftPlugin {
     fn install_version(
         &self,
         version_id: &str,
-        progress_callback: Box<dyn Fn(DownloadProgress) + Send + Sync + 'static>,
+        progress_callback: Box<dyn Fn(InstallProgress) + Send + Sync + 'static>,
+        cancel: &CancellationToken,
     ) -> Result<()> {
         let callback = Arc::new(progress_callback);
         let callback_clone = Arc::clone(&callback);
-        let wrapper = move |progress: DownloadProgress| {
+        let wrapper = move |progress: InstallProgress| {
             callback_clone(progress);
         };
 
         // Create a tokio runtime to run the async method
         let rt = tokio::runtime::Runtime::new()?;
-        rt.block_on(self.manager.install_version(version_id, wrapper))
+        rt.block_on(self.manager.install_version(version_id, wrapper, cancel))
     }
 
     fn get_ui_type(&self) -> GamePluginUIType {
@@ -150,8 +163,33 @@ impl GamePlugin for MinecraftPlugin {
         auth_session: Arc<Mutex<Option<AuthSession>>>,
         config: Rc<RefCell<Config>>,
     ) -> Result<()> {
+        // Skip the login view if a usable session was restored
+        let has_session = auth_session.lock().unwrap().as_ref().map(|s| s.is_usable()).unwrap_or(false);
+        if has_session {
+            let main_view_name = "minecraft_main";
+
+            if stack.child_by_name(main_view_name).is_none() {
+                let file_manager = Rc::new(FileManager::new());
+                let game_plugin_manager = Arc::new(Mutex::new(GamePluginManager::new(config.clone(), file_manager.clone())));
+
+                let main_view = self::ui::main_view::build_main_view(
+                    window,
+                    toast_overlay,
+                    config,
+                    game_plugin_manager,
+                    file_manager,
+                    auth_session,
+                );
+                stack.add_named(&main_view, Some(main_view_name));
+            }
+
+            info!("Using stored auth session, skipping the login view");
+            stack.set_visible_child_name(main_view_name);
+            return Ok(());
+        }
+
         // Create a new AuthManager
-        let auth_manager = Rc::new(AuthManager::new());
+        let auth_manager = Rc::new(AuthManager::new(&config.borrow().proxy));
 
         // Build the login view
         let login_view = build_login_view(
@@ -1,7 +1,7 @@
 // Data models for the Minecraft game plugin
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Minecraft version manifest
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +19,7 @@ pub struct LatestVersions {
 
 /// Minecraft version information
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct VersionInfo {
     pub id: String,
     pub r#type: String,
@@ -26,29 +27,129 @@ pub struct VersionInfo {
     pub time: String,
     #[serde(default)]
     pub release_time: String,
+    /// Hash of the version JSON, only in the v2 manifest
+    #[serde(default)]
+    pub sha1: Option<String>,
 }
 
 /// Minecraft version details
+///
+/// Uses the field names of Mojang's version JSON. The aliases read files saved
+/// by older launcher versions, which wrote snake_case names.
+///
+/// Mod loaders install a version that only lists what they add and names the
+/// vanilla version in `inheritsFrom`; see `inherit` for how the two combine.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct VersionDetails {
     pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherits_from: Option<String>,
+    /// Version whose client jar is used, when it isn't this version's own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jar: Option<String>,
+    #[serde(default)]
     pub r#type: String,
-    pub time: String,
     #[serde(default)]
+    pub time: String,
+    #[serde(default, alias = "release_time")]
     pub release_time: String,
+    #[serde(alias = "main_class")]
     pub main_class: Option<String>,
+    #[serde(alias = "minimum_launcher_version")]
     pub minimum_launcher_version: Option<u32>,
+    #[serde(default)]
     pub assets: String,
+    #[serde(rename = "assetIndex", alias = "assets_index")]
     pub assets_index: Option<AssetIndex>,
+    #[serde(default)]
     pub downloads: HashMap<String, Download>,
+    #[serde(default)]
     pub libraries: Vec<Library>,
     pub logging: Option<Logging>,
     pub arguments: Option<Arguments>,
+    #[serde(alias = "minecraft_arguments")]
     pub minecraft_arguments: Option<String>,
+    /// Java the version was built for, missing before 1.17
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub java_version: Option<JavaVersion>,
+}
+
+impl VersionDetails {
+    /// Gets the id of the version whose jar is the client jar
+    pub fn get_jar_id(&self) -> &str {
+        self.jar.as_deref().unwrap_or(&self.id)
+    }
+
+    /// Merges this version over the version it inherits from
+    ///
+    /// Fields set here win over the parent's. Libraries are listed child first
+    /// and replace parent libraries with the same group, artifact and
+    /// classifier; argument lists are appended to the parent's. The result
+    /// keeps this version's id and takes over the parent's `inheritsFrom`, so
+    /// a longer chain is resolved by merging one parent at a time.
+    pub fn inherit(self, parent: VersionDetails) -> VersionDetails {
+        let child_libraries: HashSet<String> = self.libraries.iter()
+            .map(|library| library.get_key())
+            .collect();
+        let mut libraries = self.libraries;
+        libraries.extend(parent.libraries.into_iter()
+            .filter(|library| !child_libraries.contains(&library.get_key())));
+
+        let arguments = match (parent.arguments, self.arguments) {
+            (Some(mut parent_arguments), Some(arguments)) => {
+                parent_arguments.game.extend(arguments.game);
+                parent_arguments.jvm.extend(arguments.jvm);
+                Some(parent_arguments)
+            },
+            (parent_arguments, arguments) => arguments.or(parent_arguments),
+        };
+
+        // Without a client download of its own the child runs on the nearest parent's jar
+        let jar = match self.jar {
+            Some(jar) => Some(jar),
+            None if self.downloads.contains_key("client") => None,
+            None if parent.jar.is_some() => parent.jar,
+            None if parent.downloads.contains_key("client") => Some(parent.id),
+            None => None,
+        };
+
+        let mut downloads = parent.downloads;
+        downloads.extend(self.downloads);
+
+        VersionDetails {
+            id: self.id,
+            inherits_from: parent.inherits_from,
+            jar,
+            r#type: if self.r#type.is_empty() { parent.r#type } else { self.r#type },
+            time: if self.time.is_empty() { parent.time } else { self.time },
+            release_time: if self.release_time.is_empty() { parent.release_time } else { self.release_time },
+            main_class: self.main_class.or(parent.main_class),
+            minimum_launcher_version: self.minimum_launcher_version.or(parent.minimum_launcher_version),
+            assets: if self.assets.is_empty() { parent.assets } else { self.assets },
+            assets_index: self.assets_index.or(parent.assets_index),
+            downloads,
+            libraries,
+            logging: self.logging.or(parent.logging),
+            arguments,
+            minecraft_arguments: self.minecraft_arguments.or(parent.minecraft_arguments),
+            java_version: self.java_version.or(parent.java_version),
+        }
+    }
+}
+
+/// Java runtime a version needs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JavaVersion {
+    #[serde(default)]
+    pub component: String,
+    pub major_version: u32,
 }
 
 /// Asset index information
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AssetIndex {
     pub id: String,
     pub sha1: String,
@@ -63,6 +164,9 @@ pub struct Download {
     pub sha1: String,
     pub size: u64,
     pub url: String,
+    /// Path below the libraries directory, only given for library artifacts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
 }
 
 /// Library information
@@ -70,11 +174,31 @@ pub struct Download {
 pub struct Library {
     pub name: String,
     pub downloads: Option<LibraryDownloads>,
+    /// Maven repository for libraries without `downloads`, as used by mod loaders
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
     pub rules: Option<Vec<Rule>>,
     pub natives: Option<HashMap<String, String>>,
     pub extract: Option<Extract>,
 }
 
+impl Library {
+    /// Gets the group, artifact and classifier, which identify a library across versions
+    pub fn get_key(&self) -> String {
+        let coordinates = self.name.split('@').next().unwrap_or(&self.name);
+        let parts: Vec<&str> = coordinates.split(':').collect();
+        match parts.as_slice() {
+            [group, artifact, _version, classifier, ..] => format!("{}:{}:{}", group, artifact, classifier),
+            [group, artifact, ..] => format!("{}:{}", group, artifact),
+            _ => self.name.clone(),
+        }
+    }
+}
+
 /// Library downloads
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LibraryDownloads {
@@ -130,7 +254,9 @@ pub struct LoggingFile {
 /// Arguments information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Arguments {
+    #[serde(default)]
     pub game: Vec<serde_json::Value>,
+    #[serde(default)]
     pub jvm: Vec<serde_json::Value>,
 }
 
@@ -138,6 +264,12 @@ pub struct Arguments {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssetObjects {
     pub objects: HashMap<String, AssetObject>,
+    /// Set by the `legacy` index, assets are read by name from `assets/virtual/<index>`
+    #[serde(default, rename = "virtual", skip_serializing_if = "std::ops::Not::not")]
+    pub is_virtual: bool,
+    /// Set by the `pre-1.6` index, assets are read by name from `<game dir>/resources`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub map_to_resources: bool,
 }
 
 /// Asset object information
@@ -5,11 +5,24 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 use log::{info, warn, error, debug};
 use std::fs;
+use std::collections::HashMap;
+use std::io::Read;
+use serde::Deserialize;
 
 use crate::config::{Profile, ModLoader};
-use crate::file_manager::{FileManager, DownloadProgress};
+use crate::file_manager::{FileManager, CancellationToken, Cancelled, is_cancelled_error};
+use crate::progress::{ProgressTracker, InstallPhase};
 
 use super::models::VersionDetails;
+use super::versions::get_maven_path;
+
+/// Loader version that picks the newest loader instead of pinning one
+pub const LATEST_LOADER_VERSION: &str = "latest";
+
+/// Gets the loader version a profile pins, `None` for the newest one
+fn get_pinned_loader_version(profile: &Profile) -> Option<&str> {
+    profile.mod_loader_version.as_deref().filter(|version| *version != LATEST_LOADER_VERSION)
+}
 
 /// Fetch available Forge versions for a given Minecraft version
 pub fn fetch_forge_versions(minecraft_version: &str) -> Result<Vec<String>> {
@@ -74,7 +87,7 @@ pub fn fetch_fabric_versions(minecraft_version: &str) -> Result<Vec<String>> {
     }
 
     // Add a "latest" option
-    versions.push("latest".to_string());
+    versions.push(LATEST_LOADER_VERSION.to_string());
 
     Ok(versions)
 }
@@ -98,7 +111,7 @@ pub fn fetch_quilt_versions(minecraft_version: &str) -> Result<Vec<String>> {
     }
 
     // Add a "latest" option
-    versions.push("latest".to_string());
+    versions.push(LATEST_LOADER_VERSION.to_string());
 
     Ok(versions)
 }
@@ -149,10 +162,12 @@ pub async fn install_forge(
     minecraft_dir: &Path,
     profile: &Profile,
     version_details: &VersionDetails,
-    progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static + Clone,
+    tracker: &ProgressTracker,
     java_path: &Path,
+    cancel: &CancellationToken,
 ) -> Result<()> {
     info!("Installing Forge for Minecraft version {}", version_details.id);
+    tracker.set_phase(InstallPhase::Loader);
 
     // Get the Forge version from the profile or use a recommended version for this Minecraft version
     let forge_version = if let Some(version) = &profile.mod_loader_version {
@@ -199,6 +214,8 @@ pub async fn install_forge(
 
     let installer_path = forge_dir.join(format!("forge-{}-installer.jar", forge_version));
 
+    tracker.add_file(&forge_installer_url, None);
+
     // Maximum number of retry attempts
     const MAX_RETRIES: usize = 3;
     let mut retry_count = 0;
@@ -206,6 +223,8 @@ pub async fn install_forge(
 
     // Try to download and verify the installer
     while retry_count < MAX_RETRIES {
+        cancel.check()?;
+
         if retry_count > 0 {
             info!("Retry attempt {} of {} for downloading Forge installer", retry_count, MAX_RETRIES);
             // Wait a bit before retrying
@@ -221,11 +240,11 @@ pub async fn install_forge(
 
         // Download the installer
         info!("Downloading Forge installer from {}", forge_installer_url);
-        match file_manager.download_file(
+        match file_manager.with_cancellation(cancel).download_file(
             &forge_installer_url,
             &installer_path,
             None, // No hash verification for now
-            progress_callback.clone(),
+            tracker.download_callback(),
         ).await {
             Ok(()) => {
                 // Verify the downloaded file is a valid JAR file
@@ -249,6 +268,7 @@ pub async fn install_forge(
                     }
                 }
             },
+            Err(e) if is_cancelled_error(&e) => return Err(e),
             Err(e) => {
                 // Error during download, try again
                 warn!("Error downloading Forge installer: {}. Retrying...", e);
@@ -258,6 +278,8 @@ pub async fn install_forge(
         }
     }
 
+    tracker.finish_file(&forge_installer_url);
+
     // Check if we've exhausted all retry attempts
     if retry_count >= MAX_RETRIES {
         let error_msg = last_error.unwrap_or_else(|| anyhow!("Failed to download and verify Forge installer after {} attempts", MAX_RETRIES));
@@ -277,12 +299,13 @@ pub async fn install_forge(
 
     // Run the installer
     info!("Running Forge installer");
-    let status = Command::new(java_path)
+    let mut installer = Command::new(java_path);
+    installer
         .current_dir(minecraft_dir)
         .arg("-jar")
         .arg(&installer_path)
-        .arg("--installClient")
-        .status()?;
+        .arg("--installClient");
+    let status = run_installer(&mut installer, cancel).await?;
 
     if !status.success() {
         return Err(anyhow!("Forge installation failed with exit code: {}", status));
@@ -298,10 +321,12 @@ pub async fn install_fabric(
     minecraft_dir: &Path,
     profile: &Profile,
     version_details: &VersionDetails,
-    progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static + Clone,
+    tracker: &ProgressTracker,
     java_path: &Path,
+    cancel: &CancellationToken,
 ) -> Result<()> {
     info!("Installing Fabric for Minecraft version {}", version_details.id);
+    tracker.set_phase(InstallPhase::Loader);
 
     // Fabric installer URL
     let fabric_installer_url = "https://maven.fabricmc.net/net/fabricmc/fabric-installer/0.11.2/fabric-installer-0.11.2.jar";
@@ -321,6 +346,8 @@ pub async fn install_fabric(
 
     let installer_path = fabric_dir.join("fabric-installer.jar");
 
+    tracker.add_file(&fabric_installer_url, None);
+
     // Maximum number of retry attempts
     const MAX_RETRIES: usize = 3;
     let mut retry_count = 0;
@@ -328,6 +355,8 @@ pub async fn install_fabric(
 
     // Try to download and verify the installer
     while retry_count < MAX_RETRIES {
+        cancel.check()?;
+
         if retry_count > 0 {
             info!("Retry attempt {} of {} for downloading Fabric installer", retry_count, MAX_RETRIES);
             // Wait a bit before retrying
@@ -343,11 +372,11 @@ pub async fn install_fabric(
 
         // Download the installer
         info!("Downloading Fabric installer from {}", fabric_installer_url);
-        match file_manager.download_file(
+        match file_manager.with_cancellation(cancel).download_file(
             &fabric_installer_url,
             &installer_path,
             None, // No hash verification for now
-            progress_callback.clone(),
+            tracker.download_callback(),
         ).await {
             Ok(()) => {
                 // Verify the downloaded file is a valid JAR file
@@ -371,6 +400,7 @@ pub async fn install_fabric(
                     }
                 }
             },
+            Err(e) if is_cancelled_error(&e) => return Err(e),
             Err(e) => {
                 // Error during download, try again
                 warn!("Error downloading Fabric installer: {}. Retrying...", e);
@@ -380,6 +410,8 @@ pub async fn install_fabric(
         }
     }
 
+    tracker.finish_file(&fabric_installer_url);
+
     // Check if we've exhausted all retry attempts
     if retry_count >= MAX_RETRIES {
         let error_msg = last_error.unwrap_or_else(|| anyhow!("Failed to download and verify Fabric installer after {} attempts", MAX_RETRIES));
@@ -401,7 +433,8 @@ pub async fn install_fabric(
     info!("Running Fabric installer");
 
     // Run the installer with verbose output to help diagnose issues
-    let status = Command::new(java_path)
+    let mut installer = Command::new(java_path);
+    installer
         .current_dir(minecraft_dir)
         .arg("-jar")
         .arg(&installer_path)
@@ -411,8 +444,11 @@ pub async fn install_fabric(
         .arg("-dir")
         .arg(minecraft_dir)
         .arg("-noprofile") // Don't create a launcher profile
-        .arg("-verbose") // Enable verbose output
-        .status()?;
+        .arg("-verbose"); // Enable verbose output
+    if let Some(loader_version) = get_pinned_loader_version(profile) {
+        installer.arg("-loader").arg(loader_version);
+    }
+    let status = run_installer(&mut installer, cancel).await?;
 
     if !status.success() {
         return Err(anyhow!("Fabric installation failed with exit code: {}", status));
@@ -428,10 +464,12 @@ pub async fn install_quilt(
     minecraft_dir: &Path,
     profile: &Profile,
     version_details: &VersionDetails,
-    progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static + Clone,
+    tracker: &ProgressTracker,
     java_path: &Path,
+    cancel: &CancellationToken,
 ) -> Result<()> {
     info!("Installing Quilt for Minecraft version {}", version_details.id);
+    tracker.set_phase(InstallPhase::Loader);
 
     // Quilt installer URL - use a specific version instead of "latest"
     let quilt_installer_url = "https://maven.quiltmc.org/repository/release/org/quiltmc/quilt-installer/0.8.1/quilt-installer-0.8.1.jar";
@@ -442,6 +480,8 @@ pub async fn install_quilt(
 
     let installer_path = quilt_dir.join("quilt-installer.jar");
 
+    tracker.add_file(&quilt_installer_url, None);
+
     // Maximum number of retry attempts
     const MAX_RETRIES: usize = 3;
     let mut retry_count = 0;
@@ -449,6 +489,8 @@ pub async fn install_quilt(
 
     // Try to download and verify the installer
     while retry_count < MAX_RETRIES {
+        cancel.check()?;
+
         if retry_count > 0 {
             info!("Retry attempt {} of {} for downloading Quilt installer", retry_count, MAX_RETRIES);
             // Wait a bit before retrying
@@ -464,11 +506,11 @@ pub async fn install_quilt(
 
         // Download the installer
         info!("Downloading Quilt installer from {}", quilt_installer_url);
-        match file_manager.download_file(
+        match file_manager.with_cancellation(cancel).download_file(
             &quilt_installer_url,
             &installer_path,
             None, // No hash verification for now
-            progress_callback.clone(),
+            tracker.download_callback(),
         ).await {
             Ok(()) => {
                 // Verify the downloaded file is a valid JAR file
@@ -492,6 +534,7 @@ pub async fn install_quilt(
                     }
                 }
             },
+            Err(e) if is_cancelled_error(&e) => return Err(e),
             Err(e) => {
                 // Error during download, try again
                 warn!("Error downloading Quilt installer: {}. Retrying...", e);
@@ -501,6 +544,8 @@ pub async fn install_quilt(
         }
     }
 
+    tracker.finish_file(&quilt_installer_url);
+
     // Check if we've exhausted all retry attempts
     if retry_count >= MAX_RETRIES {
         let error_msg = last_error.unwrap_or_else(|| anyhow!("Failed to download and verify Quilt installer after {} attempts", MAX_RETRIES));
@@ -521,16 +566,21 @@ pub async fn install_quilt(
 
     // Run the installer
     info!("Running Quilt installer");
-    let status = Command::new(java_path)
+    let mut installer = Command::new(java_path);
+    installer
         .current_dir(minecraft_dir)
         .arg("-jar")
         .arg(&installer_path)
         .arg("install")
         .arg("client")
-        .arg(&version_details.id)
+        .arg(&version_details.id);
+    if let Some(loader_version) = get_pinned_loader_version(profile) {
+        installer.arg(loader_version);
+    }
+    installer
         .arg("--install-dir")
-        .arg(minecraft_dir)
-        .status()?;
+        .arg(minecraft_dir);
+    let status = run_installer(&mut installer, cancel).await?;
 
     if !status.success() {
         return Err(anyhow!("Quilt installation failed with exit code: {}", status));
@@ -546,10 +596,12 @@ pub async fn install_neoforge(
     minecraft_dir: &Path,
     profile: &Profile,
     version_details: &VersionDetails,
-    progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static + Clone,
+    tracker: &ProgressTracker,
     java_path: &Path,
+    cancel: &CancellationToken,
 ) -> Result<()> {
     info!("Installing NeoForge for Minecraft version {}", version_details.id);
+    tracker.set_phase(InstallPhase::Loader);
 
     // Get the NeoForge version from the profile or use a recommended version for this Minecraft version
     let neoforge_version = if let Some(version) = &profile.mod_loader_version {
@@ -592,6 +644,8 @@ pub async fn install_neoforge(
 
     let installer_path = neoforge_dir.join(format!("neoforge-{}-installer.jar", neoforge_version));
 
+    tracker.add_file(&neoforge_installer_url, None);
+
     // Maximum number of retry attempts
     const MAX_RETRIES: usize = 3;
     let mut retry_count = 0;
@@ -599,6 +653,8 @@ pub async fn install_neoforge(
 
     // Try to download and verify the installer
     while retry_count < MAX_RETRIES {
+        cancel.check()?;
+
         if retry_count > 0 {
             info!("Retry attempt {} of {} for downloading NeoForge installer", retry_count, MAX_RETRIES);
             // Wait a bit before retrying
@@ -614,11 +670,11 @@ pub async fn install_neoforge(
 
         // Download the installer
         info!("Downloading NeoForge installer from {}", neoforge_installer_url);
-        match file_manager.download_file(
+        match file_manager.with_cancellation(cancel).download_file(
             &neoforge_installer_url,
             &installer_path,
             None, // No hash verification for now
-            progress_callback.clone(),
+            tracker.download_callback(),
         ).await {
             Ok(()) => {
                 // Verify the downloaded file is a valid JAR file
@@ -642,6 +698,7 @@ pub async fn install_neoforge(
                     }
                 }
             },
+            Err(e) if is_cancelled_error(&e) => return Err(e),
             Err(e) => {
                 // Error during download, try again
                 warn!("Error downloading NeoForge installer: {}. Retrying...", e);
@@ -651,6 +708,8 @@ pub async fn install_neoforge(
         }
     }
 
+    tracker.finish_file(&neoforge_installer_url);
+
     // Check if we've exhausted all retry attempts
     if retry_count >= MAX_RETRIES {
         let error_msg = last_error.unwrap_or_else(|| anyhow!("Failed to download and verify NeoForge installer after {} attempts", MAX_RETRIES));
@@ -671,12 +730,13 @@ pub async fn install_neoforge(
 
     // Run the installer
     info!("Running NeoForge installer");
-    let status = Command::new(java_path)
+    let mut installer = Command::new(java_path);
+    installer
         .current_dir(minecraft_dir)
         .arg("-jar")
         .arg(&installer_path)
-        .arg("--installClient")
-        .status()?;
+        .arg("--installClient");
+    let status = run_installer(&mut installer, cancel).await?;
 
     if !status.success() {
         return Err(anyhow!("NeoForge installation failed with exit code: {}", status));
@@ -686,6 +746,76 @@ pub async fn install_neoforge(
     Ok(())
 }
 
+/// Runs a mod loader installer, killing it if the install is cancelled
+async fn run_installer(command: &mut Command, cancel: &CancellationToken) -> Result<std::process::ExitStatus> {
+    let mut child = command.spawn()?;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+
+        if cancel.is_cancelled() {
+            warn!("Installer cancelled, stopping it");
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(Cancelled.into());
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+    }
+}
+
+/// The parts of a Forge or NeoForge `install_profile.json` that name generated files
+#[derive(Deserialize)]
+struct InstallProfile {
+    /// The version id the installer creates
+    version: String,
+    #[serde(default)]
+    data: HashMap<String, InstallData>,
+    #[serde(default)]
+    processors: Vec<InstallProcessor>,
+}
+
+#[derive(Deserialize)]
+struct InstallData {
+    #[serde(default)]
+    client: String,
+}
+
+#[derive(Deserialize)]
+struct InstallProcessor {
+    sides: Option<Vec<String>>,
+    /// Files the processor writes, keyed by a `{DATA}` reference or `[maven coordinates]`
+    #[serde(default)]
+    outputs: HashMap<String, String>,
+}
+
+/// Reads the version a Forge or NeoForge installer creates and the library paths its processors write
+///
+/// The processors run while installing and write jars, e.g. the deobfuscated
+/// client, that the version JSON doesn't list. Paths are relative to `libraries/`.
+pub fn read_installer_outputs(installer_path: &Path) -> Result<(String, Vec<String>)> {
+    let mut archive = zip::ZipArchive::new(fs::File::open(installer_path)?)?;
+    let mut json = String::new();
+    archive.by_name("install_profile.json")?.read_to_string(&mut json)?;
+    let profile: InstallProfile = serde_json::from_str(&json)?;
+
+    let outputs = profile.processors.iter()
+        .filter(|processor| processor.sides.as_ref().is_none_or(|sides| sides.iter().any(|side| side == "client")))
+        .flat_map(|processor| processor.outputs.keys())
+        .filter_map(|output| {
+            let artifact = match output.strip_prefix('{').and_then(|key| key.strip_suffix('}')) {
+                Some(key) => profile.data.get(key)?.client.as_str(),
+                None => output.as_str(),
+            };
+            get_maven_path(artifact.strip_prefix('[')?.strip_suffix(']')?)
+        })
+        .collect();
+
+    Ok((profile.version, outputs))
+}
+
 /// Verifies that a file is a valid JAR file by checking its ZIP structure
 async fn verify_jar_file(jar_path: &Path) -> Result<bool> {
     info!("Verifying JAR file: {}", jar_path.display());
@@ -0,0 +1,318 @@
+// Modrinth modpack (.mrpack) installation for the Minecraft game plugin
+
+use anyhow::{Result, anyhow};
+use log::{info, warn, debug};
+use reqwest::Url;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+use crate::config::{Profile, ModLoader, LaunchSettings};
+use crate::file_manager::FileManager;
+use crate::utils::sanitize_file_name;
+
+/// Name of the index file inside a .mrpack archive
+const INDEX_FILE: &str = "modrinth.index.json";
+
+/// Archive folders copied into the game directory, later ones win
+const OVERRIDE_DIRS: [&str; 2] = ["overrides/", "client-overrides/"];
+
+/// `modrinth.index.json` of a Modrinth modpack
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModpackIndex {
+    pub format_version: u32,
+    pub game: String,
+    pub version_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub files: Vec<ModpackFile>,
+    /// `minecraft`, `forge`, `neoforge`, `fabric-loader` or `quilt-loader` to version
+    #[serde(default)]
+    pub dependencies: HashMap<String, String>,
+}
+
+/// A file the modpack downloads into the game directory
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModpackFile {
+    pub path: String,
+    #[serde(default)]
+    pub hashes: HashMap<String, String>,
+    #[serde(default)]
+    pub env: Option<ModpackFileEnv>,
+    #[serde(default)]
+    pub downloads: Vec<String>,
+    #[serde(default)]
+    pub file_size: u64,
+}
+
+/// Which sides need a modpack file (`required`, `optional` or `unsupported`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModpackFileEnv {
+    #[serde(default)]
+    pub client: Option<String>,
+    #[serde(default)]
+    pub server: Option<String>,
+}
+
+/// A downloaded modpack that has not been installed yet
+#[derive(Debug, Clone)]
+pub struct Modpack {
+    pub index: ModpackIndex,
+    /// Where the .mrpack archive was downloaded to
+    pub archive_path: PathBuf,
+}
+
+impl Modpack {
+    /// Gets the Minecraft version the pack was made for
+    pub fn minecraft_version(&self) -> Result<&str> {
+        self.index.dependencies.get("minecraft")
+            .map(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Modpack {} does not specify a Minecraft version", self.index.name))
+    }
+
+    /// Gets the mod loader and loader version the pack needs
+    pub fn mod_loader(&self) -> (ModLoader, Option<String>) {
+        let dependencies = &self.index.dependencies;
+
+        if let Some(version) = dependencies.get("fabric-loader") {
+            (ModLoader::Fabric, Some(version.clone()))
+        } else if let Some(version) = dependencies.get("quilt-loader") {
+            (ModLoader::Quilt, Some(version.clone()))
+        } else if let Some(version) = dependencies.get("neoforge") {
+            (ModLoader::NeoForge, Some(version.clone()))
+        } else if let Some(version) = dependencies.get("forge") {
+            // Forge versions are stored as <minecraft>-<forge> everywhere else
+            let version = match dependencies.get("minecraft") {
+                Some(minecraft) if !version.starts_with(&format!("{}-", minecraft)) => format!("{}-{}", minecraft, version),
+                _ => version.clone(),
+            };
+            (ModLoader::Forge, Some(version))
+        } else {
+            (ModLoader::None, None)
+        }
+    }
+
+    /// Gets the files the client needs, skipping server-only ones
+    pub fn client_files(&self) -> Vec<&ModpackFile> {
+        self.index.files.iter()
+            .filter(|file| {
+                file.env.as_ref()
+                    .and_then(|env| env.client.as_deref())
+                    .map_or(true, |client| client != "unsupported")
+            })
+            .collect()
+    }
+
+    /// Builds a new profile for the pack with its own game directory in `instances_dir`
+    pub fn create_profile(&self, name: &str, instances_dir: &Path) -> Result<Profile> {
+        let (mod_loader, mod_loader_version) = self.mod_loader();
+
+        // Pick a free directory name based on the profile name
+        let base_name = sanitize_file_name(name);
+        let mut game_directory = instances_dir.join(&base_name);
+        let mut suffix = 2;
+        while game_directory.exists() {
+            game_directory = instances_dir.join(format!("{} ({})", base_name, suffix));
+            suffix += 1;
+        }
+
+        Ok(Profile {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            version: self.minecraft_version()?.to_string(),
+            mod_loader: Some(mod_loader),
+            mod_loader_version,
+            mods: Vec::new(),
+            game_directory: Some(game_directory),
+            resolution: None,
+            icon: None,
+            launch: LaunchSettings::default(),
+        })
+    }
+}
+
+/// Gets the Modrinth project id from a `cdn.modrinth.com/data/<project>/versions/...` URL
+pub fn get_modrinth_project_id(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    if url.host_str() != Some("cdn.modrinth.com") {
+        return None;
+    }
+
+    let mut segments = url.path_segments()?;
+    match (segments.next(), segments.next()) {
+        (Some("data"), Some(project)) if !project.is_empty() => Some(project.to_string()),
+        _ => None,
+    }
+}
+
+/// Downloads a .mrpack and reads its index
+pub async fn download_modpack(file_manager: &FileManager, url: &str) -> Result<Modpack> {
+    let archive_path = std::env::temp_dir().join(format!("mosaic-modpack-{}.mrpack", Uuid::new_v4()));
+
+    info!("Downloading modpack from {}", url);
+    file_manager.download_file(url, &archive_path, None, |_| {}).await?;
+
+    let index = match read_modpack_index(&archive_path) {
+        Ok(index) => index,
+        Err(e) => {
+            let _ = std::fs::remove_file(&archive_path);
+            return Err(e);
+        }
+    };
+
+    info!("Downloaded modpack {} {} ({} files)", index.name, index.version_id, index.files.len());
+    Ok(Modpack { index, archive_path })
+}
+
+/// Reads and checks `modrinth.index.json` from a .mrpack archive
+fn read_modpack_index(archive_path: &Path) -> Result<ModpackIndex> {
+    let file = std::fs::File::open(archive_path)?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| anyhow!("The modpack is not a valid .mrpack archive: {}", e))?;
+
+    let mut content = String::new();
+    archive.by_name(INDEX_FILE)
+        .map_err(|_| anyhow!("The modpack does not contain {}", INDEX_FILE))?
+        .read_to_string(&mut content)?;
+
+    let index: ModpackIndex = serde_json::from_str(&content)
+        .map_err(|e| anyhow!("Failed to parse {}: {}", INDEX_FILE, e))?;
+
+    if index.format_version != 1 {
+        return Err(anyhow!("Unsupported modpack format version {}", index.format_version));
+    }
+    if index.game != "minecraft" {
+        return Err(anyhow!("The modpack is for {}, not Minecraft", index.game));
+    }
+
+    Ok(index)
+}
+
+/// Downloads the pack's files and copies its overrides into the profile's game directory
+///
+/// `progress_callback` is called with the number of files done and the total.
+pub async fn install_modpack(
+    file_manager: &FileManager,
+    modpack: &Modpack,
+    profile: &Profile,
+    progress_callback: impl Fn(usize, usize),
+) -> Result<()> {
+    let game_dir = profile.game_directory.clone()
+        .ok_or_else(|| anyhow!("Profile {} has no game directory for the modpack", profile.name))?;
+    file_manager.create_dir_all(&game_dir).await?;
+
+    let files = modpack.client_files();
+    let total = files.len();
+    info!("Installing modpack {} into {:?} ({} files)", modpack.index.name, game_dir, total);
+
+    for (done, file) in files.into_iter().enumerate() {
+        progress_callback(done, total);
+
+        let relative_path = get_safe_relative_path(&file.path)
+            .ok_or_else(|| anyhow!("Modpack file has an unsafe path: {}", file.path))?;
+        let target = game_dir.join(relative_path);
+        if let Some(parent) = target.parent() {
+            file_manager.create_dir_all(parent).await?;
+        }
+
+        if file.downloads.is_empty() {
+            return Err(anyhow!("Modpack file {} has no download URL", file.path));
+        }
+
+        // Try every mirror the pack lists before giving up on the file
+        let expected_hash = file.hashes.get("sha1").map(|hash| hash.as_str());
+        let mut last_error = None;
+        for url in &file.downloads {
+            match file_manager.download_file(url, &target, expected_hash, |_| {}).await {
+                Ok(_) => {
+                    last_error = None;
+                    break;
+                },
+                Err(e) => {
+                    warn!("Failed to download {} from {}: {}", file.path, url, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if let Some(e) = last_error {
+            return Err(anyhow!("Failed to download {}: {}", file.path, e));
+        }
+    }
+
+    let archive_path = modpack.archive_path.clone();
+    let override_dir = game_dir.clone();
+    let copied = tokio::task::spawn_blocking(move || extract_overrides(&archive_path, &override_dir)).await??;
+    info!("Copied {} override files for modpack {}", copied, modpack.index.name);
+
+    progress_callback(total, total);
+    Ok(())
+}
+
+/// Extracts `overrides/` and `client-overrides/` from the archive into the game directory
+fn extract_overrides(archive_path: &Path, game_dir: &Path) -> Result<usize> {
+    let file = std::fs::File::open(archive_path)?;
+    let mut archive = zip::ZipArchive::new(file)?;
+    let mut copied = 0;
+
+    for prefix in OVERRIDE_DIRS {
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            let name = entry.name().to_string();
+
+            let relative_path = match name.strip_prefix(prefix) {
+                Some(relative) if !relative.is_empty() => relative,
+                _ => continue,
+            };
+            let relative_path = match get_safe_relative_path(relative_path) {
+                Some(path) => path,
+                None => {
+                    warn!("Skipping override with an unsafe path: {}", name);
+                    continue;
+                }
+            };
+
+            let target = game_dir.join(relative_path);
+            if entry.is_dir() {
+                std::fs::create_dir_all(&target)?;
+                continue;
+            }
+
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            debug!("Extracting override {} to {:?}", name, target);
+            let mut output = std::fs::File::create(&target)?;
+            std::io::copy(&mut entry, &mut output)?;
+            copied += 1;
+        }
+    }
+
+    Ok(copied)
+}
+
+/// Turns a path from the pack into a relative path that stays inside the game directory
+fn get_safe_relative_path(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    let mut safe = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => safe.push(part),
+            Component::CurDir => {},
+            _ => return None,
+        }
+    }
+
+    if safe.as_os_str().is_empty() {
+        None
+    } else {
+        Some(safe)
+    }
+}
@@ -0,0 +1,323 @@
+// Rule evaluation for version JSON libraries and arguments
+//
+// Libraries and arguments carry the same kind of rules; both are decided here
+// against an explicit environment so the outcome doesn't depend on where the
+// check runs.
+
+use log::warn;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use super::models::Rule;
+
+/// The system and launch options rules are checked against
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleEnvironment {
+    /// `windows`, `osx` or `linux`
+    pub os_name: String,
+    /// Kernel or OS release, matched by the `os.version` regex
+    pub os_version: String,
+    /// `x86_64`, `x86` or `arm64`
+    pub os_arch: String,
+    /// Launch features such as `has_custom_resolution`, unlisted ones are off
+    pub features: HashMap<String, bool>,
+}
+
+impl RuleEnvironment {
+    /// Gets the environment of the running system, with no features enabled
+    pub fn current() -> Self {
+        Self {
+            os_name: get_os_name().to_string(),
+            os_version: get_os_version().to_string(),
+            os_arch: get_os_arch().to_string(),
+            features: HashMap::new(),
+        }
+    }
+
+    /// Turns a launch feature on or off
+    pub fn with_feature(mut self, name: &str, enabled: bool) -> Self {
+        self.features.insert(name.to_string(), enabled);
+        self
+    }
+
+    fn has_feature(&self, name: &str) -> bool {
+        self.features.get(name).copied().unwrap_or(false)
+    }
+}
+
+/// Decides whether a library or argument with these rules applies
+///
+/// Without rules everything applies. Otherwise nothing does until a rule
+/// matches, and the last matching rule's action wins.
+pub fn rules_allow(rules: Option<&[Rule]>, environment: &RuleEnvironment) -> bool {
+    let rules = match rules {
+        Some(rules) if !rules.is_empty() => rules,
+        _ => return true,
+    };
+
+    let mut allowed = false;
+    for rule in rules {
+        if rule_matches(rule, environment) {
+            allowed = rule.action == "allow";
+        }
+    }
+
+    allowed
+}
+
+/// Decides whether an argument object from a version JSON applies
+///
+/// Argument objects look like `{ "rules": [...], "value": ... }`.
+pub fn argument_allowed(argument: &serde_json::Map<String, serde_json::Value>, environment: &RuleEnvironment) -> bool {
+    let rules = match argument.get("rules") {
+        Some(rules) => rules,
+        None => return true,
+    };
+
+    match serde_json::from_value::<Vec<Rule>>(rules.clone()) {
+        Ok(rules) => rules_allow(Some(&rules), environment),
+        Err(e) => {
+            warn!("Skipping argument with invalid rules {}: {}", rules, e);
+            false
+        }
+    }
+}
+
+/// Checks whether every condition of a rule holds in the environment
+fn rule_matches(rule: &Rule, environment: &RuleEnvironment) -> bool {
+    if let Some(os) = &rule.os {
+        if let Some(name) = &os.name {
+            if *name != environment.os_name {
+                return false;
+            }
+        }
+
+        if let Some(version) = &os.version {
+            if !os_version_matches(version, &environment.os_version) {
+                return false;
+            }
+        }
+
+        if let Some(arch) = &os.arch {
+            if *arch != environment.os_arch {
+                return false;
+            }
+        }
+    }
+
+    if let Some(features) = &rule.features {
+        if features.iter().any(|(name, wanted)| environment.has_feature(name) != *wanted) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Matches an `os.version` pattern, compiling each pattern only once
+///
+/// An invalid pattern never matches and is only reported the first time.
+fn os_version_matches(pattern: &str, os_version: &str) -> bool {
+    static PATTERNS: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+
+    let mut patterns = PATTERNS.get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let regex = patterns.entry(pattern.to_string()).or_insert_with(|| {
+        Regex::new(pattern)
+            .map_err(|e| warn!("Invalid OS version pattern {}: {}", pattern, e))
+            .ok()
+    });
+
+    regex.as_ref().is_some_and(|regex| regex.is_match(os_version))
+}
+
+/// Gets the OS name as used by rules
+fn get_os_name() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "osx"
+    } else {
+        "linux"
+    }
+}
+
+/// Gets the architecture as used by rules
+fn get_os_arch() -> &'static str {
+    if cfg!(target_arch = "x86_64") {
+        "x86_64"
+    } else if cfg!(target_arch = "x86") {
+        "x86"
+    } else if cfg!(target_arch = "aarch64") {
+        "arm64"
+    } else {
+        "unknown"
+    }
+}
+
+/// Gets the OS release, e.g. `10.0.19045` on Windows or `14.2.1` on macOS
+///
+/// Looked up once, an empty string if it can't be determined.
+fn get_os_version() -> &'static str {
+    static OS_VERSION: OnceLock<String> = OnceLock::new();
+    OS_VERSION.get_or_init(|| {
+        let version = if cfg!(target_os = "windows") {
+            // "Microsoft Windows [Version 10.0.19045.3570]"
+            run_command("cmd", &["/c", "ver"]).and_then(|output| {
+                let start = output.find("Version ")? + "Version ".len();
+                let version = &output[start..];
+                Some(version[..version.find(']').unwrap_or(version.len())].to_string())
+            })
+        } else if cfg!(target_os = "macos") {
+            run_command("sw_vers", &["-productVersion"])
+        } else {
+            std::fs::read_to_string("/proc/sys/kernel/osrelease").ok()
+        };
+
+        version.map(|version| version.trim().to_string()).unwrap_or_default()
+    })
+}
+
+/// Runs a command and returns its standard output if it succeeded
+fn run_command(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn environment(os_name: &str, os_version: &str, os_arch: &str) -> RuleEnvironment {
+        RuleEnvironment {
+            os_name: os_name.to_string(),
+            os_version: os_version.to_string(),
+            os_arch: os_arch.to_string(),
+            features: HashMap::new(),
+        }
+    }
+
+    fn parse_rules(rules: Value) -> Vec<Rule> {
+        serde_json::from_value(rules).unwrap()
+    }
+
+    fn parse_argument(argument: Value) -> serde_json::Map<String, Value> {
+        argument.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn no_rules_allow_everything() {
+        let linux = environment("linux", "6.5.0", "x86_64");
+        assert!(rules_allow(None, &linux));
+        assert!(rules_allow(Some(&[]), &linux));
+    }
+
+    #[test]
+    fn lwjgl_disallowed_on_osx() {
+        // org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209 in 1.12.2
+        let rules = parse_rules(json!([
+            { "action": "allow" },
+            { "action": "disallow", "os": { "name": "osx" } }
+        ]));
+
+        assert!(rules_allow(Some(&rules), &environment("linux", "6.5.0", "x86_64")));
+        assert!(rules_allow(Some(&rules), &environment("windows", "10.0.19045", "x86_64")));
+        assert!(!rules_allow(Some(&rules), &environment("osx", "14.2.1", "arm64")));
+    }
+
+    #[test]
+    fn lwjgl_only_on_osx() {
+        // org.lwjgl:lwjgl:3.3.1 natives-macos in 1.19.4
+        let rules = parse_rules(json!([
+            { "action": "allow", "os": { "name": "osx" } }
+        ]));
+
+        assert!(rules_allow(Some(&rules), &environment("osx", "14.2.1", "arm64")));
+        assert!(!rules_allow(Some(&rules), &environment("linux", "6.5.0", "x86_64")));
+    }
+
+    #[test]
+    fn windows_10_version_pattern() {
+        // JVM arguments of 1.13 and later
+        let argument = parse_argument(json!({
+            "rules": [{ "action": "allow", "os": { "name": "windows", "version": "^10\\." } }],
+            "value": ["-Dos.name=Windows 10", "-Dos.version=10.0"]
+        }));
+
+        assert!(argument_allowed(&argument, &environment("windows", "10.0.19045", "x86_64")));
+        assert!(!argument_allowed(&argument, &environment("windows", "6.1.7601", "x86_64")));
+        assert!(!argument_allowed(&argument, &environment("linux", "10.0.0", "x86_64")));
+    }
+
+    #[test]
+    fn x86_arch() {
+        // JVM arguments of 1.13 and later
+        let argument = parse_argument(json!({
+            "rules": [{ "action": "allow", "os": { "arch": "x86" } }],
+            "value": "-Xss1M"
+        }));
+
+        assert!(argument_allowed(&argument, &environment("windows", "10.0.19045", "x86")));
+        assert!(!argument_allowed(&argument, &environment("windows", "10.0.19045", "x86_64")));
+    }
+
+    #[test]
+    fn feature_arguments() {
+        // Game arguments of 1.20.1
+        let demo = parse_argument(json!({
+            "rules": [{ "action": "allow", "features": { "is_demo_user": true } }],
+            "value": "--demo"
+        }));
+        let resolution = parse_argument(json!({
+            "rules": [{ "action": "allow", "features": { "has_custom_resolution": true } }],
+            "value": ["--width", "${resolution_width}", "--height", "${resolution_height}"]
+        }));
+        let quick_play = parse_argument(json!({
+            "rules": [{ "action": "allow", "features": { "has_quick_plays_support": true } }],
+            "value": ["--quickPlayPath", "${quickPlayPath}"]
+        }));
+
+        let launch = environment("linux", "6.5.0", "x86_64")
+            .with_feature("has_custom_resolution", true)
+            .with_feature("is_demo_user", false);
+
+        assert!(argument_allowed(&resolution, &launch));
+        assert!(!argument_allowed(&demo, &launch));
+        // Features the launcher doesn't set count as off
+        assert!(!argument_allowed(&quick_play, &launch));
+        assert!(!argument_allowed(&resolution, &environment("linux", "6.5.0", "x86_64")));
+    }
+
+    #[test]
+    fn plain_arguments_apply() {
+        let argument = parse_argument(json!({ "value": "--username" }));
+        assert!(argument_allowed(&argument, &environment("linux", "6.5.0", "x86_64")));
+    }
+
+    #[test]
+    fn invalid_version_pattern_never_matches() {
+        let rules = parse_rules(json!([
+            { "action": "allow", "os": { "name": "windows", "version": "^10\\.(" } }
+        ]));
+        let windows = environment("windows", "10.0.19045", "x86_64");
+
+        // Checked twice, the pattern is cached after the first failure
+        assert!(!rules_allow(Some(&rules), &windows));
+        assert!(!rules_allow(Some(&rules), &windows));
+    }
+
+    #[test]
+    fn invalid_rules_skip_the_argument() {
+        let argument = parse_argument(json!({
+            "rules": [{ "os": { "name": "linux" } }],
+            "value": "--broken"
+        }));
+        assert!(!argument_allowed(&argument, &environment("linux", "6.5.0", "x86_64")));
+    }
+}
@@ -303,6 +303,11 @@ pub fn build_login_view(
                             gtk::glib::spawn_future_local(async move {
                                 match auth_manager.complete_login(device_auth).await {
                                     Ok(session) => {
+                                        // Remember the session for the next start
+                                        if let Err(e) = auth_manager.save_session(&session) {
+                                            warn!("Failed to store auth session: {}", e);
+                                        }
+
                                         // Store the auth session
                                         *auth_session.lock().unwrap() = Some(session);
 
@@ -476,6 +481,11 @@ pub fn build_login_view(
                 // Create an offline session
                 match auth_manager.create_offline_session(&username) {
                     Ok(session) => {
+                        // Remember the session for the next start
+                        if let Err(e) = auth_manager.save_session(&session) {
+                            warn!("Failed to store auth session: {}", e);
+                        }
+
                         // Store the auth session
                         *auth_session.lock().unwrap() = Some(session);
 
@@ -11,7 +11,8 @@ use tokio::fs;
 use log::{info, warn, error, debug};
 
 use crate::config::{Config, Profile, Mod, ModSource, ModLoader};
-use crate::file_manager::{FileManager, DownloadProgress};
+use crate::file_manager::{FileManager, DownloadProgress, get_mirror_urls};
+use crate::network::{client_builder, get_client};
 use crate::mods::{DependencyType, ModDependency, ModSearchParams, ModSearchResult, ModSortField, ModVersionInfo, SortOrder};
 
 // API endpoints
@@ -180,7 +181,8 @@ struct ModrinthFile {
 }
 
 pub struct MinecraftModManager {
-    http_client: HttpClient,
+    /// The error instead if the proxy settings are invalid
+    http_client: std::result::Result<HttpClient, String>,
     file_manager: FileManager,
     config: Config,
 }
@@ -191,18 +193,47 @@ impl MinecraftModManager {
         let mut headers = HeaderMap::new();
         headers.insert("x-api-key", HeaderValue::from_static(CURSEFORGE_API_KEY));
 
-        let http_client = HttpClient::builder()
-            .default_headers(headers)
-            .build()
-            .unwrap_or_else(|_| HttpClient::new());
+        let http_client = client_builder(&config.proxy)
+            .and_then(|builder| Ok(builder.default_headers(headers).build()?))
+            .map_err(|e| {
+                warn!("Invalid proxy settings, mod searches will fail: {}", e);
+                e.to_string()
+            });
+
+        // Mod downloads use the configured parallelism and mirrors
+        let mut file_manager = (*file_manager).clone();
+        file_manager.configure(&config);
 
         Self {
             http_client,
-            file_manager: (*file_manager).clone(),
+            file_manager,
             config,
         }
     }
 
+    /// Sends a GET request to the API, trying the configured mirrors for its host in order
+    async fn api_get(&self, url: &str, query: &[(&str, String)]) -> Result<reqwest::Response> {
+        let mut last_error = None;
+
+        for candidate in get_mirror_urls(&self.config.mirrors, url) {
+            let response = get_client(&self.http_client)?.get(&candidate)
+                .query(query)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+
+            match response {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    warn!("API request to {} failed: {}", candidate, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.map(Into::into).unwrap_or_else(|| anyhow!("No URL to request {} from", url)))
+    }
+
     // Helper method to get the Minecraft directory from the selected game
     fn get_minecraft_directory(&self) -> PathBuf {
         // Get the selected game ID
@@ -229,6 +260,13 @@ impl MinecraftModManager {
         game.game_directory.clone()
     }
 
+    // Helper method to get the mods directory of a profile
+    fn get_mods_directory(&self, profile: &Profile) -> PathBuf {
+        profile.game_directory.clone()
+            .unwrap_or_else(|| self.get_minecraft_directory())
+            .join("mods")
+    }
+
     pub async fn search_mods(&self, params: &ModSearchParams) -> Result<Vec<ModSearchResult>> {
         info!("Searching for mods with query: {}", params.query);
 
@@ -310,9 +348,7 @@ impl MinecraftModManager {
         query_params.push(("sortOrder", sort_order.to_string()));
 
         // Make the request
-        let response = self.http_client.get(&url)
-            .query(&query_params)
-            .send()
+        let response = self.api_get(&url, &query_params)
             .await?
             .json::<CurseForgeResponse<CurseForgeSearchResponse>>()
             .await?;
@@ -460,15 +496,13 @@ impl MinecraftModManager {
         };
 
         // Make the request
-        let response = self.http_client.get(&url)
-            .query(&[
-                ("query", &params.query),
-                ("limit", &params.limit.to_string()),
-                ("offset", &params.offset.to_string()),
-                ("facets", &facets_str),
-                ("sort", &sort),
+        let response = self.api_get(&url, &[
+                ("query", params.query.clone()),
+                ("limit", params.limit.to_string()),
+                ("offset", params.offset.to_string()),
+                ("facets", facets_str),
+                ("sort", sort),
             ])
-            .send()
             .await?
             .json::<ModrinthSearchResponse>()
             .await?;
@@ -573,8 +607,7 @@ impl MinecraftModManager {
 
     async fn get_modrinth_version(&self, version_id: &str) -> Result<ModrinthVersion> {
         let url = format!("{}/version/{}", MODRINTH_API_BASE, version_id);
-        let response = self.http_client.get(&url)
-            .send()
+        let response = self.api_get(&url, &[])
             .await?
             .json::<ModrinthVersion>()
             .await?;
@@ -634,8 +667,7 @@ impl MinecraftModManager {
 
     async fn get_curseforge_mod_details(&self, mod_id: &str) -> Result<ModSearchResult> {
         let url = format!("{}/mods/{}", CURSEFORGE_API_BASE, mod_id);
-        let response = self.http_client.get(&url)
-            .send()
+        let response = self.api_get(&url, &[])
             .await?
             .json::<CurseForgeResponse<CurseForgeMod>>()
             .await?;
@@ -731,16 +763,14 @@ impl MinecraftModManager {
     async fn get_modrinth_mod_details(&self, mod_id: &str) -> Result<ModSearchResult> {
         // Get project details
         let project_url = format!("{}/project/{}", MODRINTH_API_BASE, mod_id);
-        let project = self.http_client.get(&project_url)
-            .send()
+        let project = self.api_get(&project_url, &[])
             .await?
             .json::<ModrinthMod>()
             .await?;
 
         // Get version details
         let versions_url = format!("{}/project/{}/version", MODRINTH_API_BASE, mod_id);
-        let versions_response = self.http_client.get(&versions_url)
-            .send()
+        let versions_response = self.api_get(&versions_url, &[])
             .await?
             .json::<Vec<ModrinthVersion>>()
             .await?;
@@ -831,11 +861,8 @@ impl MinecraftModManager {
     }
 
     pub async fn install_mod(&self, mod_version: &ModVersionInfo, profile: &Profile) -> Result<()> {
-        // Get the Minecraft directory
-        let minecraft_dir = self.get_minecraft_directory();
-
         // Get the mods directory for this profile
-        let mods_dir = minecraft_dir.join("mods");
+        let mods_dir = self.get_mods_directory(profile);
 
         // Create the mods directory if it doesn't exist
         if !mods_dir.exists() {
@@ -851,11 +878,8 @@ impl MinecraftModManager {
     }
 
     pub async fn uninstall_mod(&self, mod_file: &str, profile: &Profile) -> Result<()> {
-        // Get the Minecraft directory
-        let minecraft_dir = self.get_minecraft_directory();
-
         // Get the mods directory for this profile
-        let mods_dir = minecraft_dir.join("mods");
+        let mods_dir = self.get_mods_directory(profile);
 
         // Check if the mod file exists
         let mod_path = mods_dir.join(mod_file);
@@ -870,11 +894,8 @@ impl MinecraftModManager {
     }
 
     pub async fn get_installed_mods(&self, profile: &Profile) -> Result<Vec<Mod>> {
-        // Get the Minecraft directory
-        let minecraft_dir = self.get_minecraft_directory();
-
         // Get the mods directory for this profile
-        let mods_dir = minecraft_dir.join("mods");
+        let mods_dir = self.get_mods_directory(profile);
 
         // Check if the mods directory exists
         if !mods_dir.exists() {
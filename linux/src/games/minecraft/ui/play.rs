@@ -1,6 +1,6 @@
 use gtk4 as gtk;
 use gtk::prelude::*;
-use gtk::glib;
+use gtk::{gio, glib};
 use gtk::glib::clone;
 use libadwaita as adw;
 use adw::prelude::*;
@@ -8,12 +8,17 @@ use std::rc::Rc;
 use std::cell::RefCell;
 use std::sync::{Arc, Mutex};
 use std::process::Command;
-use std::thread;
 use log::{info, warn, error, debug};
 
 use crate::config::{Config, save_config};
 use crate::games::minecraft::{MinecraftManager, VersionManifest};
 use crate::games::minecraft::auth::AuthSession;
+use crate::file_manager::{CancellationToken, is_cancelled_error, set_full_speed};
+use crate::progress::InstallProgress;
+use crate::utils::{format_file_size, format_duration};
+
+/// Widget name of the install progress shown while a launch downloads files
+const INSTALL_PROGRESS_NAME: &str = "install-progress";
 
 pub fn build_play_view(
     window: &adw::ApplicationWindow,
@@ -39,6 +44,14 @@ pub fn build_play_view(
     let header = adw::HeaderBar::new();
     header.set_title_widget(Some(&gtk::Label::new(Some("Play"))));
     header.set_show_start_title_buttons(false);
+
+    // Add an offline mode toggle to the header
+    let offline_button = gtk::ToggleButton::new();
+    offline_button.set_icon_name("network-offline-symbolic");
+    offline_button.set_tooltip_text(Some("Offline mode"));
+    offline_button.set_active(config.borrow().offline_mode);
+    header.pack_end(&offline_button);
+
     play_box.append(&header);
 
     // Create a content box
@@ -138,7 +151,7 @@ pub fn build_play_view(
         row.set_subtitle(&subtitle);
 
         // Add memory info as a suffix
-        if let Some(memory) = profile.memory {
+        if let Some(memory) = profile.launch.max_memory {
             let memory_label = gtk::Label::new(Some(&format!("{}MB", memory)));
             memory_label.add_css_class("dim-label");
             memory_label.add_css_class("numeric");
@@ -247,7 +260,7 @@ pub fn build_play_view(
             row.set_subtitle(&subtitle);
 
             // Add memory info as a suffix
-            if let Some(memory) = profile.memory {
+            if let Some(memory) = profile.launch.max_memory {
                 let memory_label = gtk::Label::new(Some(&format!("{}MB", memory)));
                 memory_label.add_css_class("dim-label");
                 memory_label.add_css_class("numeric");
@@ -299,6 +312,32 @@ pub fn build_play_view(
     login_message.set_visible(false);
     content.append(&login_message);
 
+    // Add an offline mode message (shown while offline mode is on)
+    let offline_message = gtk::Label::new(Some("Offline mode: only installed versions can be launched"));
+    offline_message.add_css_class("caption");
+    offline_message.add_css_class("warning");
+    offline_message.set_margin_top(10);
+    offline_message.set_halign(gtk::Align::Center);
+    offline_message.set_visible(config.borrow().offline_mode);
+    content.append(&offline_message);
+
+    // Connect the offline mode toggle
+    offline_button.connect_toggled(clone!(@strong config, @strong minecraft_manager, @strong toast_overlay, @strong offline_message => move |button| {
+        let offline = button.is_active();
+        info!("Offline mode {}", if offline { "enabled" } else { "disabled" });
+
+        minecraft_manager.lock().unwrap().set_offline_mode(offline);
+        offline_message.set_visible(offline);
+
+        // Save the config
+        let mut config_mut = config.borrow_mut();
+        config_mut.offline_mode = offline;
+        if let Err(e) = save_config(&config_mut) {
+            let toast = adw::Toast::new(&format!("Failed to save offline mode: {}", e));
+            toast_overlay.add_toast(toast);
+        }
+    }));
+
     // Connect the play button
     let config_clone = config.clone();
     let minecraft_manager_clone = minecraft_manager.clone();
@@ -429,51 +468,99 @@ pub fn build_play_view(
         button.set_child(Some(&spinner));
         button.set_sensitive(false);
 
-        // Remove any existing progress bar
+        // Remove any existing install progress
         let mut child_opt = content.first_child();
         while let Some(child) = child_opt {
-            if let Some(_) = child.downcast_ref::<gtk::ProgressBar>() {
+            if child.widget_name() == INSTALL_PROGRESS_NAME {
                 content.remove(&child);
                 break;
             }
             child_opt = child.next_sibling();
         }
 
-        // Create a progress bar
+        // Create the install progress: a bar for the overall fraction and a line of details
+        let progress_box = gtk::Box::new(gtk::Orientation::Vertical, 4);
+        progress_box.set_widget_name(INSTALL_PROGRESS_NAME);
+        progress_box.set_margin_top(10);
+        progress_box.set_margin_bottom(10);
+        progress_box.set_margin_start(20);
+        progress_box.set_margin_end(20);
+
+        let progress_row = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+        progress_box.append(&progress_row);
+
         let progress_bar = gtk::ProgressBar::new();
         progress_bar.set_show_text(true);
         progress_bar.set_text(Some("Preparing..."));
-        progress_bar.set_margin_top(10);
-        progress_bar.set_margin_bottom(10);
-        progress_bar.set_margin_start(20);
-        progress_bar.set_margin_end(20);
-        progress_bar.set_visible(true);
-        content.append(&progress_bar);
+        progress_bar.set_hexpand(true);
+        progress_bar.set_valign(gtk::Align::Center);
+        progress_row.append(&progress_bar);
+
+        // Cancelling stops the downloads, the launch fails with a `Cancelled` error
+        let cancel = CancellationToken::new();
+        let cancel_button = gtk::Button::with_label("Cancel");
+        cancel_button.set_valign(gtk::Align::Center);
+        progress_row.append(&cancel_button);
+
+        cancel_button.connect_clicked(clone!(@strong cancel, @strong progress_bar => move |cancel_button| {
+            info!("Cancelling launch");
+            cancel.cancel();
+            cancel_button.set_sensitive(false);
+            progress_bar.set_text(Some("Cancelling..."));
+        }));
+
+        // Lets this install ignore the download speed limit until it finishes
+        let full_speed_button = gtk::ToggleButton::with_label("Full Speed");
+        full_speed_button.set_valign(gtk::Align::Center);
+        full_speed_button.set_tooltip_text(Some("Ignore the download speed limit for this install"));
+        full_speed_button.set_visible(config_clone.borrow().downloads.max_bytes_per_second.is_some());
+        progress_row.append(&full_speed_button);
+
+        full_speed_button.connect_toggled(|full_speed_button| {
+            info!("Download speed limit {}", if full_speed_button.is_active() { "lifted" } else { "restored" });
+            set_full_speed(full_speed_button.is_active());
+        });
+
+        let progress_details = gtk::Label::new(None);
+        progress_details.add_css_class("caption");
+        progress_details.add_css_class("dim-label");
+        progress_details.set_halign(gtk::Align::Start);
+        progress_details.set_ellipsize(gtk::pango::EllipsizeMode::End);
+        progress_box.append(&progress_details);
+
+        content.append(&progress_box);
 
         // Create a channel for progress updates
         let (sender, receiver) = glib::MainContext::channel(glib::Priority::DEFAULT);
 
         // Set up the receiver to update the UI
-        receiver.attach(None, clone!(@strong progress_bar => move |progress: crate::file_manager::DownloadProgress| {
-            // Update the progress bar
-            let percentage = progress.percentage / 100.0;
-            progress_bar.set_fraction(percentage as f64);
-
-            // Update the progress text
-            let downloaded_mb = progress.downloaded_size as f64 / 1024.0 / 1024.0;
-            let total_mb = match progress.total_size {
-                Some(size) => size as f64 / 1024.0 / 1024.0,
-                None => 0.0,
-            };
-
-            if total_mb > 0.0 {
-                progress_bar.set_text(Some(&format!("Downloading {} ({:.1} MB / {:.1} MB)", 
-                    progress.file_name, downloaded_mb, total_mb)));
+        receiver.attach(None, clone!(@strong progress_bar, @strong progress_details => move |progress: InstallProgress| {
+            progress_bar.set_fraction(progress.fraction());
+
+            // The bar shows the phase and file count, the label the bytes, rate and ETA
+            if progress.files_total > 0 {
+                progress_bar.set_text(Some(&format!("{} ({} / {} files)",
+                    progress.phase.label(), progress.files_done, progress.files_total)));
             } else {
-                progress_bar.set_text(Some(&format!("Downloading {} ({:.1} MB)", 
-                    progress.file_name, downloaded_mb)));
+                progress_bar.set_text(Some(progress.phase.label()));
             }
 
+            let mut details = Vec::new();
+            if progress.bytes_total > 0 {
+                details.push(format!("{} / {}",
+                    format_file_size(progress.bytes_done), format_file_size(progress.bytes_total)));
+            }
+            if progress.bytes_per_second >= 1.0 {
+                details.push(format!("{}/s", format_file_size(progress.bytes_per_second as u64)));
+            }
+            if let Some(eta) = progress.eta {
+                details.push(format!("{} left", format_duration(eta.as_millis() as u64)));
+            }
+            if let Some(file) = &progress.current_file {
+                details.push(file.clone());
+            }
+            progress_details.set_text(&details.join(" · "));
+
             glib::ControlFlow::Continue
         }));
 
@@ -499,11 +586,12 @@ pub fn build_play_view(
             let profile_clone_thread = profile_clone.clone();
             let sender_thread = sender.clone();
             let auth_session_thread = auth_session_clone.clone();
+            let cancel_thread = cancel.clone();
 
             // Create a Tokio runtime for this operation in a separate thread
             // to avoid freezing the UI
-            let result = std::thread::spawn(move || {
-                let rt = tokio::runtime::Runtime::new().unwrap();
+            let result = gio::spawn_blocking(move || -> anyhow::Result<u32> {
+                let rt = tokio::runtime::Runtime::new()?;
 
                 // Get the auth session
                 let auth = {
@@ -518,13 +606,16 @@ pub fn build_play_view(
                 rt.block_on(manager_guard.launch_game(&profile_clone_thread, &auth, move |progress| {
                     // Send progress update through the channel
                     let _ = sender_thread.send(progress);
-                }))
-            }).join().unwrap();
+                }, &cancel_thread))
+            }).await.unwrap_or_else(|_| Err(anyhow::anyhow!("The launch thread panicked")));
+
+            // The full speed override only lasts for this install
+            set_full_speed(false);
 
-            // Remove the progress bar
+            // Remove the install progress
             let mut child_opt = content_clone.first_child();
             while let Some(child) = child_opt {
-                if let Some(_) = child.downcast_ref::<gtk::ProgressBar>() {
+                if child.widget_name() == INSTALL_PROGRESS_NAME {
                     content_clone.remove(&child);
                     break;
                 }
@@ -545,6 +636,14 @@ pub fn build_play_view(
                     let toast = adw::Toast::new(&format!("Launched Minecraft with profile '{}'", profile_name));
                     toast_overlay.add_toast(toast);
                 }
+                Err(e) if is_cancelled_error(&e) => {
+                    // Reset the button
+                    button.set_label("Play");
+                    button.set_sensitive(true);
+
+                    let toast = adw::Toast::new("Launch cancelled");
+                    toast_overlay.add_toast(toast);
+                }
                 Err(e) => {
                     // Reset the button
                     button.set_label("Play");
@@ -3,266 +3,654 @@
 use anyhow::{Result, anyhow};
 use log::{info, warn, error, debug};
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
 use sha1::{Sha1, Digest};
 
-use crate::file_manager::{FileManager, DownloadProgress};
-use super::models::{VersionManifest, VersionDetails, AssetObjects, AssetObject, Library, Rule, Os};
+use crate::config::ModLoader;
+use crate::file_manager::{FileManager, DownloadQueue, DownloadJob, CancellationToken, combine_download_results, is_cancelled_error};
+use crate::progress::{ProgressTracker, InstallPhase};
+use super::importers::parse_version_id;
+use super::modloaders::LATEST_LOADER_VERSION;
+use super::models::{VersionDetails, AssetObjects, AssetObject, Library};
+use super::rules::{RuleEnvironment, rules_allow};
 
-// Minecraft version manifest URL
-const VERSION_MANIFEST_URL: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+/// Directory below the Minecraft directory where installs are put together
+const STAGING_DIR: &str = ".staging";
 
-/// Fetches the Minecraft version manifest
-pub async fn fetch_version_manifest(file_manager: &FileManager) -> Result<VersionManifest> {
-    info!("Fetching Minecraft version manifest");
+/// File in a version directory that marks its install as complete
+const INSTALL_MARKER: &str = ".install-complete";
 
-    // Download the version manifest
-    let manifest_json = file_manager.download_string(VERSION_MANIFEST_URL).await?;
+/// How often an install waiting for another install of the same version checks again
+const STAGING_LOCK_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
-    // Parse the manifest
-    let manifest: VersionManifest = serde_json::from_str(&manifest_json)?;
+/// Downloads a Minecraft version
+///
+/// The version directory is put together in a staging directory and only
+/// replaces `versions/<id>` once every file is downloaded and verified, with
+/// the install marker written last. A failed install leaves the old version
+/// directory as it was and clears its staging directory, keeping only the
+/// partial downloads so a large client jar resumes. Verified libraries and
+/// assets go straight to their shared directories since other versions use
+/// them too, so their partial downloads resume as well. Installs of the same
+/// version wait for each other.
+///
+/// `version_json` is the JSON as downloaded, saved byte for byte so it keeps
+/// matching its hash; None for versions whose JSON is already on disk.
+pub async fn download_version(
+    file_manager: &FileManager,
+    minecraft_dir: &Path,
+    version_details: &VersionDetails,
+    version_json: Option<&str>,
+    tracker: &ProgressTracker,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let staging_root = minecraft_dir.join(STAGING_DIR);
+    let staging_dir = staging_root.join(&version_details.id);
+    let file_manager = file_manager.with_cancellation(cancel);
+    let _lock = StagingLock::acquire(&staging_root, &version_details.id, cancel).await?;
+
+    // Whatever an earlier install left behind could be from another JSON
+    let (root, id) = (staging_root.clone(), version_details.id.clone());
+    tokio::task::spawn_blocking(move || clear_staging_dirs(&root, &id)).await??;
+
+    let result = download_version_files(&file_manager, minecraft_dir, &staging_dir, version_details, version_json, tracker, cancel).await;
+
+    if let Err(e) = &result {
+        info!("Install of {} failed, clearing {:?}: {}", version_details.id, staging_dir, e);
+        let (root, id) = (staging_root.clone(), version_details.id.clone());
+        if let Err(e) = tokio::task::spawn_blocking(move || clear_staging_dirs(&root, &id)).await? {
+            warn!("Failed to clear the failed install {:?}: {}", staging_dir, e);
+        }
+    }
 
-    Ok(manifest)
+    result
 }
 
-/// Fetches the details for a specific Minecraft version
-pub async fn fetch_version_details(file_manager: &FileManager, version_id: &str, manifest: &VersionManifest) -> Result<VersionDetails> {
-    info!("Fetching details for Minecraft version {}", version_id);
+/// Lock on the staging directory of a version, released when dropped
+///
+/// The lock file holds the pid of the install, a lock whose process is gone
+/// is stale and taken over.
+struct StagingLock {
+    path: PathBuf,
+}
 
-    // Find the version in the manifest
-    let version_info = manifest.versions.iter()
-        .find(|v| v.id == version_id)
-        .ok_or_else(|| anyhow!("Version {} not found in manifest", version_id))?;
+impl StagingLock {
+    /// Waits until no other install of the version runs, then takes the lock
+    async fn acquire(staging_root: &Path, version_id: &str, cancel: &CancellationToken) -> Result<Self> {
+        static ATTEMPTS: AtomicU64 = AtomicU64::new(0);
+
+        fs::create_dir_all(staging_root)?;
+        let path = staging_root.join(format!("{}.lock", version_id));
+
+        // The pid is written before the lock appears, so a lock is never seen empty
+        let pid = std::process::id();
+        let attempt = ATTEMPTS.fetch_add(1, Ordering::Relaxed);
+        let candidate = staging_root.join(format!("{}.lock.{}-{}", version_id, pid, attempt));
+        fs::write(&candidate, pid.to_string())?;
+
+        let mut waiting = false;
+        let result = loop {
+            match fs::hard_link(&candidate, &path) {
+                Ok(()) => break Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {},
+                Err(e) => break Err(e.into()),
+            }
 
-    // Download the version details
-    let details_json = file_manager.download_string(&version_info.url).await?;
+            let owner = fs::read_to_string(&path).ok().and_then(|owner| owner.trim().parse::<u32>().ok());
+            if owner.is_some_and(|owner| Path::new("/proc").join(owner.to_string()).exists()) {
+                if !waiting {
+                    info!("Waiting for another install of {} to finish", version_id);
+                    waiting = true;
+                }
+                if let Err(e) = cancel.check() {
+                    break Err(e);
+                }
+                tokio::time::sleep(STAGING_LOCK_POLL_INTERVAL).await;
+            } else {
+                warn!("Taking over the stale install lock {:?}", path);
+                let _ = fs::remove_file(&path);
+            }
+        };
 
-    // Parse the details
-    let details: VersionDetails = serde_json::from_str(&details_json)?;
+        let _ = fs::remove_file(&candidate);
+        result
+    }
+}
 
-    Ok(details)
+impl Drop for StagingLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to release the install lock {:?}: {}", self.path, e);
+        }
+    }
 }
 
-/// Downloads a Minecraft version
-pub async fn download_version(
+/// Clears the staging directory of a version and removes the replaced install left next to it
+///
+/// Partial downloads in the staging directory are kept so they resume.
+fn clear_staging_dirs(staging_root: &Path, version_id: &str) -> Result<()> {
+    let old_dir = staging_root.join(format!("{}.old", version_id));
+    if old_dir.exists() {
+        fs::remove_dir_all(&old_dir)?;
+    }
+
+    let staging_dir = staging_root.join(version_id);
+    if staging_dir.exists() && clear_dir_except_part_files(&staging_dir)? {
+        fs::remove_dir(&staging_dir)?;
+    }
+
+    Ok(())
+}
+
+/// Removes everything below `dir` except `.part` files, returns true if `dir` ends up empty
+fn clear_dir_except_part_files(dir: &Path) -> Result<bool> {
+    let mut empty = true;
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if entry.file_type()?.is_dir() {
+            if clear_dir_except_part_files(&path)? {
+                fs::remove_dir(&path)?;
+            } else {
+                empty = false;
+            }
+        } else if path.extension().is_some_and(|extension| extension == "part") {
+            empty = false;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(empty)
+}
+
+/// Downloads the files of a Minecraft version into `staging_dir` and moves it into place
+async fn download_version_files(
     file_manager: &FileManager,
     minecraft_dir: &Path,
+    staging_dir: &Path,
     version_details: &VersionDetails,
-    progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static + Clone,
+    version_json: Option<&str>,
+    tracker: &ProgressTracker,
+    cancel: &CancellationToken,
 ) -> Result<()> {
     info!("Downloading Minecraft version {}", version_details.id);
 
-    // Create the version directory
+    // Start from what is installed, so a loader's JSON and intact files carry over
     let version_dir = minecraft_dir.join("versions").join(&version_details.id);
-    file_manager.create_dir_all(&version_dir).await?;
+    file_manager.create_dir_all(staging_dir).await?;
+    if version_dir.exists() {
+        let (from, to) = (version_dir.clone(), staging_dir.to_path_buf());
+        tokio::task::spawn_blocking(move || copy_version_dir(&from, &to)).await??;
+    }
+
+    let mut libraries = DownloadQueue::new(file_manager);
 
-    // Download the client jar
+    // Queue the client jar with the libraries, a jar of another version is already installed
     let client_download = version_details.downloads.get("client")
         .ok_or_else(|| anyhow!("Client download not found for version {}", version_details.id))?;
 
-    let client_jar_path = version_dir.join(format!("{}.jar", version_details.id));
+    let client_jar_path = match version_details.get_jar_id() {
+        jar_id if jar_id == version_details.id => staging_dir.join(format!("{}.jar", jar_id)),
+        _ => get_client_jar_path(minecraft_dir, version_details),
+    };
+    libraries.add(DownloadJob {
+        url: client_download.url.clone(),
+        path: client_jar_path,
+        sha1: Some(client_download.sha1.clone()),
+        size: Some(client_download.size),
+    });
+
+    let natives = queue_libraries(&mut libraries, minecraft_dir, version_details);
+
+    if let Some(LibraryFile { path, url: Some(url), sha1, size }) = get_logging_config(minecraft_dir, version_details) {
+        debug!("Queueing logging config: {:?}", path);
+        libraries.add(DownloadJob { url, path, sha1, size });
+    }
+
+    let mut assets = DownloadQueue::new(file_manager);
+    queue_assets(file_manager, &mut assets, minecraft_dir, version_details, tracker).await?;
 
-    info!("Downloading client jar for version {}", version_details.id);
-    file_manager.download_file(
-        &client_download.url,
-        &client_jar_path,
-        Some(&client_download.sha1),
-        progress_callback.clone(),
-    ).await?;
+    // Count everything up front so the totals don't grow during the install
+    libraries.register(tracker);
+    assets.register(tracker);
+
+    tracker.set_phase(InstallPhase::Libraries);
+    let libraries_result = libraries.run(tracker).await;
+
+    // Natives can only be extracted once their jars are on disk
+    if libraries_result.is_ok() && !natives.is_empty() {
+        tracker.set_phase(InstallPhase::Natives);
+        let natives_dir = staging_dir.join("natives");
+        file_manager.create_dir_all(&natives_dir).await?;
+        extract_all_natives(natives, natives_dir, cancel).await?;
+    }
 
-    // Download the libraries
-    download_libraries(file_manager, minecraft_dir, version_details, progress_callback.clone()).await?;
+    cancel.check()?;
+    tracker.set_phase(InstallPhase::Assets);
+    let assets_result = assets.run(tracker).await;
+
+    combine_download_results(vec![libraries_result, assets_result])?;
+    cancel.check()?;
+
+    // Save the downloaded JSON as it came. Otherwise keep the one that is there,
+    // a loader's JSON must keep its inheritsFrom
+    let version_json_path = staging_dir.join(format!("{}.json", version_details.id));
+    match version_json {
+        Some(version_json) => file_manager.write_to_file(&version_json_path, version_json).await?,
+        None if !version_json_path.exists() => {
+            let version_json = serde_json::to_string_pretty(version_details)?;
+            file_manager.write_to_file(&version_json_path, &version_json).await?;
+        },
+        None => {},
+    }
 
-    // Download the assets
-    download_assets(file_manager, minecraft_dir, version_details, progress_callback).await?;
+    // Every download was checked against its hash, the marker records what is on disk now.
+    // Generated files are hashed, which blocks
+    let (dir, staged, details) = (minecraft_dir.to_path_buf(), staging_dir.to_path_buf(), version_details.clone());
+    let marker = tokio::task::spawn_blocking(move || create_install_marker(&dir, &staged, &details)).await??;
+    file_manager.write_to_file(staging_dir.join(INSTALL_MARKER), serde_json::to_string(&marker)?).await?;
 
-    // Save the version json
-    let version_json_path = version_dir.join(format!("{}.json", version_details.id));
-    let version_json = serde_json::to_string_pretty(version_details)?;
-    file_manager.write_to_file(&version_json_path, &version_json).await?;
+    cancel.check()?;
+    let (dir, staged) = (minecraft_dir.to_path_buf(), staging_dir.to_path_buf());
+    tokio::task::spawn_blocking(move || commit_staged_version(&dir, &staged, &version_dir)).await??;
 
     info!("Minecraft version {} downloaded successfully", version_details.id);
     Ok(())
 }
 
-/// Downloads the libraries for a Minecraft version
-async fn download_libraries(
-    file_manager: &FileManager,
-    minecraft_dir: &Path,
-    version_details: &VersionDetails,
-    progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static + Clone,
-) -> Result<()> {
-    info!("Downloading libraries for Minecraft version {}", version_details.id);
+/// Extracts native jars into `natives_dir` on a blocking thread
+async fn extract_all_natives(natives: Vec<(PathBuf, Vec<String>)>, natives_dir: PathBuf, cancel: &CancellationToken) -> Result<()> {
+    let cancel = cancel.clone();
+    tokio::task::spawn_blocking(move || {
+        for (native_path, exclude) in &natives {
+            cancel.check()?;
+            info!("Extracting native library: {:?}", native_path);
+            extract_natives(native_path, &natives_dir, exclude)?;
+        }
+        Ok(())
+    }).await?
+}
 
-    let libraries_dir = minecraft_dir.join("libraries");
-    file_manager.create_dir_all(&libraries_dir).await?;
+/// Copies an installed version directory into a staging directory
+///
+/// Leaves out the install marker, which only a complete install writes, and partial downloads.
+fn copy_version_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let source = entry.path();
+        let target = to.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            copy_version_dir(&source, &target)?;
+        } else if entry.file_name() != INSTALL_MARKER && source.extension().map_or(true, |extension| extension != "part") {
+            fs::copy(&source, &target)?;
+        }
+    }
 
-    let os_name = if cfg!(target_os = "windows") {
-        "windows"
-    } else if cfg!(target_os = "macos") {
-        "osx"
-    } else {
-        "linux"
-    };
+    Ok(())
+}
 
-    let os_arch = if cfg!(target_arch = "x86_64") {
-        "x86_64"
-    } else if cfg!(target_arch = "x86") {
-        "x86"
-    } else if cfg!(target_arch = "aarch64") {
-        "arm64"
-    } else {
-        "unknown"
-    };
+/// Moves a finished staging directory to `versions/<id>`
+///
+/// The old directory is moved aside first and only removed once the new one
+/// is in place, so the version directory is never half written.
+fn commit_staged_version(minecraft_dir: &Path, staging_dir: &Path, version_dir: &Path) -> Result<()> {
+    if let Some(parent) = version_dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if !version_dir.exists() {
+        fs::rename(staging_dir, version_dir)?;
+        return Ok(());
+    }
+
+    let file_name = version_dir.file_name().ok_or_else(|| anyhow!("Invalid version directory {:?}", version_dir))?;
+    let old_dir = minecraft_dir.join(STAGING_DIR).join(format!("{}.old", file_name.to_string_lossy()));
+    if old_dir.exists() {
+        fs::remove_dir_all(&old_dir)?;
+    }
+
+    fs::rename(version_dir, &old_dir)?;
+    if let Err(e) = fs::rename(staging_dir, version_dir) {
+        // Put the old install back rather than leave none
+        fs::rename(&old_dir, version_dir)?;
+        return Err(anyhow!("Failed to move {:?} into place: {}", staging_dir, e));
+    }
+
+    if let Err(e) = fs::remove_dir_all(&old_dir) {
+        warn!("Failed to remove the replaced install {:?}: {}", old_dir, e);
+    }
+    Ok(())
+}
+
+/// A file of an install as the install marker records it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct InstalledFile {
+    sha1: String,
+    size: u64,
+}
+
+/// Contents of the install marker of a version
+#[derive(Debug, Serialize, Deserialize)]
+struct InstallMarker {
+    /// SHA-1 over `files`, so a damaged marker doesn't count
+    digest: String,
+    /// Every file of the install, by its path below the Minecraft directory
+    files: BTreeMap<String, InstalledFile>,
+}
+
+impl InstallMarker {
+    fn new(files: BTreeMap<String, InstalledFile>) -> Self {
+        Self { digest: get_files_digest(&files), files }
+    }
+
+    fn is_intact(&self) -> bool {
+        self.digest == get_files_digest(&self.files)
+    }
+}
 
-    // Download each library
+/// Gets the digest of the files in an install marker
+fn get_files_digest(files: &BTreeMap<String, InstalledFile>) -> String {
+    let mut hasher = Sha1::new();
+    for (path, file) in files {
+        hasher.update(format!("{} {} {}\n", path, file.sha1, file.size).as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Lists every file an install of a version puts on disk
+///
+/// The client jar, libraries and natives for this system, the logging config,
+/// the asset index and its objects. Fails if the asset index can't be read.
+fn get_install_files(minecraft_dir: &Path, version_details: &VersionDetails) -> Result<Vec<LibraryFile>> {
+    let client_download = version_details.downloads.get("client");
+    let mut files = vec![LibraryFile {
+        path: get_client_jar_path(minecraft_dir, version_details),
+        url: client_download.map(|download| download.url.clone()),
+        sha1: client_download.map(|download| download.sha1.clone()),
+        size: client_download.map(|download| download.size),
+    }];
+
+    let environment = RuleEnvironment::current();
+    let libraries_dir = minecraft_dir.join("libraries");
     for library in &version_details.libraries {
-        // Check if the library should be downloaded for this OS
-        if !should_download_library(library, os_name, os_arch) {
-            continue;
+        if rules_allow(library.rules.as_deref(), &environment) {
+            files.extend(get_library_artifact(&libraries_dir, library));
+            files.extend(get_library_native(&libraries_dir, library, &environment.os_name));
         }
+    }
 
-        // Download the main artifact if it exists
-        if let Some(downloads) = &library.downloads {
-            if let Some(artifact) = &downloads.artifact {
-                // Determine the library path from the library name
-                // Format: group:artifact:version
-                // Example: org.lwjgl:lwjgl:3.2.2
-                let parts: Vec<&str> = library.name.split(':').collect();
-                if parts.len() < 3 {
-                    warn!("Invalid library name format: {}", library.name);
-                    continue;
+    files.extend(get_logging_config(minecraft_dir, version_details));
+
+    if let Some(asset_index) = &version_details.assets_index {
+        let index_path = minecraft_dir.join("assets").join("indexes").join(format!("{}.json", asset_index.id));
+        files.extend(get_asset_files(minecraft_dir, &index_path)?);
+        files.push(LibraryFile {
+            path: index_path,
+            url: Some(asset_index.url.clone()),
+            sha1: Some(asset_index.sha1.clone()),
+            size: Some(asset_index.size),
+        });
+    }
+
+    Ok(files)
+}
+
+/// Gets the key of a file in the install marker
+fn get_marker_path(minecraft_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(minecraft_dir).unwrap_or(path).to_string_lossy().into_owned()
+}
+
+/// Records the hash and size of every file of a finished install
+///
+/// Downloaded files just passed their hash check, so their hash is taken from
+/// the JSON; files without one, and those an installer generated, are hashed.
+/// The version's own jar is read from the staging directory.
+fn create_install_marker(minecraft_dir: &Path, staging_dir: &Path, version_details: &VersionDetails) -> Result<InstallMarker> {
+    let own_jar = minecraft_dir.join("versions").join(&version_details.id).join(format!("{}.jar", version_details.id));
+    let mut files = BTreeMap::new();
+
+    for file in get_install_files(minecraft_dir, version_details)? {
+        let disk_path = if file.path == own_jar {
+            staging_dir.join(format!("{}.jar", version_details.id))
+        } else {
+            file.path.clone()
+        };
+
+        let size = fs::metadata(&disk_path)
+            .map_err(|e| anyhow!("{:?} is missing after the install: {}", disk_path, e))?
+            .len();
+
+        let sha1 = match (&file.sha1, &file.url) {
+            (Some(sha1), Some(_)) => sha1.to_ascii_lowercase(),
+            (expected, _) => {
+                let sha1 = get_file_sha1(&disk_path)?;
+                if expected.as_ref().is_some_and(|expected| !expected.eq_ignore_ascii_case(&sha1)) {
+                    return Err(anyhow!("Generated library {:?} is damaged, reinstall the mod loader", disk_path));
                 }
+                sha1
+            },
+        };
+
+        files.insert(get_marker_path(minecraft_dir, &file.path), InstalledFile { sha1, size });
+    }
+
+    // The JSON too, so a JSON changed since the install is noticed without resolving it
+    let staged_json = staging_dir.join(format!("{}.json", version_details.id));
+    let json = InstalledFile { sha1: get_file_sha1(&staged_json)?, size: fs::metadata(&staged_json)?.len() };
+    files.insert(get_marker_path(minecraft_dir, &get_version_json_path(minecraft_dir, &version_details.id)), json);
+
+    Ok(InstallMarker::new(files))
+}
+
+/// Queues the libraries for a Minecraft version
+///
+/// Returns the native jars to extract after the download, with their exclusions.
+fn queue_libraries(
+    queue: &mut DownloadQueue,
+    minecraft_dir: &Path,
+    version_details: &VersionDetails,
+) -> Vec<(PathBuf, Vec<String>)> {
+    info!("Queueing libraries for Minecraft version {}", version_details.id);
 
-                let group = parts[0].replace('.', "/");
-                let artifact_id = parts[1];
-                let version = parts[2];
+    let libraries_dir = minecraft_dir.join("libraries");
+    let environment = RuleEnvironment::current();
+    let mut natives_to_extract = Vec::new();
 
-                let library_path = libraries_dir.join(format!("{}/{}/{}/{}-{}.jar", 
-                    group, artifact_id, version, artifact_id, version));
-                let library_dir = library_path.parent().unwrap();
+    for library in &version_details.libraries {
+        // Check if the library should be downloaded for this OS
+        if !rules_allow(library.rules.as_deref(), &environment) {
+            continue;
+        }
 
-                file_manager.create_dir_all(library_dir).await?;
+        // Queue the main artifact if it exists
+        if let Some(artifact) = get_library_artifact(&libraries_dir, library) {
+            match artifact.url {
+                Some(url) => {
+                    debug!("Queueing library: {}", library.name);
+                    queue.add(DownloadJob { url, path: artifact.path, sha1: artifact.sha1, size: artifact.size });
+                },
+                // Installers generate some of their libraries, there is nothing to download
+                None => debug!("Library {} has no download", library.name),
+            }
+        }
 
-                info!("Downloading library: {}", library.name);
-                file_manager.download_file(
-                    &artifact.url,
-                    &library_path,
-                    Some(&artifact.sha1),
-                    progress_callback.clone(),
-                ).await?;
+        // Queue natives if they exist
+        if let Some(native) = get_library_native(&libraries_dir, library, &environment.os_name) {
+            if let Some(url) = native.url {
+                debug!("Queueing native library: {}", library.name);
+                queue.add(DownloadJob { url, path: native.path.clone(), sha1: native.sha1, size: native.size });
             }
 
-            // Download natives if they exist
-            if let Some(classifiers) = &downloads.classifiers {
-                if let Some(natives) = &library.natives {
-                    if let Some(native_key) = natives.get(os_name) {
-                        if let Some(native_artifact) = classifiers.get(native_key) {
-                            // Determine the native library path from the library name
-                            // Format: group:artifact:version
-                            // Example: org.lwjgl:lwjgl:3.2.2
-                            let parts: Vec<&str> = library.name.split(':').collect();
-                            if parts.len() < 3 {
-                                warn!("Invalid library name format for native: {}", library.name);
-                                continue;
-                            }
-
-                            let group = parts[0].replace('.', "/");
-                            let artifact_id = parts[1];
-                            let version = parts[2];
-
-                            // For natives, we need to add the classifier to the filename
-                            let native_path = libraries_dir.join(format!("{}/{}/{}/{}-{}-{}.jar", 
-                                group, artifact_id, version, artifact_id, version, native_key));
-                            let native_dir = native_path.parent().unwrap();
-
-                            file_manager.create_dir_all(native_dir).await?;
-
-                            info!("Downloading native library: {}", library.name);
-                            file_manager.download_file(
-                                &native_artifact.url,
-                                &native_path,
-                                Some(&native_artifact.sha1),
-                                progress_callback.clone(),
-                            ).await?;
-
-                            // Extract natives if needed
-                            if let Some(extract) = &library.extract {
-                                let natives_dir = minecraft_dir.join("versions")
-                                    .join(&version_details.id)
-                                    .join("natives");
-
-                                file_manager.create_dir_all(&natives_dir).await?;
-
-                                info!("Extracting native library: {}", library.name);
-                                extract_natives(
-                                    &native_path,
-                                    &natives_dir,
-                                    &extract.exclude,
-                                )?;
-                            }
-                        }
-                    }
-                }
+            if let Some(extract) = &library.extract {
+                natives_to_extract.push((native.path, extract.exclude.clone()));
             }
         }
     }
 
-    info!("Libraries downloaded successfully");
-    Ok(())
+    natives_to_extract
 }
 
-/// Checks if a library should be downloaded for the current OS
-fn should_download_library(library: &Library, os_name: &str, os_arch: &str) -> bool {
-    // If there are no rules, the library is always included
-    if library.rules.is_none() {
-        return true;
-    }
+/// Repository used for libraries that name neither a download nor a repository
+const DEFAULT_LIBRARY_REPOSITORY: &str = "https://libraries.minecraft.net/";
 
-    let rules = library.rules.as_ref().unwrap();
-    let mut allowed = false;
+/// A library, jar or asset to put on disk
+struct LibraryFile {
+    path: PathBuf,
+    /// None for files an installer generates
+    url: Option<String>,
+    sha1: Option<String>,
+    size: Option<u64>,
+}
 
-    for rule in rules {
-        let action_allowed = rule.action == "allow";
+/// Gets where the log4j configuration of a version goes, if it has one
+///
+/// The vanilla launcher keeps them in `assets/log_configs`, by the name in the version JSON.
+pub fn get_logging_config_path(minecraft_dir: &Path, version_details: &VersionDetails) -> Option<PathBuf> {
+    let client = &version_details.logging.as_ref()?.client;
+    if client.r#type != "log4j2-xml" {
+        warn!("Unknown logging config type {} of version {}", client.r#type, version_details.id);
+        return None;
+    }
 
-        // If there's no OS specified, the rule applies to all OSes
-        if rule.os.is_none() {
-            allowed = action_allowed;
-            continue;
-        }
+    // The name comes from a downloaded JSON, keep it inside log_configs
+    let file_name = Path::new(&client.file.id).file_name()?;
+    if file_name != client.file.id.as_str() {
+        warn!("Ignoring logging config {:?} of version {}", client.file.id, version_details.id);
+        return None;
+    }
 
-        let os = rule.os.as_ref().unwrap();
+    Some(minecraft_dir.join("assets").join("log_configs").join(file_name))
+}
 
-        // Check if the rule applies to the current OS
-        let os_matches = match &os.name {
-            Some(name) => name == os_name,
-            None => true,
-        };
+/// Gets the log4j configuration of a version as a file to download
+fn get_logging_config(minecraft_dir: &Path, version_details: &VersionDetails) -> Option<LibraryFile> {
+    let path = get_logging_config_path(minecraft_dir, version_details)?;
+    let file = &version_details.logging.as_ref()?.client.file;
+
+    Some(LibraryFile {
+        path,
+        url: Some(file.url.clone()).filter(|url| !url.is_empty()),
+        sha1: Some(file.sha1.clone()),
+        size: Some(file.size),
+    })
+}
 
-        // Check if the rule applies to the current OS version
-        let version_matches = match &os.version {
-            Some(_version) => {
-                // TODO: Implement version matching if needed
-                true
-            },
-            None => true,
-        };
+/// Gets the path of a library below the libraries directory from its Maven coordinates
+///
+/// Format: `group:artifact:version[:classifier][@extension]`, e.g. `org.lwjgl:lwjgl:3.2.2`
+/// is `org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2.jar`.
+pub fn get_maven_path(name: &str) -> Option<String> {
+    let (coordinates, extension) = name.split_once('@').unwrap_or((name, "jar"));
+    let parts: Vec<&str> = coordinates.split(':').collect();
+
+    let (group, artifact, version, classifier) = match parts.as_slice() {
+        [group, artifact, version] => (group, artifact, version, None),
+        [group, artifact, version, classifier] => (group, artifact, version, Some(classifier)),
+        _ => return None,
+    };
 
-        // Check if the rule applies to the current OS architecture
-        let arch_matches = match &os.arch {
-            Some(arch) => arch == os_arch,
-            None => true,
-        };
+    let file_name = match classifier {
+        Some(classifier) => format!("{}-{}-{}.{}", artifact, version, classifier, extension),
+        None => format!("{}-{}.{}", artifact, version, extension),
+    };
 
-        // If all conditions match, apply the rule
-        if os_matches && version_matches && arch_matches {
-            allowed = action_allowed;
-        }
+    Some(format!("{}/{}/{}/{}", group.replace('.', "/"), artifact, version, file_name))
+}
+
+/// Gets the main jar of a library
+///
+/// Libraries from Mojang and Forge list their download; loader libraries only
+/// name a Maven repository and the file is found from the coordinates.
+fn get_library_artifact(libraries_dir: &Path, library: &Library) -> Option<LibraryFile> {
+    match &library.downloads {
+        // A library with downloads but no artifact only has natives
+        Some(downloads) => {
+            let artifact = downloads.artifact.as_ref()?;
+            let path = match &artifact.path {
+                Some(path) => path.clone(),
+                None => get_maven_path(&library.name)?,
+            };
+
+            Some(LibraryFile {
+                path: libraries_dir.join(path),
+                url: Some(artifact.url.clone()).filter(|url| !url.is_empty()),
+                sha1: Some(artifact.sha1.clone()),
+                size: Some(artifact.size),
+            })
+        },
+        None => {
+            let path = match get_maven_path(&library.name) {
+                Some(path) => path,
+                None => {
+                    warn!("Invalid library name format: {}", library.name);
+                    return None;
+                }
+            };
+
+            let repository = library.url.as_deref().unwrap_or(DEFAULT_LIBRARY_REPOSITORY);
+            let url = format!("{}/{}", repository.trim_end_matches('/'), path);
+
+            Some(LibraryFile {
+                path: libraries_dir.join(path),
+                url: Some(url),
+                sha1: library.sha1.clone(),
+                size: library.size,
+            })
+        },
     }
+}
+
+/// Gets the natives jar of a library for the current OS
+fn get_library_native(libraries_dir: &Path, library: &Library, os_name: &str) -> Option<LibraryFile> {
+    let native_key = library.natives.as_ref()?.get(os_name)?;
+    // Some old natives name the JVM's bitness, e.g. natives-windows-${arch}
+    let native_key = native_key.replace("${arch}", if cfg!(target_pointer_width = "64") { "64" } else { "32" });
+
+    let classifiers = library.downloads.as_ref().and_then(|downloads| downloads.classifiers.as_ref());
+    let native_artifact = match classifiers {
+        Some(classifiers) => classifiers.get(&native_key)?,
+        // Older version JSONs only name the classifier, the jar sits next to the library's own
+        None => {
+            let path = get_maven_path(&format!("{}:{}", library.name, native_key))?;
+            let repository = library.url.as_deref().unwrap_or(DEFAULT_LIBRARY_REPOSITORY);
+            let url = format!("{}/{}", repository.trim_end_matches('/'), path);
+
+            return Some(LibraryFile {
+                path: libraries_dir.join(path),
+                url: Some(url),
+                sha1: None,
+                size: None,
+            });
+        },
+    };
+    let path = match &native_artifact.path {
+        Some(path) => path.clone(),
+        None => get_maven_path(&format!("{}:{}", library.name, native_key))?,
+    };
+
+    Some(LibraryFile {
+        path: libraries_dir.join(path),
+        url: Some(native_artifact.url.clone()).filter(|url| !url.is_empty()),
+        sha1: Some(native_artifact.sha1.clone()),
+        size: Some(native_artifact.size),
+    })
+}
 
-    allowed
+/// Gets the client jar a version runs on, which may belong to the version it inherits from
+fn get_client_jar_path(minecraft_dir: &Path, version_details: &VersionDetails) -> PathBuf {
+    let jar_id = version_details.get_jar_id();
+    minecraft_dir.join("versions").join(jar_id).join(format!("{}.jar", jar_id))
 }
 
 /// Extracts native libraries from a JAR file
@@ -298,56 +686,66 @@ fn extract_natives(jar_path: &Path, output_dir: &Path, exclude: &[String]) -> Re
     Ok(())
 }
 
-/// Downloads the assets for a Minecraft version
-async fn download_assets(
+/// Downloads the asset index for a Minecraft version and queues its objects
+async fn queue_assets(
     file_manager: &FileManager,
+    queue: &mut DownloadQueue,
     minecraft_dir: &Path,
     version_details: &VersionDetails,
-    progress_callback: impl Fn(DownloadProgress) + Send + Sync + 'static + Clone,
+    tracker: &ProgressTracker,
 ) -> Result<()> {
-    info!("Downloading assets for Minecraft version {}", version_details.id);
+    info!("Queueing assets for Minecraft version {}", version_details.id);
 
     let assets_dir = minecraft_dir.join("assets");
-    file_manager.create_dir_all(&assets_dir).await?;
-
     let indexes_dir = assets_dir.join("indexes");
     file_manager.create_dir_all(&indexes_dir).await?;
 
     let objects_dir = assets_dir.join("objects");
-    file_manager.create_dir_all(&objects_dir).await?;
 
     // Get the asset index
     let asset_index = version_details.assets_index.as_ref()
         .ok_or_else(|| anyhow!("Asset index not found for version {}", version_details.id))?;
 
-    // Download the asset index
+    // The index lists the objects, so it has to be downloaded before anything is queued
     let index_path = indexes_dir.join(format!("{}.json", asset_index.id));
 
     info!("Downloading asset index for version {}", version_details.id);
-    file_manager.download_file(
+    tracker.add_file(&asset_index.url, Some(asset_index.size));
+    let index_result = file_manager.download_file(
         &asset_index.url,
         &index_path,
         Some(&asset_index.sha1),
-        progress_callback.clone(),
-    ).await?;
+        tracker.download_callback(),
+    ).await;
+    tracker.finish_file(&asset_index.url);
+    index_result?;
+
+    // Hashing the objects that are already there blocks, keep it off the runtime
+    let jobs = tokio::task::spawn_blocking(move || get_missing_assets(&index_path, &objects_dir)).await??;
+    for job in jobs {
+        queue.add(job);
+    }
+
+    Ok(())
+}
 
-    // Parse the asset index
-    let index_json = file_manager.read_to_string(&index_path).await?;
-    let asset_objects: AssetObjects = serde_json::from_str(&index_json)?;
+/// Lists the downloads for the objects of an asset index that are missing or damaged
+fn get_missing_assets(index_path: &Path, objects_dir: &Path) -> Result<Vec<DownloadJob>> {
+    let asset_objects: AssetObjects = serde_json::from_str(&fs::read_to_string(index_path)?)?;
+    let mut jobs = Vec::new();
 
-    // Download each asset
     for (asset_name, asset) in &asset_objects.objects {
-        let hash_prefix = &asset.hash[0..2];
-        let hash_dir = objects_dir.join(hash_prefix);
-        file_manager.create_dir_all(&hash_dir).await?;
+        if asset.hash.len() < 2 {
+            warn!("Invalid hash for asset {}: {}", asset_name, asset.hash);
+            continue;
+        }
 
-        let asset_path = hash_dir.join(&asset.hash);
+        let hash_prefix = &asset.hash[0..2];
+        let asset_path = objects_dir.join(hash_prefix).join(&asset.hash);
 
         // Skip if the asset already exists and has the correct hash
-        if asset_path.exists() {
-            if verify_file_hash(&asset_path, &asset.hash)? {
-                continue;
-            }
+        if asset_path.exists() && verify_file_hash(&asset_path, &asset.hash)? {
+            continue;
         }
 
         let asset_url = format!(
@@ -355,150 +753,759 @@ async fn download_assets(
             hash_prefix, asset.hash
         );
 
-        info!("Downloading asset: {}", asset_name);
-        file_manager.download_file(
-            &asset_url,
-            &asset_path,
-            Some(&asset.hash),
-            progress_callback.clone(),
-        ).await?;
+        jobs.push(DownloadJob {
+            url: asset_url,
+            path: asset_path,
+            sha1: Some(asset.hash.clone()),
+            size: Some(asset.size),
+        });
     }
 
-    info!("Assets downloaded successfully");
-    Ok(())
+    Ok(jobs)
 }
 
-/// Verifies the hash of a file
-fn verify_file_hash(file_path: &Path, expected_hash: &str) -> Result<bool> {
+/// Lays out the assets of an old version by name, where the game looks for them
+///
+/// Indexes marked `virtual` read their assets from `assets/virtual/<index>`,
+/// `map_to_resources` ones from `<game dir>/resources`. The objects are copied
+/// there from the hashed store; files that are already in place are kept.
+/// Returns the directory for `${game_assets}`, None for the hashed layout
+/// every version since 1.7.10 uses.
+pub fn prepare_asset_layout(minecraft_dir: &Path, version_details: &VersionDetails, game_dir: &Path) -> Result<Option<PathBuf>> {
+    let index_id = match &version_details.assets_index {
+        Some(asset_index) => asset_index.id.clone(),
+        None => version_details.assets.clone(),
+    };
+
+    let assets_dir = minecraft_dir.join("assets");
+    let index_path = assets_dir.join("indexes").join(format!("{}.json", index_id));
+    let asset_objects: AssetObjects = match fs::read_to_string(&index_path) {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|e| anyhow!("Failed to parse asset index {:?}: {}", index_path, e))?,
+        Err(_) => return Ok(None),
+    };
+
+    let target_dir = if asset_objects.map_to_resources {
+        game_dir.join("resources")
+    } else if asset_objects.is_virtual || index_id == "legacy" {
+        assets_dir.join("virtual").join(&index_id)
+    } else {
+        return Ok(None);
+    };
+
+    info!("Laying out {} assets of index {} in {:?}", asset_objects.objects.len(), index_id, target_dir);
+    let objects_dir = assets_dir.join("objects");
+    let mut copied = 0;
+
+    for (asset_name, asset) in &asset_objects.objects {
+        // Names come from the index, never let one point outside the target
+        let relative = Path::new(asset_name);
+        if asset.hash.len() < 2 || relative.components().any(|component| !matches!(component, std::path::Component::Normal(_))) {
+            warn!("Skipping asset with invalid name or hash: {} ({})", asset_name, asset.hash);
+            continue;
+        }
+
+        let target = target_dir.join(relative);
+        if fs::metadata(&target).map_or(false, |metadata| metadata.len() == asset.size) {
+            continue;
+        }
+
+        let source = objects_dir.join(&asset.hash[0..2]).join(&asset.hash);
+        if !source.exists() {
+            warn!("Asset {} is missing from {:?}", asset_name, source);
+            continue;
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&source, &target)?;
+        copied += 1;
+    }
+
+    if copied > 0 {
+        info!("Copied {} assets to {:?}", copied, target_dir);
+    }
+    Ok(Some(target_dir))
+}
+
+/// Gets the SHA-1 of a file
+fn get_file_sha1(file_path: &Path) -> Result<String> {
     let mut file = std::fs::File::open(file_path)?;
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
 
     let mut hasher = Sha1::new();
     hasher.update(&buffer);
-    let hash = format!("{:x}", hasher.finalize());
+    Ok(format!("{:x}", hasher.finalize()))
+}
 
-    Ok(hash == expected_hash)
+/// Verifies the hash of a file
+fn verify_file_hash(file_path: &Path, expected_hash: &str) -> Result<bool> {
+    Ok(get_file_sha1(file_path)?.eq_ignore_ascii_case(expected_hash))
 }
 
 /// Checks if a Minecraft version is installed
+///
+/// Cheap enough to run for every profile: the install marker has to be
+/// intact and record the version JSON as it is on disk, and the files in the
+/// version directory have to keep the size the marker records. An install
+/// that stopped halfway, a JSON changed since, or a truncated jar count as
+/// not installed. Libraries and assets are only checked by `verify_version`.
+/// A version that inherits from another is only installed together with it.
 pub fn is_version_installed(minecraft_dir: &Path, version_id: &str) -> bool {
+    let mut visited = HashSet::new();
+    let mut current = version_id.to_string();
+
+    loop {
+        if !visited.insert(current.clone()) {
+            debug!("Version {} inherits from itself through {}", version_id, current);
+            return false;
+        }
+        if !has_complete_install(minecraft_dir, &current) {
+            return false;
+        }
+
+        match read_version_json(minecraft_dir, &current) {
+            Ok(version_details) => match version_details.inherits_from {
+                Some(parent) => current = parent,
+                None => return true,
+            },
+            Err(e) => {
+                debug!("Version {} is not installed: {}", version_id, e);
+                return false;
+            },
+        }
+    }
+}
+
+/// Checks the install marker of a single version against its JSON and version directory
+fn has_complete_install(minecraft_dir: &Path, version_id: &str) -> bool {
     let version_dir = minecraft_dir.join("versions").join(version_id);
-    let jar_path = version_dir.join(format!("{}.jar", version_id));
-    let json_path = version_dir.join(format!("{}.json", version_id));
+    let marker: InstallMarker = match fs::read_to_string(version_dir.join(INSTALL_MARKER)).ok().and_then(|json| serde_json::from_str(&json).ok()) {
+        Some(marker) => marker,
+        None => return false,
+    };
+    if !marker.is_intact() {
+        warn!("Install marker of {} is damaged", version_id);
+        return false;
+    }
+
+    let json_path = get_version_json_path(minecraft_dir, version_id);
+    let json_unchanged = marker.files.get(&get_marker_path(minecraft_dir, &json_path))
+        .is_some_and(|installed| get_file_sha1(&json_path).is_ok_and(|sha1| sha1 == installed.sha1));
+    if !json_unchanged {
+        debug!("JSON of version {} changed since it was installed", version_id);
+        return false;
+    }
 
-    jar_path.exists() && json_path.exists()
+    let version_prefix = get_marker_path(minecraft_dir, &version_dir);
+    marker.files.iter()
+        .filter(|(path, _)| Path::new(path).starts_with(&version_prefix))
+        .all(|(path, installed)| {
+            let intact = fs::metadata(minecraft_dir.join(path)).is_ok_and(|metadata| metadata.len() == installed.size);
+            if !intact {
+                debug!("{} of version {} changed since it was installed", path, version_id);
+            }
+            intact
+        })
 }
 
-/// Gets the list of installed Minecraft versions
-pub fn get_installed_versions(minecraft_dir: &Path) -> Vec<String> {
-    let versions_dir = minecraft_dir.join("versions");
+/// Finds the first version in the inheritance chain of `version_id` that has no JSON on disk
+///
+/// Returns None once every version the chain names is there.
+pub fn find_missing_version(minecraft_dir: &Path, version_id: &str) -> Result<Option<String>> {
+    let mut visited = HashSet::new();
+    let mut current = version_id.to_string();
+
+    loop {
+        if !visited.insert(current.clone()) {
+            return Err(anyhow!("Version {} inherits from itself through {}", version_id, current));
+        }
+        if !get_version_json_path(minecraft_dir, &current).exists() {
+            return Ok(Some(current));
+        }
 
-    if !versions_dir.exists() {
-        return Vec::new();
+        match read_version_json(minecraft_dir, &current)?.inherits_from {
+            Some(parent) => current = parent,
+            None => return Ok(None),
+        }
     }
+}
 
-    let entries = match fs::read_dir(versions_dir) {
-        Ok(entries) => entries,
-        Err(_) => return Vec::new(),
-    };
+/// Lists the files a launch of an installed version needs that are not on disk
+///
+/// Only checks that files exist, so this stays cheap enough to run before every launch.
+pub fn get_missing_files(minecraft_dir: &Path, version_details: &VersionDetails) -> Vec<PathBuf> {
+    let mut missing = Vec::new();
+
+    let client_jar = get_client_jar_path(minecraft_dir, version_details);
+    if !client_jar.exists() {
+        missing.push(client_jar);
+    }
+
+    let environment = RuleEnvironment::current();
+    let libraries_dir = minecraft_dir.join("libraries");
+    let natives_dir = minecraft_dir.join("versions").join(&version_details.id).join("natives");
+
+    for library in &version_details.libraries {
+        if !rules_allow(library.rules.as_deref(), &environment) {
+            continue;
+        }
+
+        let native = get_library_native(&libraries_dir, library, &environment.os_name);
 
-    let mut versions = Vec::new();
+        // Natives are extracted per version, so a version sharing its jars still needs its own copy
+        if native.is_some() && library.extract.is_some() && !natives_dir.exists() && !missing.contains(&natives_dir) {
+            missing.push(natives_dir.clone());
+        }
+
+        for file in get_library_artifact(&libraries_dir, library).into_iter().chain(native) {
+            if file.path.exists() {
+                continue;
+            }
+            if file.url.is_none() {
+                warn!("Generated library {:?} is missing, reinstall the mod loader to restore it", file.path);
+                continue;
+            }
+            missing.push(file.path);
+        }
+    }
 
-    for entry in entries {
-        if let Ok(entry) = entry {
-            if entry.path().is_dir() {
-                if let Some(name) = entry.file_name().to_str() {
-                    let jar_path = entry.path().join(format!("{}.jar", name));
-                    let json_path = entry.path().join(format!("{}.json", name));
+    if let Some(logging_config) = get_logging_config(minecraft_dir, version_details) {
+        if !logging_config.path.exists() {
+            missing.push(logging_config.path);
+        }
+    }
 
-                    if jar_path.exists() && json_path.exists() {
-                        versions.push(name.to_string());
+    // Assets are checked through the index, a missing index means all of them are missing
+    if let Some(asset_index) = &version_details.assets_index {
+        let assets_dir = minecraft_dir.join("assets");
+        let index_path = assets_dir.join("indexes").join(format!("{}.json", asset_index.id));
+
+        let asset_objects = fs::read_to_string(&index_path).ok()
+            .and_then(|json| serde_json::from_str::<AssetObjects>(&json).ok());
+        match asset_objects {
+            Some(asset_objects) => {
+                for asset in asset_objects.objects.values() {
+                    if asset.hash.len() < 2 {
+                        continue;
+                    }
+                    let asset_path = assets_dir.join("objects").join(&asset.hash[0..2]).join(&asset.hash);
+                    if !asset_path.exists() {
+                        missing.push(asset_path);
                     }
                 }
+            },
+            None => missing.push(index_path),
+        }
+    }
+
+    missing
+}
+
+/// Why a file of an installed version failed verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileIssue {
+    Missing,
+    WrongSize { expected: u64, actual: u64 },
+    WrongHash,
+}
+
+impl std::fmt::Display for FileIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileIssue::Missing => write!(f, "missing"),
+            FileIssue::WrongSize { expected, actual } => write!(f, "{} bytes instead of {}", actual, expected),
+            FileIssue::WrongHash => write!(f, "checksum mismatch"),
+        }
+    }
+}
+
+/// A file that is missing or doesn't match the version JSON
+#[derive(Debug, Clone)]
+pub struct DamagedFile {
+    pub path: PathBuf,
+    pub issue: FileIssue,
+    /// None for files an installer generates, which can't be downloaded again
+    pub url: Option<String>,
+    pub sha1: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// Result of checking every file of an installed version
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Number of files that were checked
+    pub checked: usize,
+    pub damaged: Vec<DamagedFile>,
+    /// The natives extracted for the version are gone
+    pub natives_missing: bool,
+}
+
+impl VerifyReport {
+    /// Returns true if nothing needs repairing
+    pub fn is_intact(&self) -> bool {
+        self.damaged.is_empty() && !self.natives_missing
+    }
+}
+
+/// Checks the client jar, libraries, natives, logging config and assets of a version against its JSON
+///
+/// Reads every file to compare its size and SHA-1, so this is much slower than
+/// `get_missing_files` and blocks; async callers run it with `spawn_blocking`.
+/// An asset index that is damaged itself is reported without checking the
+/// objects it lists.
+pub fn verify_version(
+    minecraft_dir: &Path,
+    version_details: &VersionDetails,
+    tracker: &ProgressTracker,
+    cancel: &CancellationToken,
+) -> Result<VerifyReport> {
+    info!("Verifying files of Minecraft version {}", version_details.id);
+    tracker.restart(InstallPhase::Verifying);
+
+    let mut report = VerifyReport::default();
+    let mut files = Vec::new();
+
+    let client_download = version_details.downloads.get("client");
+    files.push(LibraryFile {
+        path: get_client_jar_path(minecraft_dir, version_details),
+        url: client_download.map(|download| download.url.clone()),
+        sha1: client_download.map(|download| download.sha1.clone()),
+        size: client_download.map(|download| download.size),
+    });
+
+    let environment = RuleEnvironment::current();
+    let libraries_dir = minecraft_dir.join("libraries");
+    let natives_dir = minecraft_dir.join("versions").join(&version_details.id).join("natives");
+
+    for library in &version_details.libraries {
+        if !rules_allow(library.rules.as_deref(), &environment) {
+            continue;
+        }
+
+        let native = get_library_native(&libraries_dir, library, &environment.os_name);
+        if native.is_some() && library.extract.is_some() && !natives_dir.exists() {
+            report.natives_missing = true;
+        }
+
+        files.extend(get_library_artifact(&libraries_dir, library).into_iter().chain(native));
+    }
+
+    files.extend(get_logging_config(minecraft_dir, version_details));
+
+    // The objects can only be listed from an intact index
+    if let Some(asset_index) = &version_details.assets_index {
+        let index_path = minecraft_dir.join("assets").join("indexes").join(format!("{}.json", asset_index.id));
+        let index = LibraryFile {
+            path: index_path.clone(),
+            url: Some(asset_index.url.clone()),
+            sha1: Some(asset_index.sha1.clone()),
+            size: Some(asset_index.size),
+        };
+
+        match check_file(&index)? {
+            Some(issue) => report.damaged.push(damaged_file(index, issue)),
+            None => {
+                report.checked += 1;
+                files.extend(get_asset_files(minecraft_dir, &index_path)?);
+            },
+        }
+    }
+
+    // Libraries shared by several entries are only checked once
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(file.path.clone()));
+
+    for file in &files {
+        tracker.add_file(&file.path.to_string_lossy(), file.size);
+    }
+
+    for file in files {
+        cancel.check()?;
+
+        let issue = check_file(&file)?;
+        tracker.finish_file(&file.path.to_string_lossy());
+        report.checked += 1;
+
+        if let Some(issue) = issue {
+            warn!("{:?} is damaged: {}", file.path, issue);
+            report.damaged.push(damaged_file(file, issue));
+        }
+    }
+
+    info!(
+        "Verified {} files of version {}, {} damaged",
+        report.checked, version_details.id, report.damaged.len()
+    );
+    Ok(report)
+}
+
+/// Downloads the damaged files of a verified version again and restores its natives
+///
+/// Files without a download are skipped, they come back by reinstalling the
+/// mod loader that generated them.
+pub async fn repair_version(
+    file_manager: &FileManager,
+    minecraft_dir: &Path,
+    version_details: &VersionDetails,
+    report: &VerifyReport,
+    tracker: &ProgressTracker,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    info!("Repairing Minecraft version {}", version_details.id);
+    tracker.restart(InstallPhase::Repairing);
+
+    let file_manager = file_manager.with_cancellation(cancel);
+    let mut damaged = report.damaged.clone();
+
+    // A damaged index hid the state of the objects, check them once it is replaced
+    if let Some(asset_index) = &version_details.assets_index {
+        let index_path = minecraft_dir.join("assets").join("indexes").join(format!("{}.json", asset_index.id));
+
+        if let Some(position) = damaged.iter().position(|file| file.path == index_path) {
+            damaged.remove(position);
+
+            info!("Downloading asset index for version {}", version_details.id);
+            file_manager.download_file(
+                &asset_index.url,
+                &index_path,
+                Some(&asset_index.sha1),
+                tracker.download_callback(),
+            ).await?;
+
+            for file in get_asset_files(minecraft_dir, &index_path)? {
+                cancel.check()?;
+                if let Some(issue) = check_file(&file)? {
+                    damaged.push(damaged_file(file, issue));
+                }
+            }
+        }
+    }
+
+    let mut queue = DownloadQueue::new(&file_manager);
+    for file in &damaged {
+        match &file.url {
+            Some(url) => {
+                queue.add(DownloadJob {
+                    url: url.clone(),
+                    path: file.path.clone(),
+                    sha1: file.sha1.clone().filter(|sha1| !sha1.is_empty()),
+                    size: file.size,
+                });
+            },
+            None => warn!("Generated file {:?} is {}, reinstall the mod loader to restore it", file.path, file.issue),
+        }
+    }
+
+    queue.run(tracker).await?;
+
+    // Natives are extracted from their jars, so a replaced jar means extracting again
+    let natives = get_natives_to_extract(minecraft_dir, version_details);
+    let natives_replaced = natives.iter()
+        .any(|(native_path, _)| damaged.iter().any(|file| file.path == *native_path));
+
+    if !natives.is_empty() && (report.natives_missing || natives_replaced) {
+        let natives_dir = minecraft_dir.join("versions").join(&version_details.id).join("natives");
+        file_manager.create_dir_all(&natives_dir).await?;
+        extract_all_natives(natives, natives_dir, cancel).await?;
+    }
+
+    info!("Minecraft version {} repaired", version_details.id);
+    Ok(())
+}
+
+/// Lists the asset objects of a downloaded index
+fn get_asset_files(minecraft_dir: &Path, index_path: &Path) -> Result<Vec<LibraryFile>> {
+    let index_json = fs::read_to_string(index_path)?;
+    let asset_objects: AssetObjects = serde_json::from_str(&index_json)
+        .map_err(|e| anyhow!("Failed to parse asset index {:?}: {}", index_path, e))?;
+
+    let objects_dir = minecraft_dir.join("assets").join("objects");
+    Ok(asset_objects.objects.values()
+        .filter(|asset| asset.hash.len() >= 2)
+        .map(|asset| {
+            let hash_prefix = &asset.hash[0..2];
+            LibraryFile {
+                path: objects_dir.join(hash_prefix).join(&asset.hash),
+                url: Some(format!("https://resources.download.minecraft.net/{}/{}", hash_prefix, asset.hash)),
+                sha1: Some(asset.hash.clone()),
+                size: Some(asset.size),
             }
+        })
+        .collect())
+}
+
+/// Gets the native jars of a version with their exclusions
+fn get_natives_to_extract(minecraft_dir: &Path, version_details: &VersionDetails) -> Vec<(PathBuf, Vec<String>)> {
+    let environment = RuleEnvironment::current();
+    let libraries_dir = minecraft_dir.join("libraries");
+
+    version_details.libraries.iter()
+        .filter(|library| rules_allow(library.rules.as_deref(), &environment))
+        .filter_map(|library| {
+            let extract = library.extract.as_ref()?;
+            let native = get_library_native(&libraries_dir, library, &environment.os_name)?;
+            Some((native.path, extract.exclude.clone()))
+        })
+        .collect()
+}
+
+/// Compares a file on disk with its expected size and SHA-1
+fn check_file(file: &LibraryFile) -> Result<Option<FileIssue>> {
+    let metadata = match fs::metadata(&file.path) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return Ok(Some(FileIssue::Missing)),
+    };
+
+    // Some loader JSONs list a size of 0 where they don't know it
+    if let Some(expected) = file.size.filter(|size| *size > 0) {
+        if metadata.len() != expected {
+            return Ok(Some(FileIssue::WrongSize { expected, actual: metadata.len() }));
         }
     }
 
-    versions
+    if let Some(sha1) = file.sha1.as_deref().filter(|sha1| !sha1.is_empty()) {
+        let matches = verify_file_hash(&file.path, sha1)
+            .map_err(|e| anyhow!("Failed to read {:?}: {}", file.path, e))?;
+        if !matches {
+            return Ok(Some(FileIssue::WrongHash));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Records a file that failed verification
+fn damaged_file(file: LibraryFile, issue: FileIssue) -> DamagedFile {
+    DamagedFile { path: file.path, issue, url: file.url, sha1: file.sha1, size: file.size }
+}
+
+/// Gets the list of installed Minecraft versions
+pub fn get_installed_versions(minecraft_dir: &Path) -> Vec<String> {
+    get_version_ids(minecraft_dir).into_iter()
+        .filter(|version_id| is_version_installed(minecraft_dir, version_id))
+        .collect()
+}
+
+/// Gets the ids of the versions with a JSON on disk, installed or not
+fn get_version_ids(minecraft_dir: &Path) -> Vec<String> {
+    let entries = match fs::read_dir(minecraft_dir.join("versions")) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries.flatten()
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|version_id| get_version_json_path(minecraft_dir, version_id).exists())
+        .collect()
+}
+
+/// Finds the installed version a mod loader created for a Minecraft version
+///
+/// Loader installers name their versions as `importers::parse_version_id` expects.
+/// Without a loader version, or with `latest`, the last matching id wins.
+/// Versions only need their JSON, launching them downloads the rest.
+pub fn find_loader_version(
+    minecraft_dir: &Path,
+    game_version: &str,
+    mod_loader: &ModLoader,
+    loader_version: Option<&str>,
+) -> Option<String> {
+    let loader_version = loader_version.filter(|version| *version != LATEST_LOADER_VERSION);
+
+    get_version_ids(minecraft_dir).into_iter()
+        .filter(|version_id| {
+            let (minecraft_version, loader, installed_loader_version) = parse_version_id(version_id, &mut Vec::new());
+            minecraft_version == game_version
+                && loader == *mod_loader
+                && loader_version.map_or(true, |wanted| installed_loader_version.as_deref() == Some(wanted))
+        })
+        .max()
+}
+
+/// Gets the path of a version's JSON
+fn get_version_json_path(minecraft_dir: &Path, version_id: &str) -> PathBuf {
+    minecraft_dir.join("versions").join(version_id).join(format!("{}.json", version_id))
 }
 
-/// Loads the details for an installed Minecraft version
-pub async fn load_version_details(file_manager: &FileManager, minecraft_dir: &Path, version_id: &str) -> Result<VersionDetails> {
-    let json_path = minecraft_dir.join("versions").join(version_id).join(format!("{}.json", version_id));
+/// Reads the JSON of an installed version as it is, without its parents
+fn read_version_json(minecraft_dir: &Path, version_id: &str) -> Result<VersionDetails> {
+    let json_path = get_version_json_path(minecraft_dir, version_id);
 
     if !json_path.exists() {
         return Err(anyhow!("Version {} is not installed", version_id));
     }
 
-    let json = file_manager.read_to_string(&json_path).await?;
-    let details: VersionDetails = serde_json::from_str(&json)?;
+    let json = fs::read_to_string(&json_path)?;
+    serde_json::from_str(&json)
+        .map_err(|e| anyhow!("Failed to parse the JSON of version {}: {}", version_id, e))
+}
+
+/// Reads the JSONs of a version and the versions it inherits from, child first
+///
+/// Stops at the first version that isn't installed; fails on a JSON that
+/// can't be read, since what it uses is then unknown.
+pub fn read_version_chain(minecraft_dir: &Path, version_id: &str) -> Result<Vec<VersionDetails>> {
+    let mut chain = Vec::new();
+    let mut visited = HashSet::new();
+    let mut current = Some(version_id.to_string());
+
+    while let Some(id) = current {
+        if !visited.insert(id.clone()) || !get_version_json_path(minecraft_dir, &id).exists() {
+            break;
+        }
+
+        let version_details = read_version_json(minecraft_dir, &id)?;
+        current = version_details.inherits_from.clone();
+        chain.push(version_details);
+    }
 
-    Ok(details)
+    Ok(chain)
 }
 
-/// Builds the classpath for a Minecraft version
-pub fn build_classpath(minecraft_dir: &Path, version_details: &VersionDetails) -> Result<String> {
-    let mut classpath = String::new();
+/// Lists the jar, libraries, logging config, asset index and asset objects a version JSON names
+///
+/// Libraries are listed whatever their rules say, so nothing the version
+/// might load counts as unused. Library paths in the arguments count too.
+/// Fails if the asset index can't be read, since the objects it lists would
+/// otherwise count as unused.
+pub fn get_version_files(minecraft_dir: &Path, version_details: &VersionDetails) -> Result<Vec<PathBuf>> {
+    let mut files = vec![get_client_jar_path(minecraft_dir, version_details)];
 
-    // Add the client jar
-    let client_jar = minecraft_dir.join("versions")
-        .join(&version_details.id)
-        .join(format!("{}.jar", version_details.id));
+    let os_name = RuleEnvironment::current().os_name;
+    let libraries_dir = minecraft_dir.join("libraries");
+
+    for library in &version_details.libraries {
+        let artifact = get_library_artifact(&libraries_dir, library);
+        let native = get_library_native(&libraries_dir, library, &os_name);
+        files.extend(artifact.into_iter().chain(native).map(|file| file.path));
+    }
 
-    classpath.push_str(&client_jar.to_string_lossy());
+    files.extend(get_logging_config_path(minecraft_dir, version_details));
 
-    // Get the OS name for natives
-    let os_name = if cfg!(target_os = "windows") {
-        "windows"
-    } else if cfg!(target_os = "macos") {
-        "osx"
-    } else {
-        "linux"
-    };
+    // Forge and NeoForge put libraries on the module path in their arguments
+    for argument in get_argument_strings(version_details) {
+        for entry in argument.split("${classpath_separator}") {
+            if let Some((_, path)) = entry.split_once("${library_directory}/") {
+                files.push(libraries_dir.join(path));
+            }
+        }
+    }
 
-    let os_arch = if cfg!(target_arch = "x86_64") {
-        "x86_64"
-    } else if cfg!(target_arch = "x86") {
-        "x86"
-    } else if cfg!(target_arch = "aarch64") {
-        "arm64"
-    } else {
-        "unknown"
+    if let Some(asset_index) = &version_details.assets_index {
+        let index_path = minecraft_dir.join("assets").join("indexes").join(format!("{}.json", asset_index.id));
+
+        let objects = get_asset_files(minecraft_dir, &index_path)
+            .map_err(|e| anyhow!("Failed to read the asset index of version {}: {}", version_details.id, e))?;
+        files.extend(objects.into_iter().map(|object| object.path));
+        files.push(index_path);
+    }
+
+    Ok(files)
+}
+
+/// Lists the library directories a Forge or NeoForge installer generated jars into
+///
+/// The launch JSON doesn't name those jars, e.g. the deobfuscated client,
+/// FML finds them from the `--fml.*` arguments. Their directories are
+/// returned as a whole.
+pub fn get_generated_library_dirs(minecraft_dir: &Path, version_details: &VersionDetails) -> Vec<PathBuf> {
+    let arguments = get_argument_strings(version_details);
+    let value = |name: &str| arguments.windows(2).find(|pair| pair[0] == name).map(|pair| pair[1]);
+
+    let libraries_dir = minecraft_dir.join("libraries");
+    let mut dirs = Vec::new();
+
+    if let Some(minecraft_version) = value("--fml.mcVersion") {
+        if let Some(mcp_version) = value("--fml.mcpVersion").or_else(|| value("--fml.neoFormVersion")) {
+            dirs.push(libraries_dir.join("net/minecraft/client").join(format!("{}-{}", minecraft_version, mcp_version)));
+        }
+        if let Some(forge_version) = value("--fml.forgeVersion") {
+            let group = value("--fml.forgeGroup").unwrap_or("net.minecraftforge");
+            dirs.push(libraries_dir.join(group.replace('.', "/")).join("forge").join(format!("{}-{}", minecraft_version, forge_version)));
+        }
+    }
+    if let Some(neoforge_version) = value("--fml.neoForgeVersion") {
+        dirs.push(libraries_dir.join("net/neoforged/neoforge").join(neoforge_version));
+    }
+
+    dirs
+}
+
+/// Lists every string in a version's JVM and game arguments, whatever their rules say
+fn get_argument_strings(version_details: &VersionDetails) -> Vec<&str> {
+    let mut strings = Vec::new();
+    let arguments = match &version_details.arguments {
+        Some(arguments) => arguments,
+        None => return strings,
     };
 
+    for argument in arguments.jvm.iter().chain(&arguments.game) {
+        match argument {
+            serde_json::Value::String(value) => strings.push(value.as_str()),
+            serde_json::Value::Object(object) => match object.get("value") {
+                Some(serde_json::Value::String(value)) => strings.push(value.as_str()),
+                Some(serde_json::Value::Array(values)) => strings.extend(values.iter().filter_map(|value| value.as_str())),
+                _ => {},
+            },
+            _ => {},
+        }
+    }
+
+    strings
+}
+
+/// Loads an installed version by id, merged with every version it inherits from
+///
+/// This is what a launch runs: vanilla versions load as they are, loader
+/// versions come out with the vanilla libraries, arguments and assets.
+pub fn load_version(minecraft_dir: &Path, version_id: &str) -> Result<VersionDetails> {
+    let mut version_details = read_version_json(minecraft_dir, version_id)?;
+    let mut visited = HashSet::from([version_id.to_string()]);
+
+    while let Some(parent_id) = version_details.inherits_from.clone() {
+        if !visited.insert(parent_id.clone()) {
+            return Err(anyhow!("Version {} inherits from itself through {}", version_id, parent_id));
+        }
+
+        let parent = read_version_json(minecraft_dir, &parent_id)
+            .map_err(|e| anyhow!("Version {} inherits from {}: {}", version_id, parent_id, e))?;
+        debug!("Merging version {} over {}", version_details.id, parent_id);
+        version_details = version_details.inherit(parent);
+    }
+
+    Ok(version_details)
+}
+
+/// Builds the classpath for a Minecraft version
+pub fn build_classpath(minecraft_dir: &Path, version_details: &VersionDetails) -> Result<String> {
+    let mut entries = Vec::new();
+
+    // Add the client jar
+    entries.push(get_client_jar_path(minecraft_dir, version_details));
+
+    let environment = RuleEnvironment::current();
+    let libraries_dir = minecraft_dir.join("libraries");
+
     // Add the libraries
     for library in &version_details.libraries {
         // Check if the library should be included for this OS
-        if !should_download_library(library, os_name, os_arch) {
+        if !rules_allow(library.rules.as_deref(), &environment) {
             continue;
         }
 
-        if let Some(downloads) = &library.downloads {
-            if let Some(artifact) = &downloads.artifact {
-                // Determine the library path from the library name
-                // Format: group:artifact:version
-                // Example: org.lwjgl:lwjgl:3.2.2
-                let parts: Vec<&str> = library.name.split(':').collect();
-                if parts.len() < 3 {
-                    warn!("Invalid library name format: {}", library.name);
-                    continue;
-                }
-
-                let group = parts[0].replace('.', "/");
-                let artifact_id = parts[1];
-                let version = parts[2];
-
-                let library_path = minecraft_dir.join("libraries").join(format!("{}/{}/{}/{}-{}.jar", 
-                    group, artifact_id, version, artifact_id, version));
-
-                if library_path.exists() {
-                    classpath.push(if cfg!(windows) { ';' } else { ':' });
-                    classpath.push_str(&library_path.to_string_lossy());
-                }
+        if let Some(artifact) = get_library_artifact(&libraries_dir, library) {
+            if artifact.path.exists() && !entries.contains(&artifact.path) {
+                entries.push(artifact.path);
             }
         }
     }
 
-    Ok(classpath)
+    let separator = if cfg!(windows) { ";" } else { ":" };
+    Ok(entries.iter()
+        .map(|entry| entry.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(separator))
 }
@@ -10,7 +10,8 @@ use std::rc::Rc;
 use std::cell::RefCell;
 use serde::{Serialize, Deserialize};
 use crate::config::{Config, Profile};
-use crate::file_manager::{FileManager, DownloadProgress};
+use crate::file_manager::{FileManager, CancellationToken};
+use crate::progress::InstallProgress;
 use crate::games::minecraft::auth::AuthSession;
 
 /// Enum representing the UI type for a game plugin
@@ -54,7 +55,7 @@ pub trait GamePlugin: Send + Sync {
     fn delete_profile(&mut self, profile_id: &str) -> Result<()>;
 
     /// Launch the game with the specified profile
-    fn launch_game(&self, profile: &Profile, auth_session: &AuthSession, progress_callback: Box<dyn Fn(DownloadProgress) + Send + Sync + 'static>) -> Result<u32>;
+    fn launch_game(&self, profile: &Profile, auth_session: &AuthSession, progress_callback: Box<dyn Fn(InstallProgress) + Send + Sync + 'static>, cancel: &CancellationToken) -> Result<u32>;
 
     /// Check if a version is installed
     fn is_version_installed(&self, version_id: &str) -> bool;
@@ -66,7 +67,7 @@ pub trait GamePlugin: Send + Sync {
     fn get_modloader_versions(&mut self, mod_loader_type: &str, game_version: &str) -> Result<Vec<String>>;
 
     /// Install a specific version
-    fn install_version(&self, version_id: &str, progress_callback: Box<dyn Fn(DownloadProgress) + Send + Sync + 'static>) -> Result<()>;
+    fn install_version(&self, version_id: &str, progress_callback: Box<dyn Fn(InstallProgress) + Send + Sync + 'static>, cancel: &CancellationToken) -> Result<()>;
 
     /// Get the UI type for this game plugin
     fn get_ui_type(&self) -> GamePluginUIType {
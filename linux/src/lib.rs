@@ -3,4 +3,9 @@ pub mod ui;
 pub mod file_manager;
 pub mod config;
 pub mod utils;
-pub mod mods; 
\ No newline at end of file
+pub mod mods;
+pub mod cli;
+pub mod desktop;
+pub mod links;
+pub mod progress;
+pub mod network;
@@ -0,0 +1,149 @@
+// mosaic:// links that websites use to hand mods and modpacks to the launcher
+
+use anyhow::{Result, anyhow};
+use reqwest::Url;
+
+use crate::config::ModSource;
+
+/// URL scheme registered for the launcher
+pub const URL_SCHEME: &str = "mosaic";
+
+/// An action requested through a `mosaic://` link
+#[derive(Debug, Clone)]
+pub enum MosaicLink {
+    /// `mosaic://install?source=modrinth&project=<id>&version=<id>`
+    InstallMod {
+        source: ModSource,
+        project: String,
+        /// A version id or version number, the newest compatible version when missing
+        version: Option<String>,
+    },
+    /// `mosaic://modpack?url=<https link to a .mrpack>`
+    InstallModpack {
+        url: String,
+    },
+}
+
+/// Checks whether a command line argument is a `mosaic://` link
+pub fn is_mosaic_link(arg: &str) -> bool {
+    arg.split_once(':').is_some_and(|(scheme, _)| scheme.eq_ignore_ascii_case(URL_SCHEME))
+}
+
+/// Parses a `mosaic://` link
+pub fn parse_link(link: &str) -> Result<MosaicLink> {
+    let url = Url::parse(link.trim())
+        .map_err(|e| anyhow!("Invalid link {}: {}", link, e))?;
+
+    if url.scheme() != URL_SCHEME {
+        return Err(anyhow!("Not a {}:// link: {}", URL_SCHEME, link));
+    }
+
+    // mosaic://install?... puts the action in the host, mosaic:install?... in the path
+    let action = url.host_str()
+        .filter(|host| !host.is_empty())
+        .map(|host| host.to_string())
+        .unwrap_or_else(|| url.path().trim_matches('/').to_string());
+
+    let query_value = |name: &str| -> Option<String> {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    match action.to_lowercase().as_str() {
+        "install" => {
+            let source = match query_value("source").map(|s| s.to_lowercase()).as_deref() {
+                Some("modrinth") | None => ModSource::Modrinth,
+                Some("curseforge") => ModSource::CurseForge,
+                Some(other) => return Err(anyhow!("Unsupported mod source: {}", other)),
+            };
+            let project = query_value("project")
+                .ok_or_else(|| anyhow!("Install link is missing the project parameter"))?;
+
+            Ok(MosaicLink::InstallMod {
+                source,
+                project,
+                version: query_value("version"),
+            })
+        },
+        "modpack" => {
+            let url = query_value("url")
+                .ok_or_else(|| anyhow!("Modpack link is missing the url parameter"))?;
+
+            // Only fetch packs over an encrypted connection, never from local paths
+            let pack_url = Url::parse(&url)
+                .map_err(|e| anyhow!("Invalid modpack URL {}: {}", url, e))?;
+            if pack_url.scheme() != "https" {
+                return Err(anyhow!("Modpack URL must use https: {}", url));
+            }
+
+            Ok(MosaicLink::InstallModpack { url })
+        },
+        "" => Err(anyhow!("Link does not name an action: {}", link)),
+        other => Err(anyhow!("Unknown link action: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_links() {
+        assert!(is_mosaic_link("mosaic://install?project=sodium"));
+        assert!(is_mosaic_link("MOSAIC:install?project=sodium"));
+        assert!(!is_mosaic_link("mosaic"));
+        assert!(!is_mosaic_link("mosaicx://install"));
+        assert!(!is_mosaic_link("--profile"));
+        assert!(!is_mosaic_link("https://example.com"));
+        // Multi-byte characters where the scheme would end
+        assert!(!is_mosaic_link("aéééé"));
+        assert!(!is_mosaic_link("mosaï://install"));
+    }
+
+    #[test]
+    fn parses_install_links() {
+        match parse_link("mosaic://install?source=modrinth&project=AANobbMI&version=1.2.3").unwrap() {
+            MosaicLink::InstallMod { source, project, version } => {
+                assert!(matches!(source, ModSource::Modrinth));
+                assert_eq!(project, "AANobbMI");
+                assert_eq!(version.as_deref(), Some("1.2.3"));
+            },
+            other => panic!("Unexpected link {:?}", other),
+        }
+
+        match parse_link("mosaic:install?source=CurseForge&project=238222&version=").unwrap() {
+            MosaicLink::InstallMod { source, project, version } => {
+                assert!(matches!(source, ModSource::CurseForge));
+                assert_eq!(project, "238222");
+                assert_eq!(version, None);
+            },
+            other => panic!("Unexpected link {:?}", other),
+        }
+
+        assert!(parse_link("mosaic://install?source=modrinth").is_err());
+        assert!(parse_link("mosaic://install?source=github&project=x").is_err());
+    }
+
+    #[test]
+    fn parses_modpack_links() {
+        let url = "https://cdn.modrinth.com/data/pack.mrpack";
+        match parse_link(&format!("mosaic://modpack?url={}", url)).unwrap() {
+            MosaicLink::InstallModpack { url: pack_url } => assert_eq!(pack_url, url),
+            other => panic!("Unexpected link {:?}", other),
+        }
+
+        assert!(parse_link("mosaic://modpack?url=http://example.com/pack.mrpack").is_err());
+        assert!(parse_link("mosaic://modpack?url=file:///tmp/pack.mrpack").is_err());
+        assert!(parse_link("mosaic://modpack").is_err());
+    }
+
+    #[test]
+    fn rejects_other_links() {
+        assert!(parse_link("https://example.com/install?project=x").is_err());
+        assert!(parse_link("mosaic://").is_err());
+        assert!(parse_link("mosaic://uninstall?project=x").is_err());
+        assert!(parse_link("not a link").is_err());
+    }
+}
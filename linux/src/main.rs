@@ -1,6 +1,6 @@
 use anyhow::Result;
-use log::{info, error};
-use mosaic_launcher::config;
+use log::{info, warn, error};
+use mosaic_launcher::{cli, config, desktop};
 use mosaic_launcher::ui::MosaicApp;
 
 fn main() -> Result<()> {
@@ -8,14 +8,70 @@ fn main() -> Result<()> {
     env_logger::init();
     info!("Starting Mosaic Launcher");
 
+    // Parse the command line before anything touches GTK
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match cli::parse_args(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("Error: {}\n", e);
+            cli::eprint_usage();
+            std::process::exit(cli::EXIT_USAGE);
+        }
+    };
+
     // Load configuration
-    let config = config::load_config().unwrap_or_else(|e| {
-        error!("Failed to load configuration: {}", e);
-        config::default_config()
-    });
+    let (mut config, config_recovery) = match config::load_config_with_recovery() {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            error!("Failed to load configuration: {}", e);
+            eprintln!("Error: failed to load the configuration: {}", e);
+            std::process::exit(cli::EXIT_FAILURE);
+        }
+    };
+
+    // Links and desktop entry launches without a stored login need the GUI
+    let mut start_game = None;
+    let mut open_link = None;
+    let command = match command {
+        Some(cli::CliCommand::QuickLaunch { profile }) if !cli::has_stored_session() => {
+            info!("No stored login, opening the launcher for profile {}", profile);
+            config.selected_game = Some("minecraft".to_string());
+            config.last_used_profile = Some(profile);
+            start_game = config.selected_game.clone();
+            None
+        },
+        Some(cli::CliCommand::OpenLink { url }) => {
+            open_link = Some(url);
+            None
+        },
+        command => command,
+    };
+
+    // Run headless commands without starting the GUI
+    if let Some(command) = command {
+        if let Some(recovery) = &config_recovery {
+            match &recovery.backup_file {
+                Some(backup_file) => eprintln!("Warning: configuration was restored from {:?}", backup_file),
+                // Commands would run against default profiles, stop instead
+                None => {
+                    eprintln!("Error: the configuration could not be loaded: {}", recovery.error);
+                    if let Some(corrupt_file) = &recovery.corrupt_file {
+                        eprintln!("A copy of it was saved to {:?}", corrupt_file);
+                    }
+                    std::process::exit(cli::EXIT_FAILURE);
+                },
+            }
+        }
+        std::process::exit(cli::run(command, config));
+    }
+
+    // Let websites hand mosaic:// links to the launcher
+    if let Err(e) = desktop::register_url_handler() {
+        warn!("Failed to register the URL handler: {}", e);
+    }
 
     // Initialize the UI
-    let app = MosaicApp::new(&config);
+    let app = MosaicApp::new(&config, config_recovery, start_game, open_link);
 
     // Run the application (this starts the GTK main loop)
     let exit_code = app.run();
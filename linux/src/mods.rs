@@ -95,6 +95,28 @@ pub struct ModSearchResult {
     pub latest_game_version: String,
 }
 
+impl ModSearchResult {
+    /// Finds the version to install into a profile
+    ///
+    /// A requested version is matched by id or version number, otherwise the
+    /// newest version that supports the profile's Minecraft version and mod loader is used.
+    pub fn find_version(&self, requested: Option<&str>, profile: &Profile) -> Option<&ModVersionInfo> {
+        if let Some(requested) = requested {
+            return self.versions.iter()
+                .find(|v| v.id == requested || v.version_number == requested);
+        }
+
+        self.versions.iter().find(|v| {
+            let game_version_matches = v.game_versions.is_empty() || v.game_versions.contains(&profile.version);
+            let loader_matches = v.mod_loaders.is_empty() || match &profile.mod_loader {
+                Some(loader) if *loader != ModLoader::None => v.mod_loaders.contains(loader),
+                _ => false,
+            };
+            game_version_matches && loader_matches
+        })
+    }
+}
+
 /// Trait for mod managers
 pub trait ModManager {
     /// Search for mods
@@ -0,0 +1,148 @@
+// Shared HTTP client factory
+//
+// Every client the launcher uses is built here so the proxy settings from the
+// config apply to downloads, mod APIs and sign-in alike.
+
+use anyhow::{Result, anyhow};
+use reqwest::{NoProxy, Proxy};
+use std::io::Read;
+use std::time::{Duration, Instant};
+use log::info;
+
+use crate::config::{ProxyMode, ProxySettings};
+
+/// URL requested by the connection test
+const TEST_CONNECTION_URL: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+/// How long the connection test waits for a response
+const TEST_CONNECTION_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Builds the proxy for `ProxyMode::Manual`
+fn get_manual_proxy(settings: &ProxySettings) -> Result<Proxy> {
+    let host = settings.host.trim();
+    if host.is_empty() {
+        return Err(anyhow!("No proxy host configured"));
+    }
+    if settings.port == 0 {
+        return Err(anyhow!("No proxy port configured"));
+    }
+
+    let proxy_url = format!("{}://{}:{}", settings.kind.scheme(), host, settings.port);
+    let mut proxy = Proxy::all(&proxy_url)
+        .map_err(|e| anyhow!("Invalid proxy {}: {}", proxy_url, e))?;
+
+    if let Some(username) = settings.username.as_deref().filter(|username| !username.is_empty()) {
+        proxy = proxy.basic_auth(username, settings.password.as_deref().unwrap_or(""));
+    }
+
+    let no_proxy = settings.no_proxy.iter()
+        .map(|host| host.trim())
+        .filter(|host| !host.is_empty())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    Ok(proxy.no_proxy(NoProxy::from_string(&no_proxy)))
+}
+
+/// Gets a client builder with the proxy settings applied
+///
+/// Callers add their own headers or policies before building.
+pub fn client_builder(settings: &ProxySettings) -> Result<reqwest::ClientBuilder> {
+    let builder = reqwest::Client::builder();
+
+    Ok(match settings.mode {
+        // reqwest reads HTTP_PROXY, HTTPS_PROXY and NO_PROXY by default
+        ProxyMode::System => builder,
+        ProxyMode::None => builder.no_proxy(),
+        ProxyMode::Manual => builder.proxy(get_manual_proxy(settings)?),
+    })
+}
+
+/// Gets a blocking client builder with the proxy settings applied
+pub fn blocking_client_builder(settings: &ProxySettings) -> Result<reqwest::blocking::ClientBuilder> {
+    let builder = reqwest::blocking::Client::builder();
+
+    Ok(match settings.mode {
+        ProxyMode::System => builder,
+        ProxyMode::None => builder.no_proxy(),
+        ProxyMode::Manual => builder.proxy(get_manual_proxy(settings)?),
+    })
+}
+
+/// Builds an async client with the proxy settings applied
+///
+/// Fails if the proxy settings are invalid. Callers keep the error and fail
+/// their requests with it: falling back to a direct client would bypass the
+/// proxy the user asked for.
+pub fn build_client(settings: &ProxySettings) -> Result<reqwest::Client> {
+    Ok(client_builder(settings)?.build()?)
+}
+
+/// Builds a blocking client with the proxy settings applied, see `build_client`
+pub fn build_blocking_client(settings: &ProxySettings) -> Result<reqwest::blocking::Client> {
+    Ok(blocking_client_builder(settings)?.build()?)
+}
+
+/// Builds a blocking client for OAuth requests, which must not follow redirects
+pub fn build_oauth_client(settings: &ProxySettings) -> Result<reqwest::blocking::Client> {
+    Ok(blocking_client_builder(settings)?
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?)
+}
+
+/// Checks that clients can be built with the proxy settings, without connecting
+pub fn check_proxy_settings(settings: &ProxySettings) -> Result<()> {
+    build_client(settings)?;
+    Ok(())
+}
+
+/// Gets the client a manager was built with, or the proxy error that keeps its requests from going out
+pub fn get_client<C>(client: &std::result::Result<C, String>) -> Result<&C> {
+    client.as_ref().map_err(|e| anyhow!("Not connecting, the proxy settings are invalid: {}", e))
+}
+
+/// Sends an oauth2 request through `client`, in place of `oauth2::reqwest::http_client`
+pub fn send_oauth_request(
+    client: &reqwest::blocking::Client,
+    request: oauth2::HttpRequest,
+) -> std::result::Result<oauth2::HttpResponse, oauth2::reqwest::Error<reqwest::Error>> {
+    let mut request_builder = client
+        .request(request.method, request.url.as_str())
+        .body(request.body);
+    for (name, value) in &request.headers {
+        request_builder = request_builder.header(name.as_str(), value.as_bytes());
+    }
+
+    let mut response = client
+        .execute(request_builder.build().map_err(oauth2::reqwest::Error::Reqwest)?)
+        .map_err(oauth2::reqwest::Error::Reqwest)?;
+
+    let mut body = Vec::new();
+    response.read_to_end(&mut body).map_err(oauth2::reqwest::Error::Io)?;
+
+    Ok(oauth2::HttpResponse {
+        status_code: response.status(),
+        headers: response.headers().to_owned(),
+        body,
+    })
+}
+
+/// Checks that the version manifest can be reached with the given proxy settings
+///
+/// Blocks until the server answers; returns how long the request took.
+pub fn test_connection(settings: &ProxySettings) -> Result<Duration> {
+    let client = blocking_client_builder(settings)?
+        .timeout(TEST_CONNECTION_TIMEOUT)
+        .build()?;
+
+    let start = Instant::now();
+    let response = client.head(TEST_CONNECTION_URL).send()?;
+    let elapsed = start.elapsed();
+
+    if !response.status().is_success() {
+        return Err(anyhow!("Server responded with {}", response.status()));
+    }
+
+    info!("Connection test succeeded in {} ms", elapsed.as_millis());
+    Ok(elapsed)
+}
@@ -0,0 +1,292 @@
+// Install-level progress shared by game plugins, the UI and the CLI
+//
+// Downloads report progress per file; the tracker folds those reports into
+// totals for the whole install so a progress bar moves once, not per file.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::file_manager::DownloadProgress;
+use crate::utils::{format_file_size, format_duration};
+
+/// Minimum time between two progress updates, phase changes are always sent
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Window the transfer rate is averaged over
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// A step of installing a version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallPhase {
+    Manifest,
+    Libraries,
+    Natives,
+    Assets,
+    Loader,
+    Verifying,
+    Repairing,
+}
+
+impl InstallPhase {
+    /// Gets a short description for progress displays
+    pub fn label(&self) -> &'static str {
+        match self {
+            InstallPhase::Manifest => "Fetching version information",
+            InstallPhase::Libraries => "Downloading libraries",
+            InstallPhase::Natives => "Extracting natives",
+            InstallPhase::Assets => "Downloading assets",
+            InstallPhase::Loader => "Installing mod loader",
+            InstallPhase::Verifying => "Verifying files",
+            InstallPhase::Repairing => "Repairing files",
+        }
+    }
+}
+
+/// Progress of a whole install
+#[derive(Debug, Clone)]
+pub struct InstallProgress {
+    pub phase: InstallPhase,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// Averaged over the last few seconds of transfers
+    pub bytes_per_second: f64,
+    /// None until there is a rate to estimate from
+    pub eta: Option<Duration>,
+    /// The file that last reported progress
+    pub current_file: Option<String>,
+}
+
+impl InstallProgress {
+    /// Gets the completed fraction, by bytes when sizes are known and by files otherwise
+    pub fn fraction(&self) -> f64 {
+        let fraction = if self.bytes_total > 0 {
+            self.bytes_done as f64 / self.bytes_total as f64
+        } else if self.files_total > 0 {
+            self.files_done as f64 / self.files_total as f64
+        } else {
+            0.0
+        };
+
+        fraction.clamp(0.0, 1.0)
+    }
+
+    /// Describes the progress in one line, e.g. for a progress bar or a terminal
+    pub fn status_text(&self) -> String {
+        let mut text = self.phase.label().to_string();
+
+        if self.files_total > 0 {
+            text.push_str(&format!(" · {} / {} files", self.files_done, self.files_total));
+        }
+        if self.bytes_total > 0 {
+            text.push_str(&format!(" · {} / {}",
+                format_file_size(self.bytes_done), format_file_size(self.bytes_total)));
+        }
+        if self.bytes_per_second >= 1.0 {
+            text.push_str(&format!(" · {}/s", format_file_size(self.bytes_per_second as u64)));
+        }
+        if let Some(eta) = self.eta {
+            text.push_str(&format!(" · {} left", format_duration(eta.as_millis() as u64)));
+        }
+
+        text
+    }
+}
+
+/// Bookkeeping for one file the tracker knows about
+struct TrackedFile {
+    /// Size from the metadata, or from the server once the download starts
+    size: Option<u64>,
+    downloaded: u64,
+    done: bool,
+}
+
+/// Mutable state behind a `ProgressTracker`
+struct TrackerState {
+    phase: InstallPhase,
+    files: HashMap<String, TrackedFile>,
+    files_done: usize,
+    bytes_total: u64,
+    /// Bytes actually received, files that were already on disk don't count
+    bytes_transferred: u64,
+    rate_samples: VecDeque<(Instant, u64)>,
+    last_update: Option<Instant>,
+    current_file: Option<String>,
+}
+
+impl TrackerState {
+    fn snapshot(&mut self) -> InstallProgress {
+        let now = Instant::now();
+        self.rate_samples.push_back((now, self.bytes_transferred));
+        while self.rate_samples.len() > 2
+            && now.duration_since(self.rate_samples[0].0) > RATE_WINDOW
+        {
+            self.rate_samples.pop_front();
+        }
+
+        let bytes_per_second = match (self.rate_samples.front(), self.rate_samples.back()) {
+            (Some((start, start_bytes)), Some((end, end_bytes))) => {
+                let elapsed = end.duration_since(*start).as_secs_f64();
+                if elapsed >= 0.5 {
+                    (end_bytes - start_bytes) as f64 / elapsed
+                } else {
+                    0.0
+                }
+            },
+            _ => 0.0,
+        };
+
+        let bytes_done: u64 = self.files.values()
+            .map(|file| if file.done { file.size.unwrap_or(file.downloaded) } else { file.downloaded })
+            .sum();
+
+        let eta = if bytes_per_second >= 1.0 && self.bytes_total > bytes_done {
+            Some(Duration::from_secs_f64((self.bytes_total - bytes_done) as f64 / bytes_per_second))
+        } else {
+            None
+        };
+
+        InstallProgress {
+            phase: self.phase,
+            files_done: self.files_done,
+            files_total: self.files.len(),
+            bytes_done,
+            bytes_total: self.bytes_total,
+            bytes_per_second,
+            eta,
+            current_file: self.current_file.clone(),
+        }
+    }
+
+    /// Returns a snapshot if one is due, or always when `force` is set
+    fn take_update(&mut self, force: bool) -> Option<InstallProgress> {
+        let now = Instant::now();
+        let due = self.last_update.map_or(true, |last| now.duration_since(last) >= UPDATE_INTERVAL);
+        if !force && !due {
+            return None;
+        }
+
+        self.last_update = Some(now);
+        Some(self.snapshot())
+    }
+}
+
+/// Folds per-file download reports into `InstallProgress` updates
+///
+/// Clones share their state, so one tracker can be handed to every download of an install.
+#[derive(Clone)]
+pub struct ProgressTracker {
+    state: Arc<Mutex<TrackerState>>,
+    callback: Arc<dyn Fn(InstallProgress) + Send + Sync>,
+}
+
+impl ProgressTracker {
+    /// Creates a tracker that sends updates to `callback`, at most every 100 ms
+    pub fn new(callback: impl Fn(InstallProgress) + Send + Sync + 'static) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(TrackerState {
+                phase: InstallPhase::Manifest,
+                files: HashMap::new(),
+                files_done: 0,
+                bytes_total: 0,
+                bytes_transferred: 0,
+                rate_samples: VecDeque::new(),
+                last_update: None,
+                current_file: None,
+            })),
+            callback: Arc::new(callback),
+        }
+    }
+
+    /// Creates a tracker nobody listens to
+    pub fn silent() -> Self {
+        Self::new(|_| {})
+    }
+
+    /// Moves on to the next phase of the install
+    pub fn set_phase(&self, phase: InstallPhase) {
+        self.update(true, |state| state.phase = phase);
+    }
+
+    /// Moves on to a phase that counts its files from zero, e.g. downloads after a verification
+    pub fn restart(&self, phase: InstallPhase) {
+        self.update(true, |state| {
+            state.phase = phase;
+            state.files.clear();
+            state.files_done = 0;
+            state.bytes_total = 0;
+            state.bytes_transferred = 0;
+            state.rate_samples.clear();
+            state.current_file = None;
+        });
+    }
+
+    /// Adds a file to the totals, files that are already known are ignored
+    pub fn add_file(&self, url: &str, size: Option<u64>) {
+        self.update(false, |state| {
+            if state.files.contains_key(url) {
+                return;
+            }
+            state.bytes_total += size.unwrap_or(0);
+            state.files.insert(url.to_string(), TrackedFile { size, downloaded: 0, done: false });
+        });
+    }
+
+    /// Records a progress report for one file
+    pub fn update_file(&self, progress: &DownloadProgress) {
+        self.update(false, |state| {
+            let file = state.files.entry(progress.url.clone())
+                .or_insert(TrackedFile { size: None, downloaded: 0, done: false });
+
+            // Files without a known size count towards the total once the server names one
+            if file.size.is_none() {
+                if let Some(total) = progress.total_size {
+                    file.size = Some(total);
+                    state.bytes_total += total;
+                }
+            }
+
+            // Bytes resumed from an earlier attempt were already on disk, don't count them as received
+            file.downloaded = file.downloaded.max(progress.resumed_from);
+            let received = progress.downloaded_size.saturating_sub(file.downloaded);
+            file.downloaded = progress.downloaded_size;
+            state.bytes_transferred += received;
+            state.current_file = Some(progress.file_name.clone());
+        });
+    }
+
+    /// Marks a file as finished, whether it was downloaded, already present or failed
+    pub fn finish_file(&self, url: &str) {
+        self.update(false, |state| {
+            let file = state.files.entry(url.to_string())
+                .or_insert(TrackedFile { size: None, downloaded: 0, done: false });
+            if !file.done {
+                file.done = true;
+                state.files_done += 1;
+            }
+        });
+    }
+
+    /// Gets a per-file callback for `FileManager::download_file`
+    pub fn download_callback(&self) -> impl Fn(DownloadProgress) + Send + Sync + 'static + Clone {
+        let tracker = self.clone();
+        move |progress: DownloadProgress| tracker.update_file(&progress)
+    }
+
+    /// Applies a change and sends an update if one is due
+    fn update(&self, force: bool, change: impl FnOnce(&mut TrackerState)) {
+        let update = {
+            let mut state = self.state.lock().unwrap();
+            change(&mut state);
+            let all_done = !state.files.is_empty() && state.files_done == state.files.len();
+            state.take_update(force || all_done)
+        };
+
+        // Call back outside the lock so the callback can't deadlock the tracker
+        if let Some(progress) = update {
+            (self.callback)(progress);
+        }
+    }
+}
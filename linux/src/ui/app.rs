@@ -8,13 +8,14 @@ use std::sync::{Arc, Mutex};
 use log::{info, warn, error, debug};
 
 use crate::games::minecraft::auth::{AuthManager, AuthSession};
-use crate::config::{Config, ModLoader, GameType, save_config};
+use crate::config::{Config, ConfigRecovery, ModLoader, GameType, save_config};
 use crate::games::{GamePluginManager};
 use crate::games::minecraft::{MinecraftPluginFactory};
 use crate::file_manager::{FileManager};
 
 use super::main_view::build_main_view;
 use super::game_selector::build_game_selector;
+use super::links::open_link;
 
 pub struct MosaicApp {
     app: adw::Application,
@@ -23,22 +24,31 @@ pub struct MosaicApp {
     file_manager: Rc<FileManager>,
     auth_session: Arc<Mutex<Option<AuthSession>>>,
     game_plugin_manager: Arc<Mutex<GamePluginManager>>,
+    config_recovery: Option<ConfigRecovery>,
+    start_game: Option<String>,
+    open_link: Option<String>,
 }
 
 impl MosaicApp {
-    pub fn new(config: &Config) -> Self {
-        // Create the application
+    pub fn new(
+        config: &Config,
+        config_recovery: Option<ConfigRecovery>,
+        start_game: Option<String>,
+        open_link: Option<String>,
+    ) -> Self {
+        // Create the application, links are delivered through the open signal
         info!("Creating application");
-        let app = adw::Application::new(Some("xyz.aflkonstukt.launcher"), Default::default());
+        let app = adw::Application::new(Some("xyz.aflkonstukt.launcher"), gtk::gio::ApplicationFlags::HANDLES_OPEN);
 
         // Create shared resources
         let config = Rc::new(RefCell::new(config.clone()));
-        let auth_manager = Rc::new(AuthManager::new());
+        let auth_manager = Rc::new(AuthManager::new(&config.borrow().proxy));
 
         // Create a single FileManager instance and wrap it in an Rc
         let file_manager = Rc::new(FileManager::new());
 
-        let auth_session = Arc::new(Mutex::new(None));
+        // Reuse the stored login so the login view can be skipped
+        let auth_session = Arc::new(Mutex::new(auth_manager.load_session()));
 
         // Create the GamePluginManager
         let game_plugin_manager = Arc::new(Mutex::new(GamePluginManager::new(config.clone(), file_manager.clone())));
@@ -60,6 +70,9 @@ impl MosaicApp {
             file_manager,
             auth_session,
             game_plugin_manager,
+            config_recovery,
+            start_game,
+            open_link,
         }
     }
 
@@ -70,6 +83,8 @@ impl MosaicApp {
         let file_manager = self.file_manager.clone();
         let auth_session = self.auth_session.clone();
         let game_plugin_manager = self.game_plugin_manager.clone();
+        let config_recovery = self.config_recovery.clone();
+        let start_game = self.start_game.clone();
 
         info!("Connecting activate signal");
         self.app.connect_activate(move |app| {
@@ -81,6 +96,8 @@ impl MosaicApp {
                 file_manager.clone(),
                 auth_session.clone(),
                 game_plugin_manager.clone(),
+                config_recovery.clone(),
+                start_game.clone(),
             );
 
             // Show the window
@@ -88,8 +105,51 @@ impl MosaicApp {
             window.present();
         });
 
+        // Opening a link reuses the running window or builds a new one
+        let config = self.config.clone();
+        let auth_manager = self.auth_manager.clone();
+        let file_manager = self.file_manager.clone();
+        let auth_session = self.auth_session.clone();
+        let game_plugin_manager = self.game_plugin_manager.clone();
+        let config_recovery = self.config_recovery.clone();
+
+        self.app.connect_open(move |app, files, _hint| {
+            let window = match app.active_window().and_then(|w| w.downcast::<adw::ApplicationWindow>().ok()) {
+                Some(window) => window,
+                None => build_main_window(
+                    app,
+                    config.clone(),
+                    auth_manager.clone(),
+                    file_manager.clone(),
+                    auth_session.clone(),
+                    game_plugin_manager.clone(),
+                    config_recovery.clone(),
+                    None,
+                ),
+            };
+            window.present();
+
+            let toast_overlay = match window.content().and_then(|c| c.downcast::<adw::ToastOverlay>().ok()) {
+                Some(toast_overlay) => toast_overlay,
+                None => {
+                    error!("Main window has no toast overlay, cannot open links");
+                    return;
+                }
+            };
+
+            for file in files {
+                open_link(window.upcast_ref::<gtk::Window>(), &toast_overlay, config.clone(), &file.uri());
+            }
+        });
+
+        // GTK only gets to see the link, the rest of the command line is ours
+        let mut args = vec![std::env::args().next().unwrap_or_else(|| "mosaic-launcher".to_string())];
+        if let Some(link) = &self.open_link {
+            args.push(link.clone());
+        }
+
         // Run the application
-        self.app.run().into()
+        self.app.run_with_args(&args).into()
     }
 }
 
@@ -210,6 +270,8 @@ fn build_main_window(
     file_manager: Rc<FileManager>,
     auth_session: Arc<Mutex<Option<AuthSession>>>,
     game_plugin_manager: Arc<Mutex<GamePluginManager>>,
+    config_recovery: Option<ConfigRecovery>,
+    start_game: Option<String>,
 ) -> adw::ApplicationWindow {
     // Create the main window
     info!("Building main application window");
@@ -261,6 +323,27 @@ fn build_main_window(
     // Start with the game selector view
     stack.set_visible_child_name("game_selector");
 
+    // Open a game directly, e.g. when started from a profile's desktop entry
+    if let Some(game_id) = start_game {
+        info!("Opening game {} on start", game_id);
+        let manager = game_plugin_manager.lock().unwrap();
+        if let Some(plugin) = manager.get_plugin(&game_id) {
+            if let Err(e) = plugin.handle_game_selection(
+                window.upcast_ref::<gtk::Window>(),
+                &toast_overlay,
+                &stack,
+                auth_session.clone(),
+                config.clone(),
+            ) {
+                error!("Failed to open game {}: {}", game_id, e);
+                let toast = adw::Toast::new(&format!("Failed to open game: {}", e));
+                toast_overlay.add_toast(toast);
+            }
+        } else {
+            warn!("Game plugin {} not found", game_id);
+        }
+    }
+
     info!("Stack built");
 
     // Add the stack to the toast overlay
@@ -269,6 +352,26 @@ fn build_main_window(
     // Add the toast overlay to the window
     window.set_content(Some(&toast_overlay));
 
+    // Let the user know if the config had to be restored from a backup or could not be loaded
+    if let Some(recovery) = config_recovery {
+        let message = match &recovery.backup_file {
+            Some(backup_file) => {
+                warn!("Configuration was restored from {:?}: {}", backup_file, recovery.error);
+                let backup_name = backup_file.file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| backup_file.to_string_lossy().to_string());
+                format!("Your configuration was unreadable and has been restored from {}", backup_name)
+            },
+            None => {
+                warn!("Configuration could not be loaded, running read-only: {}", recovery.error);
+                "Your configuration could not be loaded. Default settings are used and no changes are saved until it is fixed".to_string()
+            },
+        };
+        let toast = adw::Toast::new(&message);
+        toast.set_timeout(0);
+        toast_overlay.add_toast(toast);
+    }
+
     // Show the window
     info!("Main application window built");
 
@@ -9,7 +9,7 @@ use std::cell::RefCell;
 use std::sync::{Arc, Mutex};
 use log::{info, warn, error, debug};
 
-use crate::config::{Config, Game, GameType, save_config};
+use crate::config::{Config, Game, GameType, LaunchSettings, save_config};
 
 pub fn build_game_selector(
     window: &adw::ApplicationWindow,
@@ -293,6 +293,7 @@ pub fn build_game_selector(
                     game_directory: std::path::PathBuf::from(directory),
                     profiles: vec![],
                     game_type: GameType::Custom,
+                    launch: LaunchSettings::default(),
                 };
 
                 // Add the game to the config
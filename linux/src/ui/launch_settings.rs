@@ -0,0 +1,176 @@
+// Rows that edit one layer of launch settings, shared by the settings view and the profile dialog
+
+use gtk4 as gtk;
+use gtk::prelude::*;
+use libadwaita as adw;
+use adw::prelude::*;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::config::{LaunchSettings, DEFAULT_MAX_MEMORY};
+use crate::utils::{join_shell_words, split_shell_words};
+
+/// Rows that edit the launch settings of one layer
+#[derive(Clone)]
+pub(super) struct LaunchOverrideRows {
+    memory_row: adw::ExpanderRow,
+    min_memory_entry: gtk::SpinButton,
+    max_memory_entry: gtk::SpinButton,
+    java_path_entry: gtk::Entry,
+    jvm_args_entry: gtk::Entry,
+    environment_entry: gtk::Entry,
+}
+
+impl LaunchOverrideRows {
+    /// Reads the settings entered in the rows; empty fields inherit
+    ///
+    /// Fails on arguments with unbalanced quotes and on environment
+    /// variables that aren't `KEY=VALUE`.
+    pub(super) fn read(&self) -> Result<LaunchSettings> {
+        let (min_memory, max_memory) = if self.memory_row.enables_expansion() {
+            (Some(self.min_memory_entry.value() as u32), Some(self.max_memory_entry.value() as u32))
+        } else {
+            (None, None)
+        };
+
+        let java_path = self.java_path_entry.text().trim().to_string();
+
+        let jvm_arguments = split_shell_words(&self.jvm_args_entry.text())
+            .map_err(|e| anyhow!("Invalid JVM arguments: {}", e))?;
+
+        let mut environment = HashMap::new();
+        let pairs = split_shell_words(&self.environment_entry.text())
+            .map_err(|e| anyhow!("Invalid environment variables: {}", e))?;
+        for pair in pairs {
+            match pair.split_once('=') {
+                Some((key, value)) if !key.is_empty() => {
+                    environment.insert(key.to_string(), value.to_string());
+                },
+                _ => return Err(anyhow!("Invalid environment variable '{}', expected KEY=VALUE", pair)),
+            }
+        }
+
+        Ok(LaunchSettings {
+            jvm_arguments: if jvm_arguments.is_empty() { None } else { Some(jvm_arguments) },
+            min_memory,
+            max_memory,
+            java_path: if java_path.is_empty() { None } else { Some(PathBuf::from(java_path)) },
+            environment,
+        })
+    }
+
+    /// Calls `callback` whenever any of the rows is edited
+    pub(super) fn connect_changed<F: Fn() + Clone + 'static>(&self, callback: F) {
+        let f = callback.clone();
+        self.memory_row.connect_enable_expansion_notify(move |_| f());
+        let f = callback.clone();
+        self.min_memory_entry.connect_value_changed(move |_| f());
+        let f = callback.clone();
+        self.max_memory_entry.connect_value_changed(move |_| f());
+        let f = callback.clone();
+        self.java_path_entry.connect_changed(move |_| f());
+        let f = callback.clone();
+        self.jvm_args_entry.connect_changed(move |_| f());
+        self.environment_entry.connect_changed(move |_| callback());
+    }
+}
+
+/// Formats environment variables as sorted, quoted `KEY=VALUE` words
+fn format_environment(environment: &HashMap<String, String>) -> String {
+    let mut pairs: Vec<String> = environment.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    pairs.sort();
+    join_shell_words(&pairs)
+}
+
+/// Builds the launch setting rows, showing inherited values as placeholders
+///
+/// `memory_title` names the switch that turns the memory setting of this layer on.
+pub(super) fn build_launch_override_rows(
+    group: &adw::PreferencesGroup,
+    memory_title: &str,
+    inherited: &LaunchSettings,
+    current: &LaunchSettings,
+) -> LaunchOverrideRows {
+    // Memory override, collapsed while the layer inherits it
+    let memory_row = adw::ExpanderRow::new();
+    memory_row.set_title(memory_title);
+    memory_row.set_show_enable_switch(true);
+    memory_row.set_enable_expansion(current.min_memory.is_some() || current.max_memory.is_some());
+
+    let inherited_max = inherited.max_memory.unwrap_or(DEFAULT_MAX_MEMORY);
+    let inherited_min = inherited.min_memory.unwrap_or(inherited_max);
+    memory_row.set_subtitle(&format!("Inherited: {}MB - {}MB", inherited_min, inherited_max));
+
+    let min_memory_row = adw::ActionRow::new();
+    min_memory_row.set_title("Minimum RAM (MB)");
+    let min_memory_entry = gtk::SpinButton::with_range(256.0, 32768.0, 256.0);
+    min_memory_entry.set_valign(gtk::Align::Center);
+    min_memory_entry.set_value(current.min_memory.unwrap_or(inherited_min) as f64);
+    min_memory_row.add_suffix(&min_memory_entry);
+    memory_row.add_row(&min_memory_row);
+
+    let max_memory_row = adw::ActionRow::new();
+    max_memory_row.set_title("Maximum RAM (MB)");
+    let max_memory_entry = gtk::SpinButton::with_range(512.0, 32768.0, 512.0);
+    max_memory_entry.set_valign(gtk::Align::Center);
+    max_memory_entry.set_value(current.max_memory.unwrap_or(inherited_max) as f64);
+    max_memory_row.add_suffix(&max_memory_entry);
+    memory_row.add_row(&max_memory_row);
+
+    group.add(&memory_row);
+
+    // Java executable
+    let java_path_row = adw::ActionRow::new();
+    java_path_row.set_title("Java Executable");
+    let java_path_entry = gtk::Entry::new();
+    if let Some(path) = &current.java_path {
+        java_path_entry.set_text(&path.to_string_lossy());
+    }
+    let java_placeholder = inherited.java_path.as_ref()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Auto-detect".to_string());
+    java_path_entry.set_placeholder_text(Some(&java_placeholder));
+    java_path_entry.set_hexpand(true);
+    java_path_entry.set_valign(gtk::Align::Center);
+    java_path_row.add_suffix(&java_path_entry);
+    group.add(&java_path_row);
+
+    // JVM arguments
+    let jvm_args_row = adw::ActionRow::new();
+    jvm_args_row.set_title("JVM Arguments");
+    let jvm_args_entry = gtk::Entry::new();
+    if let Some(args) = &current.jvm_arguments {
+        jvm_args_entry.set_text(&join_shell_words(args));
+    }
+    let args_placeholder = inherited.jvm_arguments.as_ref()
+        .map(|a| join_shell_words(a))
+        .unwrap_or_else(|| "Default".to_string());
+    jvm_args_entry.set_placeholder_text(Some(&args_placeholder));
+    jvm_args_entry.set_hexpand(true);
+    jvm_args_entry.set_valign(gtk::Align::Center);
+    jvm_args_row.add_suffix(&jvm_args_entry);
+    group.add(&jvm_args_row);
+
+    // Environment variables, merged on top of the inherited ones
+    let environment_row = adw::ActionRow::new();
+    environment_row.set_title("Environment Variables");
+    environment_row.set_subtitle("KEY=VALUE pairs, quote values with spaces");
+    let environment_entry = gtk::Entry::new();
+    environment_entry.set_text(&format_environment(&current.environment));
+    let inherited_env = format_environment(&inherited.environment);
+    environment_entry.set_placeholder_text(Some(if inherited_env.is_empty() { "None" } else { inherited_env.as_str() }));
+    environment_entry.set_hexpand(true);
+    environment_entry.set_valign(gtk::Align::Center);
+    environment_row.add_suffix(&environment_entry);
+    group.add(&environment_row);
+
+    LaunchOverrideRows {
+        memory_row,
+        min_memory_entry,
+        max_memory_entry,
+        java_path_entry,
+        jvm_args_entry,
+        environment_entry,
+    }
+}
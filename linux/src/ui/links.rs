@@ -0,0 +1,468 @@
+// Confirmation and installation of mosaic:// links
+
+use gtk4 as gtk;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use libadwaita as adw;
+use anyhow::Result;
+use std::rc::Rc;
+use std::cell::RefCell;
+use log::{info, warn, error};
+
+use crate::config::{Config, Game, GameType, ModLoader, ModSource, Profile, save_config};
+use crate::file_manager::FileManager;
+use crate::games::minecraft::modpacks::{self, Modpack};
+use crate::games::minecraft::ui::mods::MinecraftModManager;
+use crate::links::{parse_link, MosaicLink};
+use crate::mods::{ModSearchResult, ModVersionInfo};
+
+/// Handles a `mosaic://` link by asking the user to confirm the install
+pub fn open_link(
+    window: &gtk::Window,
+    toast_overlay: &adw::ToastOverlay,
+    config: Rc<RefCell<Config>>,
+    link: &str,
+) {
+    info!("Opening link {}", link);
+
+    let link = match parse_link(link) {
+        Ok(link) => link,
+        Err(e) => {
+            error!("Failed to parse link: {}", e);
+            let toast = adw::Toast::new(&format!("Cannot open link: {}", e));
+            toast_overlay.add_toast(toast);
+            return;
+        }
+    };
+
+    // Links always target the Minecraft game
+    let game = config.borrow().games.iter()
+        .find(|g| g.game_type == GameType::Minecraft)
+        .cloned();
+    let game = match game {
+        Some(game) => game,
+        None => {
+            let toast = adw::Toast::new("Add Minecraft in the game selector before installing mods");
+            toast_overlay.add_toast(toast);
+            return;
+        }
+    };
+
+    match link {
+        MosaicLink::InstallMod { source, project, version } => {
+            open_mod_link(window, toast_overlay, config, game, source, project, version)
+        },
+        MosaicLink::InstallModpack { url } => {
+            open_modpack_link(window, toast_overlay, config, game, url)
+        },
+    }
+}
+
+/// Creates a mod manager that works on the given game
+fn create_mod_manager(config: &Config, game: &Game) -> MinecraftModManager {
+    let mut config = config.clone();
+    config.selected_game = Some(game.id.clone());
+    MinecraftModManager::new(config, Rc::new(FileManager::new()))
+}
+
+/// Gets a display name for a mod loader
+fn get_mod_loader_name(mod_loader: &ModLoader) -> &'static str {
+    match mod_loader {
+        ModLoader::None => "Vanilla",
+        ModLoader::Forge => "Forge",
+        ModLoader::Fabric => "Fabric",
+        ModLoader::Quilt => "Quilt",
+        ModLoader::NeoForge => "NeoForge",
+    }
+}
+
+/// Looks up the linked mod, then asks which profile to install it into
+fn open_mod_link(
+    window: &gtk::Window,
+    toast_overlay: &adw::ToastOverlay,
+    config: Rc<RefCell<Config>>,
+    game: Game,
+    source: ModSource,
+    project: String,
+    requested_version: Option<String>,
+) {
+    if game.profiles.is_empty() {
+        let toast = adw::Toast::new("Create a profile before installing mods");
+        toast_overlay.add_toast(toast);
+        return;
+    }
+
+    let toast = adw::Toast::new("Looking up mod...");
+    toast_overlay.add_toast(toast);
+
+    let window = window.clone();
+    let toast_overlay = toast_overlay.clone();
+    let config_snapshot = config.borrow().clone();
+    let game_thread = game.clone();
+
+    glib::spawn_future_local(async move {
+        // Fetch the details off the main thread so the window stays responsive
+        let result = gio::spawn_blocking(move || -> Result<ModSearchResult> {
+            let rt = tokio::runtime::Runtime::new()?;
+            let mod_manager = create_mod_manager(&config_snapshot, &game_thread);
+            rt.block_on(mod_manager.get_mod_details(&project, &source))
+        }).await;
+
+        match result {
+            Ok(Ok(details)) => show_mod_dialog(&window, &toast_overlay, config, game, details, requested_version),
+            Ok(Err(e)) => {
+                error!("Failed to get mod details: {}", e);
+                let toast = adw::Toast::new(&format!("Failed to get mod details: {}", e));
+                toast_overlay.add_toast(toast);
+            },
+            Err(_) => {
+                let toast = adw::Toast::new("Failed to get mod details");
+                toast_overlay.add_toast(toast);
+            }
+        }
+    });
+}
+
+/// Shows the install confirmation for a mod with a choice of target profile
+fn show_mod_dialog(
+    window: &gtk::Window,
+    toast_overlay: &adw::ToastOverlay,
+    config: Rc<RefCell<Config>>,
+    game: Game,
+    details: ModSearchResult,
+    requested_version: Option<String>,
+) {
+    let dialog = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::None,
+        &format!("Install {}?", details.name),
+    );
+    dialog.set_title(Some("Install Mod"));
+
+    dialog.add_button("Cancel", gtk::ResponseType::Cancel);
+    dialog.add_button("Install", gtk::ResponseType::Accept);
+    dialog.set_default_response(gtk::ResponseType::Cancel);
+
+    // Let the user pick the profile, starting with the last used one
+    let profile_names: Vec<&str> = game.profiles.iter().map(|p| p.name.as_str()).collect();
+    let profile_dropdown = gtk::DropDown::from_strings(&profile_names);
+    let selected = config.borrow().last_used_profile.as_ref()
+        .and_then(|id| game.profiles.iter().position(|p| &p.id == id))
+        .unwrap_or(0);
+    profile_dropdown.set_selected(selected as u32);
+
+    if let Ok(message_area) = dialog.message_area().downcast::<gtk::Box>() {
+        message_area.append(&profile_dropdown);
+    }
+
+    let game = Rc::new(game);
+    let details = Rc::new(details);
+
+    // Name the target profile and the version it will get
+    let update_message = {
+        let dialog = dialog.clone();
+        let game = game.clone();
+        let details = details.clone();
+        let requested_version = requested_version.clone();
+
+        move |index: u32| {
+            let profile = match game.profiles.get(index as usize) {
+                Some(profile) => profile,
+                None => return,
+            };
+
+            match details.find_version(requested_version.as_deref(), profile) {
+                Some(version) => {
+                    dialog.set_secondary_text(Some(&format!(
+                        "{} {} by {} will be installed into the profile '{}' (Minecraft {}).",
+                        details.name, version.version_number, details.author, profile.name, profile.version
+                    )));
+                    dialog.set_response_sensitive(gtk::ResponseType::Accept, true);
+                },
+                None => {
+                    let message = match &requested_version {
+                        Some(version) => format!("Version {} of {} was not found.", version, details.name),
+                        None => format!(
+                            "No version of {} supports the profile '{}' (Minecraft {}, {}).",
+                            details.name,
+                            profile.name,
+                            profile.version,
+                            get_mod_loader_name(profile.mod_loader.as_ref().unwrap_or(&ModLoader::None))
+                        ),
+                    };
+                    dialog.set_secondary_text(Some(&message));
+                    dialog.set_response_sensitive(gtk::ResponseType::Accept, false);
+                }
+            }
+        }
+    };
+
+    update_message(selected as u32);
+    profile_dropdown.connect_selected_notify(move |dropdown| update_message(dropdown.selected()));
+
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Accept {
+            if let Some(profile) = game.profiles.get(profile_dropdown.selected() as usize) {
+                if let Some(version) = details.find_version(requested_version.as_deref(), profile) {
+                    install_mod(
+                        &toast_overlay,
+                        config.borrow().clone(),
+                        (*game).clone(),
+                        profile.clone(),
+                        version.clone(),
+                        details.name.clone(),
+                    );
+                }
+            }
+        }
+
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
+/// Installs a mod version into a profile in the background
+fn install_mod(
+    toast_overlay: &adw::ToastOverlay,
+    config_snapshot: Config,
+    game: Game,
+    profile: Profile,
+    version: ModVersionInfo,
+    mod_name: String,
+) {
+    let toast = adw::Toast::new(&format!("Installing {}...", mod_name));
+    toast_overlay.add_toast(toast);
+
+    let toast_overlay = toast_overlay.clone();
+    let profile_name = profile.name.clone();
+
+    glib::spawn_future_local(async move {
+        let result = gio::spawn_blocking(move || -> Result<()> {
+            let rt = tokio::runtime::Runtime::new()?;
+            let mod_manager = create_mod_manager(&config_snapshot, &game);
+            rt.block_on(mod_manager.install_mod(&version, &profile))
+        }).await;
+
+        let message = match result {
+            Ok(Ok(())) => {
+                info!("Installed {} into profile {}", mod_name, profile_name);
+                format!("Installed {} into '{}'", mod_name, profile_name)
+            },
+            Ok(Err(e)) => {
+                error!("Failed to install {}: {}", mod_name, e);
+                format!("Failed to install {}: {}", mod_name, e)
+            },
+            Err(_) => format!("Failed to install {}", mod_name),
+        };
+
+        let toast = adw::Toast::new(&message);
+        toast_overlay.add_toast(toast);
+    });
+}
+
+/// Downloads the linked modpack, then asks to create a profile for it
+fn open_modpack_link(
+    window: &gtk::Window,
+    toast_overlay: &adw::ToastOverlay,
+    config: Rc<RefCell<Config>>,
+    game: Game,
+    url: String,
+) {
+    let toast = adw::Toast::new("Downloading modpack...");
+    toast_overlay.add_toast(toast);
+
+    let window = window.clone();
+    let toast_overlay = toast_overlay.clone();
+    let config_snapshot = config.borrow().clone();
+    let game_thread = game.clone();
+
+    glib::spawn_future_local(async move {
+        let result = gio::spawn_blocking(move || -> Result<(Modpack, Option<ModSearchResult>)> {
+            let rt = tokio::runtime::Runtime::new()?;
+            let mut file_manager = FileManager::new();
+            file_manager.configure(&config_snapshot);
+            let modpack = rt.block_on(modpacks::download_modpack(&file_manager, &url))?;
+
+            // Packs hosted on Modrinth also get their project details
+            let details = match modpacks::get_modrinth_project_id(&url) {
+                Some(project) => {
+                    let mod_manager = create_mod_manager(&config_snapshot, &game_thread);
+                    match rt.block_on(mod_manager.get_mod_details(&project, &ModSource::Modrinth)) {
+                        Ok(details) => Some(details),
+                        Err(e) => {
+                            warn!("Failed to get details for modpack project {}: {}", project, e);
+                            None
+                        }
+                    }
+                },
+                None => None,
+            };
+
+            Ok((modpack, details))
+        }).await;
+
+        match result {
+            Ok(Ok((modpack, details))) => show_modpack_dialog(&window, &toast_overlay, config, game, modpack, details),
+            Ok(Err(e)) => {
+                error!("Failed to download modpack: {}", e);
+                let toast = adw::Toast::new(&format!("Failed to download modpack: {}", e));
+                toast_overlay.add_toast(toast);
+            },
+            Err(_) => {
+                let toast = adw::Toast::new("Failed to download modpack");
+                toast_overlay.add_toast(toast);
+            }
+        }
+    });
+}
+
+/// Shows the install confirmation for a modpack, naming the profile it will create
+fn show_modpack_dialog(
+    window: &gtk::Window,
+    toast_overlay: &adw::ToastOverlay,
+    config: Rc<RefCell<Config>>,
+    game: Game,
+    modpack: Modpack,
+    details: Option<ModSearchResult>,
+) {
+    let minecraft_version = match modpack.minecraft_version() {
+        Ok(version) => version.to_string(),
+        Err(e) => {
+            let _ = std::fs::remove_file(&modpack.archive_path);
+            let toast = adw::Toast::new(&format!("Cannot install modpack: {}", e));
+            toast_overlay.add_toast(toast);
+            return;
+        }
+    };
+
+    // Pick a profile name that is not taken yet
+    let base_name = details.as_ref()
+        .map(|d| d.name.clone())
+        .unwrap_or_else(|| modpack.index.name.clone());
+    let mut profile_name = base_name.clone();
+    let mut suffix = 2;
+    while game.profiles.iter().any(|p| p.name.eq_ignore_ascii_case(&profile_name)) {
+        profile_name = format!("{} ({})", base_name, suffix);
+        suffix += 1;
+    }
+
+    let (mod_loader, mod_loader_version) = modpack.mod_loader();
+    let loader = match mod_loader_version {
+        Some(version) => format!("{} {}", get_mod_loader_name(&mod_loader), version),
+        None => get_mod_loader_name(&mod_loader).to_string(),
+    };
+    let author = details.as_ref()
+        .map(|d| format!(" by {}", d.author))
+        .unwrap_or_default();
+
+    let dialog = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::None,
+        &format!("Install modpack {}?", modpack.index.name),
+    );
+    dialog.set_title(Some("Install Modpack"));
+    dialog.set_secondary_text(Some(&format!(
+        "{} {}{} will be installed into a new profile '{}' for Minecraft {} ({}) with {} files.",
+        modpack.index.name,
+        modpack.index.version_id,
+        author,
+        profile_name,
+        minecraft_version,
+        loader,
+        modpack.client_files().len()
+    )));
+
+    dialog.add_button("Cancel", gtk::ResponseType::Cancel);
+    dialog.add_button("Install", gtk::ResponseType::Accept);
+    dialog.set_default_response(gtk::ResponseType::Cancel);
+
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(move |dialog, response| {
+        if response == gtk::ResponseType::Accept {
+            let instances_dir = game.game_directory.join("instances");
+            match modpack.create_profile(&profile_name, &instances_dir) {
+                Ok(profile) => install_modpack(&toast_overlay, config.clone(), game.id.clone(), modpack.clone(), profile),
+                Err(e) => {
+                    let _ = std::fs::remove_file(&modpack.archive_path);
+                    let toast = adw::Toast::new(&format!("Cannot install modpack: {}", e));
+                    toast_overlay.add_toast(toast);
+                }
+            }
+        } else {
+            let _ = std::fs::remove_file(&modpack.archive_path);
+        }
+
+        dialog.close();
+    });
+
+    dialog.present();
+}
+
+/// Installs a modpack in the background and adds its profile once everything is in place
+fn install_modpack(
+    toast_overlay: &adw::ToastOverlay,
+    config: Rc<RefCell<Config>>,
+    game_id: String,
+    modpack: Modpack,
+    profile: Profile,
+) {
+    let toast = adw::Toast::new(&format!("Installing modpack {}...", modpack.index.name));
+    toast_overlay.add_toast(toast);
+
+    let toast_overlay = toast_overlay.clone();
+    let pack_name = modpack.index.name.clone();
+    let profile_thread = profile.clone();
+    let config_snapshot = config.borrow().clone();
+
+    glib::spawn_future_local(async move {
+        let result = gio::spawn_blocking(move || -> Result<()> {
+            let rt = tokio::runtime::Runtime::new()?;
+            let mut file_manager = FileManager::new();
+            file_manager.configure(&config_snapshot);
+            let result = rt.block_on(modpacks::install_modpack(&file_manager, &modpack, &profile_thread, |done, total| {
+                info!("Modpack {}: {}/{} files", modpack.index.name, done, total);
+            }));
+
+            let _ = std::fs::remove_file(&modpack.archive_path);
+
+            // Don't leave a half installed pack behind
+            if result.is_err() {
+                if let Some(dir) = &profile_thread.game_directory {
+                    let _ = std::fs::remove_dir_all(dir);
+                }
+            }
+
+            result
+        }).await;
+
+        let message = match result {
+            Ok(Ok(())) => {
+                let mut config_mut = config.borrow_mut();
+                match config_mut.games.iter_mut().find(|g| g.id == game_id) {
+                    Some(game) => {
+                        game.profiles.push(profile.clone());
+                        match save_config(&config_mut) {
+                            Ok(_) => format!("Installed modpack {} as profile '{}'", pack_name, profile.name),
+                            Err(e) => format!("Installed modpack {} but failed to save the profile: {}", pack_name, e),
+                        }
+                    },
+                    None => format!("Installed modpack {} but its game no longer exists", pack_name),
+                }
+            },
+            Ok(Err(e)) => {
+                error!("Failed to install modpack {}: {}", pack_name, e);
+                format!("Failed to install modpack {}: {}", pack_name, e)
+            },
+            Err(_) => format!("Failed to install modpack {}", pack_name),
+        };
+
+        let toast = adw::Toast::new(&message);
+        toast_overlay.add_toast(toast);
+    });
+}
@@ -5,6 +5,8 @@ mod app;
 mod main_view;
 mod profiles;
 mod settings;
+mod launch_settings;
 mod game_selector;
+mod links;
 
 pub use app::MosaicApp;
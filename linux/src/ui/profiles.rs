@@ -1,19 +1,23 @@
 use gtk4 as gtk;
 use gtk::prelude::*;
-use gtk::glib;
+use gtk::{gio, glib};
 use gtk::glib::clone;
 use libadwaita as adw;
 use adw::prelude::*;
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use log::{info, warn, error, debug};
 use uuid::Uuid;
 
 use crate::config::{Config, Profile, ModLoader, save_config};
-use crate::games::minecraft::VersionManifest;
-use crate::file_manager::FileManager;
+use crate::games::minecraft::{MinecraftManager, VersionManifest, VerifyReport};
+use crate::games::minecraft::importers::{self, ImportedProfile};
+use crate::file_manager::{FileManager, CancellationToken};
+use crate::progress::InstallProgress;
+use crate::desktop;
+use super::launch_settings::build_launch_override_rows;
 use std::fs;
 
 pub fn build_profiles_view(
@@ -40,6 +44,18 @@ pub fn build_profiles_view(
     new_profile_button.set_tooltip_text(Some("Create new profile"));
     header.pack_end(&new_profile_button);
 
+    // Add an import button
+    let import_button = gtk::Button::new();
+    import_button.set_icon_name("document-open-symbolic");
+    import_button.set_tooltip_text(Some("Import profiles from the Minecraft Launcher"));
+    header.pack_end(&import_button);
+
+    // Add a MultiMC / Prism import button
+    let import_instance_button = gtk::Button::new();
+    import_instance_button.set_icon_name("folder-download-symbolic");
+    import_instance_button.set_tooltip_text(Some("Import MultiMC or Prism instances"));
+    header.pack_end(&import_instance_button);
+
     profiles_box.append(&header);
 
     // Create a scrolled window for the profiles list
@@ -112,6 +128,51 @@ pub fn build_profiles_view(
 
         row.add_suffix(&edit_button);
 
+        // Add a desktop entry button
+        let desktop_button = gtk::Button::new();
+        desktop_button.set_icon_name("application-x-executable-symbolic");
+        desktop_button.set_tooltip_text(Some("Add to applications menu"));
+        desktop_button.add_css_class("flat");
+
+        // Connect the desktop entry button
+        let toast_overlay_clone = toast_overlay.clone();
+        let profile_clone = profile.clone();
+        let game_name = game.name.clone();
+
+        desktop_button.connect_clicked(move |_| {
+            match desktop::create_desktop_entry(&profile_clone, &game_name) {
+                Ok(_) => {
+                    let toast = adw::Toast::new(&format!("Added {} to the applications menu", profile_clone.name));
+                    toast_overlay_clone.add_toast(toast);
+                },
+                Err(e) => {
+                    error!("Failed to create desktop entry: {}", e);
+                    let toast = adw::Toast::new(&format!("Failed to create desktop entry: {}", e));
+                    toast_overlay_clone.add_toast(toast);
+                }
+            }
+        });
+
+        row.add_suffix(&desktop_button);
+
+        // Add a verify files button
+        let verify_button = gtk::Button::new();
+        verify_button.set_icon_name("emblem-ok-symbolic");
+        verify_button.set_tooltip_text(Some("Verify files"));
+        verify_button.add_css_class("flat");
+
+        // Connect the verify files button
+        let config_clone = config.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        let row_clone = row.clone();
+        let profile_clone = profile.clone();
+
+        verify_button.connect_clicked(move |verify_button| {
+            verify_profile_files(&toast_overlay_clone, config_clone.borrow().clone(), profile_clone.clone(), &row_clone, verify_button);
+        });
+
+        row.add_suffix(&verify_button);
+
         // Add a delete button
         let delete_button = gtk::Button::new();
         delete_button.set_icon_name("user-trash-symbolic");
@@ -185,6 +246,11 @@ pub fn build_profiles_view(
                         // Remove the profile from the config
                         game.profiles.remove(profile_index);
 
+                        // Remove its desktop entry, if one was created
+                        if let Err(e) = desktop::remove_desktop_entry(&profile_id2) {
+                            warn!("Failed to remove desktop entry: {}", e);
+                        }
+
                         // Save the config
                         if let Err(e) = save_config(&config_mut) {
                             let toast = adw::Toast::new(&format!("Failed to delete profile: {}", e));
@@ -291,11 +357,143 @@ pub fn build_profiles_view(
         );
     });
 
+    // Connect the import button
+    let config_clone = config.clone();
+    let version_manifest_clone = version_manifest.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    let window_clone = window.clone();
+    let list_box_clone = list_box.clone();
+
+    import_button.connect_clicked(move |_| {
+        import_from_official_launcher(
+            &window_clone,
+            &toast_overlay_clone,
+            config_clone.clone(),
+            version_manifest_clone.clone(),
+            list_box_clone.clone(),
+        );
+    });
+
+    // Connect the instance import button
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    let window_clone = window.clone();
+    let list_box_clone = list_box.clone();
+
+    import_instance_button.connect_clicked(move |_| {
+        import_from_multimc(
+            &window_clone,
+            &toast_overlay_clone,
+            config_clone.clone(),
+            list_box_clone.clone(),
+        );
+    });
+
     info!("Built profiles view");   
 
     profiles_box
 }
 
+/// Verifies the files of a profile's version in the background and downloads damaged ones again
+///
+/// The row shows the progress in its subtitle until the check is done.
+fn verify_profile_files(
+    toast_overlay: &adw::ToastOverlay,
+    config_snapshot: Config,
+    profile: Profile,
+    row: &adw::ActionRow,
+    button: &gtk::Button,
+) {
+    button.set_sensitive(false);
+    let subtitle = row.subtitle().map(|subtitle| subtitle.to_string()).unwrap_or_default();
+
+    // Progress arrives from the worker thread, late updates mustn't replace the restored subtitle
+    let done = Rc::new(Cell::new(false));
+    let (sender, receiver) = glib::MainContext::channel(glib::Priority::DEFAULT);
+    receiver.attach(None, clone!(@strong row, @strong done => move |progress: InstallProgress| {
+        if done.get() {
+            return glib::ControlFlow::Break;
+        }
+        row.set_subtitle(&format!("{:.0}% · {}", progress.fraction() * 100.0, progress.status_text()));
+        glib::ControlFlow::Continue
+    }));
+
+    let toast_overlay = toast_overlay.clone();
+    let row = row.clone();
+    let button = button.clone();
+    let profile_name = profile.name.clone();
+
+    glib::spawn_future_local(async move {
+        let result = gio::spawn_blocking(move || -> anyhow::Result<VerifyReport> {
+            let rt = tokio::runtime::Runtime::new()?;
+            let manager = MinecraftManager::new(config_snapshot, Rc::new(FileManager::new()));
+            rt.block_on(manager.verify_profile(&profile, move |progress| {
+                let _ = sender.send(progress);
+            }, &CancellationToken::new()))
+        }).await;
+
+        done.set(true);
+        row.set_subtitle(&subtitle);
+        button.set_sensitive(true);
+
+        let message = match result {
+            Ok(Ok(report)) if report.is_intact() => format!("All {} files of '{}' are intact", report.checked, profile_name),
+            Ok(Ok(report)) => {
+                let generated = report.damaged.iter().filter(|file| file.url.is_none()).count();
+                if generated > 0 {
+                    format!("Repaired {} files of '{}', {} generated files need the mod loader reinstalled",
+                        report.damaged.len() - generated, profile_name, generated)
+                } else {
+                    format!("Repaired {} files of '{}'", report.damaged.len(), profile_name)
+                }
+            },
+            Ok(Err(e)) => {
+                error!("Failed to verify {}: {}", profile_name, e);
+                format!("Failed to verify '{}': {}", profile_name, e)
+            },
+            Err(_) => format!("Failed to verify '{}'", profile_name),
+        };
+
+        let toast = adw::Toast::new(&message);
+        toast_overlay.add_toast(toast);
+    });
+}
+
+/// Checks for the alpha and beta versions that are hidden unless asked for
+fn is_historical_version(version_type: &str) -> bool {
+    version_type == "old_alpha" || version_type == "old_beta"
+}
+
+/// Fills the version dropdown from the manifest and selects `selected` if it is listed
+///
+/// Falls back to the latest release when `selected` is a hidden historical version.
+fn populate_versions(
+    version_model: &gtk::StringList,
+    version_row: &adw::ComboRow,
+    manifest: &VersionManifest,
+    include_historical: bool,
+    selected: &str,
+) {
+    let ids: Vec<&str> = manifest.versions.iter()
+        .filter(|version| include_historical || !is_historical_version(&version.r#type))
+        .map(|version| version.id.as_str())
+        .collect();
+    version_model.splice(0, version_model.n_items(), &ids);
+
+    let position = ids.iter().position(|id| *id == selected)
+        .or_else(|| ids.iter().position(|id| *id == manifest.latest.release));
+    if let Some(position) = position {
+        version_row.set_selected(position as u32);
+    }
+}
+
+/// Gets the version id selected in the version dropdown
+fn get_selected_version(version_row: &adw::ComboRow) -> Option<String> {
+    version_row.selected_item()
+        .and_downcast::<gtk::StringObject>()
+        .map(|item| item.string().to_string())
+}
+
 fn show_profile_dialog(
     window: &adw::ApplicationWindow,
     toast_overlay: &adw::ToastOverlay,
@@ -346,32 +544,21 @@ fn show_profile_dialog(
     let version_model = gtk::StringList::new(&[]);
     version_row.set_model(Some(&version_model));
 
+    // Alpha and beta versions are only listed on request, or when the profile already uses one
+    let include_historical = match (&*version_manifest.lock().unwrap(), &profile) {
+        (Some(manifest), Some(profile)) => manifest.versions.iter()
+            .any(|version| version.id == profile.version && is_historical_version(&version.r#type)),
+        _ => false,
+    };
+
     // Populate the version dropdown
     if let Some(manifest) = &*version_manifest.lock().unwrap() {
-        // Add all versions to the dropdown
-        for version in &manifest.versions {
-            version_model.append(&version.id);
-        }
-
-        // Select the appropriate version
-        if let Some(profile) = &profile {
-            // For existing profiles, select the current version
-            for (i, version) in manifest.versions.iter().enumerate() {
-                if version.id == profile.version {
-                    version_row.set_selected(i as u32);
-                    break;
-                }
-            }
-        } else {
-            // For new profiles, select the latest release
-            let latest_release = &manifest.latest.release;
-            for (i, version) in manifest.versions.iter().enumerate() {
-                if &version.id == latest_release {
-                    version_row.set_selected(i as u32);
-                    break;
-                }
-            }
-        }
+        // Existing profiles keep their version, new ones start on the latest release
+        let selected = match &profile {
+            Some(profile) => profile.version.clone(),
+            None => manifest.latest.release.clone(),
+        };
+        populate_versions(&version_model, &version_row, manifest, include_historical, &selected);
     } else {
         // If the version manifest isn't loaded, show a placeholder
         version_model.append("Loading...");
@@ -380,6 +567,31 @@ fn show_profile_dialog(
     }
     basic_group.add(&version_row);
 
+    // Add a row to list the historical versions
+    let historical_row = adw::ActionRow::new();
+    historical_row.set_title("Include Historical Versions");
+    historical_row.set_subtitle("Alpha and beta versions from before Minecraft 1.0");
+
+    let historical_switch = gtk::Switch::new();
+    historical_switch.set_valign(gtk::Align::Center);
+    historical_switch.set_active(include_historical);
+    historical_row.add_suffix(&historical_switch);
+
+    basic_group.add(&historical_row);
+
+    // Connect the historical switch, the selected version stays selected if it is still listed
+    let version_model_clone = version_model.clone();
+    let version_row_clone = version_row.clone();
+    let version_manifest_clone = version_manifest.clone();
+
+    historical_switch.connect_active_notify(move |switch| {
+        if let Some(manifest) = &*version_manifest_clone.lock().unwrap() {
+            let selected = get_selected_version(&version_row_clone)
+                .unwrap_or_else(|| manifest.latest.release.clone());
+            populate_versions(&version_model_clone, &version_row_clone, manifest, switch.is_active(), &selected);
+        }
+    });
+
     // Add a row for the mod loader
     let loader_row = adw::ComboRow::new();
     loader_row.set_title("Mod Loader");
@@ -562,12 +774,8 @@ fn show_profile_dialog(
 
     // Get the current Minecraft version
     let minecraft_version = if let Some(manifest) = &*version_manifest.lock().unwrap() {
-        let selected_index = version_row.selected();
-        if selected_index != gtk::INVALID_LIST_POSITION && selected_index < manifest.versions.len() as u32 {
-            manifest.versions[selected_index as usize].id.clone()
-        } else {
-            manifest.latest.release.clone()
-        }
+        get_selected_version(&version_row)
+            .unwrap_or_else(|| manifest.latest.release.clone())
     } else {
         "1.20.4".to_string() // Default to a recent version if manifest isn't loaded
     };
@@ -592,12 +800,8 @@ fn show_profile_dialog(
 
         // Get the current Minecraft version
         let minecraft_version = if let Some(manifest) = &*version_manifest_clone.lock().unwrap() {
-            let selected_index = version_row_clone.selected();
-            if selected_index != gtk::INVALID_LIST_POSITION && selected_index < manifest.versions.len() as u32 {
-                manifest.versions[selected_index as usize].id.clone()
-            } else {
-                manifest.latest.release.clone()
-            }
+            get_selected_version(&version_row_clone)
+                .unwrap_or_else(|| manifest.latest.release.clone())
         } else {
             "1.20.4".to_string() // Default to a recent version if manifest isn't loaded
         };
@@ -630,12 +834,8 @@ fn show_profile_dialog(
 
         // Get the current Minecraft version
         let minecraft_version = if let Some(manifest) = &*version_manifest_clone.lock().unwrap() {
-            let selected_index = version_row.selected();
-            if selected_index != gtk::INVALID_LIST_POSITION && selected_index < manifest.versions.len() as u32 {
-                manifest.versions[selected_index as usize].id.clone()
-            } else {
-                manifest.latest.release.clone()
-            }
+            get_selected_version(&version_row)
+                .unwrap_or_else(|| manifest.latest.release.clone())
         } else {
             "1.20.4".to_string() // Default to a recent version if manifest isn't loaded
         };
@@ -674,22 +874,23 @@ fn show_profile_dialog(
     browse_button.set_valign(gtk::Align::Center);
     game_dir_row.add_suffix(&browse_button);
 
-    // Add a row for RAM allocation
-    let memory_row = adw::ActionRow::new();
-    memory_row.set_title("RAM Allocation (MB)");
-    let memory_entry = gtk::SpinButton::with_range(512.0, 32768.0, 512.0);
-
-    // Set the default value from the profile or global config
-    let config_ref = config.borrow();
-    let default_memory = if let Some(profile) = &profile {
-        profile.memory.unwrap_or(config_ref.max_memory)
-    } else {
-        config_ref.max_memory
+    // Create a group for the launch setting overrides
+    let launch_group = adw::PreferencesGroup::new();
+    launch_group.set_title("Launch Settings");
+    launch_group.set_description(Some("Leave a field empty to use the game or global setting"));
+    general_page.add(&launch_group);
+
+    // The settings this profile inherits from the global and game layers
+    let inherited_launch = {
+        let config_ref = config.borrow();
+        let selected_game_id = config_ref.selected_game.clone().unwrap_or_else(|| "minecraft".to_string());
+        match config_ref.games.iter().find(|g| g.id == selected_game_id) {
+            Some(game) => config_ref.launch.overlay(&game.launch),
+            None => config_ref.launch.clone(),
+        }
     };
-    memory_entry.set_value(default_memory as f64);
-
-    memory_row.add_suffix(&memory_entry);
-    basic_group.add(&memory_row);
+    let current_launch = profile.as_ref().map(|p| p.launch.clone()).unwrap_or_default();
+    let launch_rows = build_launch_override_rows(&launch_group, "Override Memory", &inherited_launch, &current_launch);
 
     // Connect the browse button
     let window_clone = window.clone();
@@ -761,7 +962,7 @@ fn show_profile_dialog(
         let loader_version_row_clone = loader_version_row.clone();
         let loader_version_model_clone = loader_version_model.clone();
         let game_dir_entry_clone = game_dir_entry.clone();
-        let memory_entry_clone = memory_entry.clone();
+        let launch_rows_clone = launch_rows.clone();
         let version_manifest_clone = version_manifest.clone();
         let dialog_clone = dialog.clone();
 
@@ -772,7 +973,6 @@ fn show_profile_dialog(
             let loader_index = loader_row_clone.selected();
             let loader_version_index = loader_version_row_clone.selected();
             let game_dir = game_dir_entry_clone.text().to_string();
-            let memory = memory_entry_clone.value() as u32;
 
             // Validate the form
             if name.is_empty() {
@@ -789,12 +989,14 @@ fn show_profile_dialog(
 
             // Get the selected version
             let version = if let Some(manifest) = &*version_manifest_clone.lock().unwrap() {
-                if version_index as usize >= manifest.versions.len() {
-                    let toast = adw::Toast::new("Invalid Minecraft version selected");
-                    toast_overlay_clone.add_toast(toast);
-                    return;
+                match get_selected_version(&version_row_clone) {
+                    Some(version) if manifest.versions.iter().any(|listed| listed.id == version) => version,
+                    _ => {
+                        let toast = adw::Toast::new("Invalid Minecraft version selected");
+                        toast_overlay_clone.add_toast(toast);
+                        return;
+                    }
                 }
-                manifest.versions[version_index as usize].id.clone()
             } else {
                 let toast = adw::Toast::new("Failed to load Minecraft versions");
                 toast_overlay_clone.add_toast(toast);
@@ -815,6 +1017,15 @@ fn show_profile_dialog(
                 }
             };
 
+            let launch = match launch_rows_clone.read() {
+                Ok(launch) => launch,
+                Err(e) => {
+                    let toast = adw::Toast::new(&e.to_string());
+                    toast_overlay_clone.add_toast(toast);
+                    return;
+                }
+            };
+
             // Create the profile
             let mut config_mut = config_clone.borrow_mut();
 
@@ -862,8 +1073,9 @@ fn show_profile_dialog(
                     Some(PathBuf::from(game_dir))
                 },
                 resolution: None,
+                icon: None,
                 mods: Vec::new(),
-                memory: Some(memory),
+                launch,
             });
 
             // Save the config
@@ -927,7 +1139,7 @@ fn show_profile_dialog(
         let loader_version_row_clone = loader_version_row.clone();
         let loader_version_model_clone = loader_version_model.clone();
         let game_dir_entry_clone = game_dir_entry.clone();
-        let memory_entry_clone = memory_entry.clone();
+        let launch_rows_clone = launch_rows.clone();
         let version_manifest_clone = version_manifest.clone();
 
         dialog.connect_close_request(move |dialog| {
@@ -953,12 +1165,14 @@ fn show_profile_dialog(
 
             // Get the selected version
             let version = if let Some(manifest) = &*version_manifest_clone.lock().unwrap() {
-                if version_index as usize >= manifest.versions.len() {
-                    let toast = adw::Toast::new("Invalid Minecraft version selected");
-                    toast_overlay_clone.add_toast(toast);
-                    return glib::Propagation::Stop;
+                match get_selected_version(&version_row_clone) {
+                    Some(version) if manifest.versions.iter().any(|listed| listed.id == version) => version,
+                    _ => {
+                        let toast = adw::Toast::new("Invalid Minecraft version selected");
+                        toast_overlay_clone.add_toast(toast);
+                        return glib::Propagation::Stop;
+                    }
                 }
-                manifest.versions[version_index as usize].id.clone()
             } else {
                 let toast = adw::Toast::new("Failed to load Minecraft versions");
                 toast_overlay_clone.add_toast(toast);
@@ -979,6 +1193,15 @@ fn show_profile_dialog(
                 }
             };
 
+            let launch = match launch_rows_clone.read() {
+                Ok(launch) => launch,
+                Err(e) => {
+                    let toast = adw::Toast::new(&e.to_string());
+                    toast_overlay_clone.add_toast(toast);
+                    return glib::Propagation::Stop;
+                }
+            };
+
             // Create or update the profile
             let mut config_mut = config_clone.borrow_mut();
 
@@ -1024,7 +1247,7 @@ fn show_profile_dialog(
                         } else {
                             Some(PathBuf::from(game_dir))
                         };
-                        p.memory = Some(memory_entry.value() as u32);
+                        p.launch = launch;
                         break;
                     }
                 }
@@ -1053,8 +1276,9 @@ fn show_profile_dialog(
                         Some(PathBuf::from(game_dir))
                     },
                     resolution: None,
+                    icon: None,
                     mods: Vec::new(),
-                    memory: Some(memory_entry.value() as u32),
+                    launch,
                 });
                 id
             };
@@ -1108,3 +1332,343 @@ fn show_profile_dialog(
         dialog.present();
     }
 }
+
+/// Reads the official launcher's profiles from the selected game's directory and previews them
+fn import_from_official_launcher(
+    window: &adw::ApplicationWindow,
+    toast_overlay: &adw::ToastOverlay,
+    config: Rc<RefCell<Config>>,
+    version_manifest: Arc<Mutex<Option<VersionManifest>>>,
+    list_box: gtk::ListBox,
+) {
+    // Read the profiles from the selected game's directory
+    let minecraft_dir = {
+        let config_ref = config.borrow();
+        let selected_game_id = config_ref.selected_game.clone().unwrap_or_else(|| "minecraft".to_string());
+        match config_ref.games.iter().find(|g| g.id == selected_game_id) {
+            Some(game) => game.game_directory.clone(),
+            None => {
+                let toast = adw::Toast::new("No game selected");
+                toast_overlay.add_toast(toast);
+                return;
+            }
+        }
+    };
+
+    let latest = version_manifest.lock().unwrap().as_ref().map(|m| m.latest.clone());
+    let candidates = match importers::read_launcher_profiles(&minecraft_dir, latest.as_ref()) {
+        Ok(candidates) => candidates,
+        Err(e) => {
+            error!("Failed to read launcher profiles: {}", e);
+            let toast = adw::Toast::new(&format!("Failed to read launcher profiles: {}", e));
+            toast_overlay.add_toast(toast);
+            return;
+        }
+    };
+
+    if candidates.is_empty() {
+        let toast = adw::Toast::new("No profiles found in launcher_profiles.json");
+        toast_overlay.add_toast(toast);
+        return;
+    }
+
+    show_import_dialog(
+        window,
+        toast_overlay,
+        config,
+        list_box,
+        "Minecraft Launcher Profiles",
+        &format!("Found in {}", minecraft_dir.join("launcher_profiles.json").to_string_lossy()),
+        candidates,
+        None,
+    );
+}
+
+/// Lets the user pick a MultiMC / Prism instance (or instances folder) and previews it
+fn import_from_multimc(
+    window: &adw::ApplicationWindow,
+    toast_overlay: &adw::ToastOverlay,
+    config: Rc<RefCell<Config>>,
+    list_box: gtk::ListBox,
+) {
+    let file_chooser = gtk::FileDialog::new();
+    file_chooser.set_title("Select a MultiMC or Prism Instance");
+    file_chooser.set_modal(true);
+
+    let window_clone = window.clone();
+    let toast_overlay = toast_overlay.clone();
+    file_chooser.select_folder(Some(window), None::<&gtk::gio::Cancellable>, move |result| {
+        let path = match result.ok().and_then(|file| file.path()) {
+            Some(path) => path,
+            None => return,
+        };
+
+        let candidates = match importers::read_multimc_instances(&path) {
+            Ok(candidates) => candidates,
+            Err(e) => {
+                error!("Failed to read instances: {}", e);
+                let toast = adw::Toast::new(&format!("Failed to read instances: {}", e));
+                toast_overlay.add_toast(toast);
+                return;
+            }
+        };
+
+        // Copies go next to the game's other files
+        let instances_dir = {
+            let config_ref = config.borrow();
+            let selected_game_id = config_ref.selected_game.clone().unwrap_or_else(|| "minecraft".to_string());
+            config_ref.games.iter()
+                .find(|g| g.id == selected_game_id)
+                .map(|g| g.game_directory.join("instances"))
+        };
+
+        show_import_dialog(
+            &window_clone,
+            &toast_overlay,
+            config.clone(),
+            list_box.clone(),
+            "MultiMC / Prism Instances",
+            &format!("Found in {}", path.to_string_lossy()),
+            candidates,
+            instances_dir,
+        );
+    });
+}
+
+/// Shows a preview of profiles read from another launcher and imports the selected ones
+///
+/// When `instances_dir` is set the dialog offers to copy each profile's game
+/// directory there instead of using it in place.
+fn show_import_dialog(
+    window: &adw::ApplicationWindow,
+    toast_overlay: &adw::ToastOverlay,
+    config: Rc<RefCell<Config>>,
+    list_box: gtk::ListBox,
+    title: &str,
+    description: &str,
+    candidates: Vec<ImportedProfile>,
+    instances_dir: Option<PathBuf>,
+) {
+    let existing_profiles = {
+        let config_ref = config.borrow();
+        let selected_game_id = config_ref.selected_game.clone().unwrap_or_else(|| "minecraft".to_string());
+        config_ref.games.iter()
+            .find(|g| g.id == selected_game_id)
+            .map(|g| g.profiles.clone())
+            .unwrap_or_default()
+    };
+
+    // Create a dialog for the preview
+    let dialog = adw::PreferencesWindow::new();
+    dialog.set_transient_for(Some(window));
+    dialog.set_modal(true);
+    dialog.set_title(Some("Import Profiles"));
+    dialog.set_default_width(500);
+    dialog.set_default_height(600);
+
+    let page = adw::PreferencesPage::new();
+    dialog.add(&page);
+
+    let group = adw::PreferencesGroup::new();
+    group.set_title(title);
+    group.set_description(Some(description));
+    page.add(&group);
+
+    // Add a row per profile, profiles that already exist start unchecked
+    let mut check_buttons = Vec::new();
+    for candidate in &candidates {
+        let row = adw::ActionRow::new();
+        row.set_title(&candidate.profile.name);
+
+        let mut subtitle = format!("Minecraft {}", candidate.profile.version);
+        match candidate.profile.mod_loader {
+            Some(ModLoader::Forge) => subtitle.push_str(" with Forge"),
+            Some(ModLoader::Fabric) => subtitle.push_str(" with Fabric"),
+            Some(ModLoader::Quilt) => subtitle.push_str(" with Quilt"),
+            Some(ModLoader::NeoForge) => subtitle.push_str(" with NeoForge"),
+            _ => {}
+        }
+        if let Some(version) = &candidate.profile.mod_loader_version {
+            subtitle.push_str(&format!(" {}", version));
+        }
+        for warning in &candidate.warnings {
+            subtitle.push_str(&format!("\n{}", warning));
+        }
+        row.set_subtitle(&subtitle);
+
+        let already_exists = existing_profiles.iter()
+            .any(|p| p.name == candidate.profile.name && p.version == candidate.profile.version);
+
+        let check_button = gtk::CheckButton::new();
+        check_button.set_active(!already_exists);
+        row.add_prefix(&check_button);
+        row.set_activatable_widget(Some(&check_button));
+
+        group.add(&row);
+        check_buttons.push(check_button);
+    }
+
+    // Offer to copy the game directories instead of sharing them
+    let copy_switch = gtk::Switch::new();
+    if instances_dir.is_some() {
+        let options_group = adw::PreferencesGroup::new();
+        page.add(&options_group);
+
+        let copy_row = adw::ActionRow::new();
+        copy_row.set_title("Copy Game Directories");
+        copy_row.set_subtitle("Off: profiles use the instance folders in place");
+        copy_switch.set_active(true);
+        copy_switch.set_valign(gtk::Align::Center);
+        copy_row.add_suffix(&copy_switch);
+        copy_row.set_activatable_widget(Some(&copy_switch));
+        options_group.add(&copy_row);
+    }
+
+    // Create the buttons
+    let button_group = adw::PreferencesGroup::new();
+    page.add(&button_group);
+
+    let import_button = gtk::Button::with_label("Import");
+    import_button.add_css_class("suggested-action");
+
+    let cancel_button = gtk::Button::with_label("Cancel");
+
+    let button_box = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+    button_box.set_halign(gtk::Align::End);
+    button_box.set_margin_top(10);
+    button_box.append(&cancel_button);
+    button_box.append(&import_button);
+    button_group.add(&button_box);
+
+    let dialog_clone = dialog.clone();
+    cancel_button.connect_clicked(move |_| {
+        dialog_clone.close();
+    });
+
+    let dialog_clone = dialog.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    let cancel_button_clone = cancel_button.clone();
+    import_button.connect_clicked(move |import_button| {
+        let selected: Vec<ImportedProfile> = candidates.iter()
+            .zip(check_buttons.iter())
+            .filter(|(_, check)| check.is_active())
+            .map(|(candidate, _)| candidate.clone())
+            .collect();
+
+        if selected.is_empty() {
+            dialog_clone.close();
+            return;
+        }
+
+        // Copying whole instances can take minutes, so it runs off the main thread
+        let copy_to = instances_dir.clone().filter(|_| copy_switch.is_active());
+        import_button.set_sensitive(false);
+        cancel_button_clone.set_sensitive(false);
+
+        let (sender, receiver) = glib::MainContext::channel(glib::Priority::DEFAULT);
+        receiver.attach(None, clone!(@strong import_button => move |status: String| {
+            import_button.set_label(&status);
+            glib::ControlFlow::Continue
+        }));
+
+        let config = config.clone();
+        let list_box = list_box.clone();
+        let dialog = dialog_clone.clone();
+        let toast_overlay = toast_overlay_clone.clone();
+
+        glib::spawn_future_local(async move {
+            let total = selected.len();
+            let copied = gio::spawn_blocking(move || {
+                let mut profiles = Vec::new();
+                let mut failed = Vec::new();
+                for (index, candidate) in selected.into_iter().enumerate() {
+                    info!("Importing profile {} from {}", candidate.profile.name, candidate.source);
+                    let mut profile = candidate.profile;
+
+                    if let Some(instances_dir) = &copy_to {
+                        let _ = sender.send(format!("Copying {} of {}…", index + 1, total));
+                        if let Err(e) = importers::copy_instance_game_directory(&mut profile, instances_dir) {
+                            error!("Failed to copy game directory for {}: {}", profile.name, e);
+                            failed.push(profile.name);
+                            continue;
+                        }
+                    }
+
+                    profiles.push(profile);
+                }
+                (profiles, failed)
+            }).await;
+
+            let (profiles, failed) = match copied {
+                Ok(copied) => copied,
+                Err(_) => {
+                    let toast = adw::Toast::new("Failed to import the profiles");
+                    toast_overlay.add_toast(toast);
+                    dialog.close();
+                    return;
+                }
+            };
+
+            if !failed.is_empty() {
+                let toast = adw::Toast::new(&format!("Failed to copy the game directory of: {}", failed.join(", ")));
+                toast_overlay.add_toast(toast);
+            }
+
+            // Apply the copied profiles now that the copy is done
+            let mut config_mut = config.borrow_mut();
+            let selected_game_id = config_mut.selected_game.clone().unwrap_or_else(|| "minecraft".to_string());
+            let game_index = match config_mut.games.iter().position(|g| g.id == selected_game_id) {
+                Some(index) => index,
+                None => {
+                    dialog.close();
+                    return;
+                }
+            };
+
+            let imported = profiles.len();
+            config_mut.games[game_index].profiles.extend(profiles);
+
+            // Save the config
+            if let Err(e) = save_config(&config_mut) {
+                let toast = adw::Toast::new(&format!("Failed to save imported profiles: {}", e));
+                toast_overlay.add_toast(toast);
+            } else {
+                let toast = adw::Toast::new(&format!("Imported {} profile(s)", imported));
+                toast_overlay.add_toast(toast);
+            }
+
+            // Refresh the profiles list
+            while let Some(child) = list_box.first_child() {
+                list_box.remove(&child);
+            }
+
+            // Get the game's profiles
+            let game = &config_mut.games[game_index];
+            for profile in &game.profiles {
+                let row = adw::ActionRow::new();
+                row.set_title(&profile.name);
+                row.set_subtitle(&format!("Minecraft {}", profile.version));
+
+                // Add an edit button
+                let edit_button = gtk::Button::new();
+                edit_button.set_icon_name("document-edit-symbolic");
+                edit_button.set_tooltip_text(Some("Edit profile"));
+                edit_button.add_css_class("flat");
+                row.add_suffix(&edit_button);
+
+                // Add a delete button
+                let delete_button = gtk::Button::new();
+                delete_button.set_icon_name("user-trash-symbolic");
+                delete_button.set_tooltip_text(Some("Delete profile"));
+                delete_button.add_css_class("flat");
+                row.add_suffix(&delete_button);
+
+                list_box.append(&row);
+            }
+
+            dialog.close();
+        });
+    });
+
+    dialog.present();
+}
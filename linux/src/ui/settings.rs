@@ -1,6 +1,6 @@
 use gtk4 as gtk;
 use gtk::prelude::*;
-use gtk::glib;
+use gtk::{gio, glib};
 use gtk::glib::clone;
 use libadwaita as adw;
 use adw::prelude::*;
@@ -9,7 +9,12 @@ use std::cell::RefCell;
 use std::path::PathBuf;
 use log::{info, warn, error, debug};
 
-use crate::config::{Config, save_config};
+use crate::config::{Config, LaunchSettings, ProxyKind, ProxyMode, ProxySettings, save_config};
+use crate::file_manager::{FileManager, set_bandwidth_limit};
+use crate::games::minecraft::{CleanupPlan, MinecraftManager};
+use crate::network::{check_proxy_settings, test_connection};
+use crate::utils::format_file_size;
+use super::launch_settings::build_launch_override_rows;
 
 pub fn build_settings_view(
     window: &adw::ApplicationWindow,
@@ -119,47 +124,81 @@ pub fn build_settings_view(
         });
     });
 
-    // Add a preferences group for advanced settings
-    let advanced_group = adw::PreferencesGroup::new();
-    advanced_group.set_title("Advanced");
-    content.append(&advanced_group);
-
-    // Add a row for Java arguments
-    let java_args_row = adw::ActionRow::new();
-    java_args_row.set_title("Java Arguments");
+    // Add the global launch settings, the base layer for every game and profile
+    let launch_group = adw::PreferencesGroup::new();
+    launch_group.set_title("Launch Settings");
+    let launch_description = "Used by every game unless the game or profile overrides them";
+    launch_group.set_description(Some(launch_description));
+    content.append(&launch_group);
 
-    let java_args_entry = gtk::Entry::new();
-    let args = &config.borrow().java_arguments;
-    if !args.is_empty() {
-        java_args_entry.set_text(&args.join(" "));
-    }
-    java_args_entry.set_placeholder_text(Some("Default"));
-    java_args_entry.set_hexpand(true);
-    java_args_row.add_suffix(&java_args_entry);
-
-    advanced_group.add(&java_args_row);
+    let global_launch = config.borrow().launch.clone();
+    let launch_rows = build_launch_override_rows(&launch_group, "Set Memory", &LaunchSettings::default(), &global_launch);
 
-    // Connect the Java arguments entry
     let config_clone = config.clone();
     let toast_overlay_clone = toast_overlay.clone();
+    let launch_group_clone = launch_group.clone();
+    let launch_rows_clone = launch_rows.clone();
+    launch_rows.connect_changed(move || {
+        let launch = match launch_rows_clone.read() {
+            Ok(launch) => launch,
+            Err(e) => {
+                launch_group_clone.set_description(Some(&e.to_string()));
+                return;
+            }
+        };
+        launch_group_clone.set_description(Some(launch_description));
 
-    java_args_entry.connect_changed(move |entry| {
-        let text = entry.text().to_string();
         let mut config_mut = config_clone.borrow_mut();
-
-        if text.is_empty() {
-            config_mut.java_arguments = Vec::new();
-        } else {
-            config_mut.java_arguments = text.split_whitespace().map(|s| s.to_string()).collect();
-        }
-
-        // Save the config
+        config_mut.launch = launch;
         if let Err(e) = save_config(&config_mut) {
             let toast = adw::Toast::new(&format!("Failed to save config: {}", e));
             toast_overlay_clone.add_toast(toast);
         }
     });
 
+    // Add the launch settings of the selected game, layered on top of the global ones
+    let game_launch = config_ref.games.iter()
+        .find(|g| g.id == selected_game_id)
+        .map(|g| (g.name.clone(), g.launch.clone()));
+    if let Some((game_name, game_launch)) = game_launch {
+        let game_launch_group = adw::PreferencesGroup::new();
+        game_launch_group.set_title(&format!("{} Launch Settings", game_name));
+        let game_launch_description = "Leave a field empty to use the global setting";
+        game_launch_group.set_description(Some(game_launch_description));
+        content.append(&game_launch_group);
+
+        let game_launch_rows = build_launch_override_rows(&game_launch_group, "Override Memory", &global_launch, &game_launch);
+
+        let config_clone = config.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        let game_launch_rows_clone = game_launch_rows.clone();
+        let selected_game_id = selected_game_id.clone();
+        game_launch_rows.connect_changed(move || {
+            let launch = match game_launch_rows_clone.read() {
+                Ok(launch) => launch,
+                Err(e) => {
+                    game_launch_group.set_description(Some(&e.to_string()));
+                    return;
+                }
+            };
+            game_launch_group.set_description(Some(game_launch_description));
+
+            let mut config_mut = config_clone.borrow_mut();
+            if let Some(game) = config_mut.games.iter_mut().find(|g| g.id == selected_game_id) {
+                game.launch = launch;
+            }
+            if let Err(e) = save_config(&config_mut) {
+                let toast = adw::Toast::new(&format!("Failed to save config: {}", e));
+                toast_overlay_clone.add_toast(toast);
+            }
+        });
+    }
+
+    // Add a preferences group for advanced settings
+    let advanced_group = adw::PreferencesGroup::new();
+    advanced_group.set_title("Advanced");
+    content.append(&advanced_group);
+
     // Add a row for the sandbox mode
     let sandbox_row = adw::ActionRow::new();
     sandbox_row.set_title("Disable Sandbox");
@@ -195,6 +234,388 @@ pub fn build_settings_view(
         }
     });
 
+    // Add a preferences group for download settings
+    let downloads_group = adw::PreferencesGroup::new();
+    downloads_group.set_title("Downloads");
+    content.append(&downloads_group);
+
+    // Add a row for the number of parallel downloads
+    let parallel_row = adw::ActionRow::new();
+    parallel_row.set_title("Parallel Downloads");
+    parallel_row.set_subtitle("How many files are downloaded at the same time");
+
+    let parallel_spin = gtk::SpinButton::with_range(1.0, 64.0, 1.0);
+    parallel_spin.set_valign(gtk::Align::Center);
+    parallel_spin.set_value(config.borrow().downloads.max_concurrent_downloads as f64);
+    parallel_row.add_suffix(&parallel_spin);
+
+    downloads_group.add(&parallel_row);
+
+    // Add a row for the connections per host
+    let per_host_row = adw::ActionRow::new();
+    per_host_row.set_title("Connections per Server");
+    per_host_row.set_subtitle("How many of those downloads may come from the same server");
+
+    let per_host_spin = gtk::SpinButton::with_range(1.0, 32.0, 1.0);
+    per_host_spin.set_valign(gtk::Align::Center);
+    per_host_spin.set_value(config.borrow().downloads.max_connections_per_host as f64);
+    per_host_row.add_suffix(&per_host_spin);
+
+    downloads_group.add(&per_host_row);
+
+    // Add a row for the download speed limit
+    let speed_limit_row = adw::ActionRow::new();
+    speed_limit_row.set_title("Speed Limit");
+    speed_limit_row.set_subtitle("Combined download speed in KB/s, 0 for unlimited");
+
+    let speed_limit_spin = gtk::SpinButton::with_range(0.0, 1_000_000.0, 100.0);
+    speed_limit_spin.set_valign(gtk::Align::Center);
+    speed_limit_spin.set_value(config.borrow().downloads.max_bytes_per_second.map_or(0, |limit| limit / 1024) as f64);
+    speed_limit_row.add_suffix(&speed_limit_spin);
+
+    downloads_group.add(&speed_limit_row);
+
+    // Connect the download limit spin buttons
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+
+    parallel_spin.connect_value_changed(move |spin| {
+        let mut config_mut = config_clone.borrow_mut();
+        config_mut.downloads.max_concurrent_downloads = spin.value_as_int() as usize;
+
+        // Save the config
+        if let Err(e) = save_config(&config_mut) {
+            let toast = adw::Toast::new(&format!("Failed to save config: {}", e));
+            toast_overlay_clone.add_toast(toast);
+        }
+    });
+
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+
+    per_host_spin.connect_value_changed(move |spin| {
+        let mut config_mut = config_clone.borrow_mut();
+        config_mut.downloads.max_connections_per_host = spin.value_as_int() as usize;
+
+        // Save the config
+        if let Err(e) = save_config(&config_mut) {
+            let toast = adw::Toast::new(&format!("Failed to save config: {}", e));
+            toast_overlay_clone.add_toast(toast);
+        }
+    });
+
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+
+    speed_limit_spin.connect_value_changed(move |spin| {
+        let limit = Some(spin.value_as_int() as u64 * 1024).filter(|limit| *limit > 0);
+        let mut config_mut = config_clone.borrow_mut();
+        config_mut.downloads.max_bytes_per_second = limit;
+
+        // The limit is shared by every download, so running installs slow down right away
+        set_bandwidth_limit(limit);
+
+        // Save the config
+        if let Err(e) = save_config(&config_mut) {
+            let toast = adw::Toast::new(&format!("Failed to save config: {}", e));
+            toast_overlay_clone.add_toast(toast);
+        }
+    });
+
+    // Add a preferences group for network settings
+    let network_group = adw::PreferencesGroup::new();
+    network_group.set_title("Network");
+    content.append(&network_group);
+
+    let proxy = config.borrow().proxy.clone();
+    show_proxy_status(&network_group, &proxy);
+
+    // Add a row for the proxy mode
+    let proxy_mode_row = adw::ActionRow::new();
+    proxy_mode_row.set_title("Proxy");
+    proxy_mode_row.set_subtitle("Used for downloads, mod searches and sign-in");
+
+    let proxy_mode_dropdown = gtk::DropDown::from_strings(&["System", "None", "Manual"]);
+    proxy_mode_dropdown.set_valign(gtk::Align::Center);
+    proxy_mode_dropdown.set_selected(match proxy.mode {
+        ProxyMode::System => 0,
+        ProxyMode::None => 1,
+        ProxyMode::Manual => 2,
+    });
+    proxy_mode_row.add_suffix(&proxy_mode_dropdown);
+
+    network_group.add(&proxy_mode_row);
+
+    // Add a row for the proxy type
+    let proxy_kind_row = adw::ActionRow::new();
+    proxy_kind_row.set_title("Proxy Type");
+
+    let proxy_kind_dropdown = gtk::DropDown::from_strings(&["HTTP", "HTTPS", "SOCKS5"]);
+    proxy_kind_dropdown.set_valign(gtk::Align::Center);
+    proxy_kind_dropdown.set_selected(match proxy.kind {
+        ProxyKind::Http => 0,
+        ProxyKind::Https => 1,
+        ProxyKind::Socks5 => 2,
+    });
+    proxy_kind_row.add_suffix(&proxy_kind_dropdown);
+
+    network_group.add(&proxy_kind_row);
+
+    // Add rows for the proxy address
+    let proxy_host_row = adw::ActionRow::new();
+    proxy_host_row.set_title("Host");
+
+    let proxy_host_entry = gtk::Entry::new();
+    proxy_host_entry.set_text(&proxy.host);
+    proxy_host_entry.set_placeholder_text(Some("proxy.example.com"));
+    proxy_host_entry.set_valign(gtk::Align::Center);
+    proxy_host_entry.set_hexpand(true);
+    proxy_host_row.add_suffix(&proxy_host_entry);
+
+    network_group.add(&proxy_host_row);
+
+    let proxy_port_row = adw::ActionRow::new();
+    proxy_port_row.set_title("Port");
+
+    let proxy_port_spin = gtk::SpinButton::with_range(0.0, 65535.0, 1.0);
+    proxy_port_spin.set_valign(gtk::Align::Center);
+    proxy_port_spin.set_value(proxy.port as f64);
+    proxy_port_row.add_suffix(&proxy_port_spin);
+
+    network_group.add(&proxy_port_row);
+
+    // Add rows for the proxy credentials
+    let proxy_username_row = adw::ActionRow::new();
+    proxy_username_row.set_title("Username");
+    proxy_username_row.set_subtitle("Leave empty if the proxy doesn't require a login");
+
+    let proxy_username_entry = gtk::Entry::new();
+    proxy_username_entry.set_text(proxy.username.as_deref().unwrap_or(""));
+    proxy_username_entry.set_valign(gtk::Align::Center);
+    proxy_username_entry.set_hexpand(true);
+    proxy_username_row.add_suffix(&proxy_username_entry);
+
+    network_group.add(&proxy_username_row);
+
+    let proxy_password_row = adw::ActionRow::new();
+    proxy_password_row.set_title("Password");
+    proxy_password_row.set_subtitle("Stored unencrypted in the config file");
+
+    let proxy_password_entry = gtk::PasswordEntry::new();
+    proxy_password_entry.set_show_peek_icon(true);
+    proxy_password_entry.set_text(proxy.password.as_deref().unwrap_or(""));
+    proxy_password_entry.set_valign(gtk::Align::Center);
+    proxy_password_entry.set_hexpand(true);
+    proxy_password_row.add_suffix(&proxy_password_entry);
+
+    network_group.add(&proxy_password_row);
+
+    // Add a row for the hosts that bypass the proxy
+    let no_proxy_row = adw::ActionRow::new();
+    no_proxy_row.set_title("Bypass Proxy For");
+    no_proxy_row.set_subtitle("Comma-separated hosts or domains, e.g. localhost, .example.com");
+
+    let no_proxy_entry = gtk::Entry::new();
+    no_proxy_entry.set_text(&proxy.no_proxy.join(", "));
+    no_proxy_entry.set_valign(gtk::Align::Center);
+    no_proxy_entry.set_hexpand(true);
+    no_proxy_row.add_suffix(&no_proxy_entry);
+
+    network_group.add(&no_proxy_row);
+
+    // The manual settings only apply in manual mode
+    let manual_rows = [
+        proxy_kind_row.clone(),
+        proxy_host_row.clone(),
+        proxy_port_row.clone(),
+        proxy_username_row.clone(),
+        proxy_password_row.clone(),
+        no_proxy_row.clone(),
+    ];
+    for row in &manual_rows {
+        row.set_sensitive(proxy.mode == ProxyMode::Manual);
+    }
+
+    // Connect the proxy settings
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    let network_group_clone = network_group.clone();
+
+    proxy_mode_dropdown.connect_selected_notify(move |dropdown| {
+        let mode = match dropdown.selected() {
+            1 => ProxyMode::None,
+            2 => ProxyMode::Manual,
+            _ => ProxyMode::System,
+        };
+        for row in &manual_rows {
+            row.set_sensitive(mode == ProxyMode::Manual);
+        }
+        update_proxy_settings(&config_clone, &toast_overlay_clone, &network_group_clone, |proxy| proxy.mode = mode);
+    });
+
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    let network_group_clone = network_group.clone();
+
+    proxy_kind_dropdown.connect_selected_notify(move |dropdown| {
+        let kind = match dropdown.selected() {
+            1 => ProxyKind::Https,
+            2 => ProxyKind::Socks5,
+            _ => ProxyKind::Http,
+        };
+        update_proxy_settings(&config_clone, &toast_overlay_clone, &network_group_clone, |proxy| proxy.kind = kind);
+    });
+
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    let network_group_clone = network_group.clone();
+
+    proxy_host_entry.connect_changed(move |entry| {
+        let host = entry.text().trim().to_string();
+        update_proxy_settings(&config_clone, &toast_overlay_clone, &network_group_clone, |proxy| proxy.host = host);
+    });
+
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    let network_group_clone = network_group.clone();
+
+    proxy_port_spin.connect_value_changed(move |spin| {
+        let port = spin.value_as_int() as u16;
+        update_proxy_settings(&config_clone, &toast_overlay_clone, &network_group_clone, |proxy| proxy.port = port);
+    });
+
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    let network_group_clone = network_group.clone();
+
+    proxy_username_entry.connect_changed(move |entry| {
+        let username = Some(entry.text().to_string()).filter(|username| !username.is_empty());
+        update_proxy_settings(&config_clone, &toast_overlay_clone, &network_group_clone, |proxy| proxy.username = username);
+    });
+
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    let network_group_clone = network_group.clone();
+
+    proxy_password_entry.connect_changed(move |entry| {
+        let password = Some(entry.text().to_string()).filter(|password| !password.is_empty());
+        update_proxy_settings(&config_clone, &toast_overlay_clone, &network_group_clone, |proxy| proxy.password = password);
+    });
+
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    let network_group_clone = network_group.clone();
+
+    no_proxy_entry.connect_changed(move |entry| {
+        let no_proxy = entry.text().split(',')
+            .map(|host| host.trim().to_string())
+            .filter(|host| !host.is_empty())
+            .collect();
+        update_proxy_settings(&config_clone, &toast_overlay_clone, &network_group_clone, |proxy| proxy.no_proxy = no_proxy);
+    });
+
+    // Add a row to test the proxy settings
+    let test_connection_row = adw::ActionRow::new();
+    test_connection_row.set_title("Test Connection");
+    test_connection_row.set_subtitle("Check that the Minecraft servers can be reached with these settings");
+
+    let test_connection_button = gtk::Button::with_label("Test");
+    test_connection_button.set_valign(gtk::Align::Center);
+    test_connection_row.add_suffix(&test_connection_button);
+
+    network_group.add(&test_connection_row);
+
+    // Connect the test button, the request runs off the main thread
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+
+    test_connection_button.connect_clicked(move |button| {
+        let proxy = config_clone.borrow().proxy.clone();
+        let button = button.clone();
+        let toast_overlay = toast_overlay_clone.clone();
+
+        button.set_sensitive(false);
+        button.set_label("Testing...");
+
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || test_connection(&proxy)).await;
+
+            let message = match result {
+                Ok(Ok(elapsed)) => format!("Connected in {} ms", elapsed.as_millis()),
+                Ok(Err(e)) => {
+                    warn!("Connection test failed: {}", e);
+                    format!("Connection failed: {}", e)
+                },
+                Err(_) => "Connection test failed".to_string(),
+            };
+            toast_overlay.add_toast(adw::Toast::new(&message));
+
+            button.set_label("Test");
+            button.set_sensitive(true);
+        });
+    });
+
+    // Add a preferences group for storage
+    let storage_group = adw::PreferencesGroup::new();
+    storage_group.set_title("Storage");
+    content.append(&storage_group);
+
+    // Add a row to remove what no profile uses anymore
+    let cleanup_row = adw::ActionRow::new();
+    cleanup_row.set_title("Unused Files");
+    cleanup_row.set_subtitle("Versions, libraries and assets no profile uses anymore");
+
+    let cleanup_button = gtk::Button::with_label("Clean Up");
+    cleanup_button.set_valign(gtk::Align::Center);
+    cleanup_row.add_suffix(&cleanup_button);
+
+    storage_group.add(&cleanup_row);
+
+    // Connect the clean up button, nothing is removed until the dialog is confirmed
+    let config_clone = config.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    let window_clone = window.clone();
+
+    cleanup_button.connect_clicked(move |button| {
+        let config_snapshot = config_clone.borrow().clone();
+        let button = button.clone();
+        let toast_overlay = toast_overlay_clone.clone();
+        let window = window_clone.clone();
+
+        button.set_sensitive(false);
+        button.set_label("Scanning...");
+
+        glib::spawn_future_local(async move {
+            let scan_config = config_snapshot.clone();
+            let result = gio::spawn_blocking(move || {
+                MinecraftManager::new(scan_config, Rc::new(FileManager::new())).find_unused_files()
+            }).await;
+
+            button.set_label("Clean Up");
+            button.set_sensitive(true);
+
+            let plan = match result {
+                Ok(Ok(plan)) => plan,
+                Ok(Err(e)) => {
+                    error!("Failed to look for unused files: {}", e);
+                    toast_overlay.add_toast(adw::Toast::new(&format!("Failed to look for unused files: {}", e)));
+                    return;
+                },
+                Err(_) => {
+                    toast_overlay.add_toast(adw::Toast::new("Failed to look for unused files"));
+                    return;
+                },
+            };
+
+            if plan.is_empty() {
+                toast_overlay.add_toast(adw::Toast::new("No unused files"));
+                return;
+            }
+
+            show_cleanup_dialog(&window, &toast_overlay, config_snapshot, &plan);
+        });
+    });
+
     // Add a preferences group for about
     let about_group = adw::PreferencesGroup::new();
     about_group.set_title("About");
@@ -234,3 +655,91 @@ pub fn build_settings_view(
 
     settings_box
 }
+
+/// Changes the proxy settings and saves the config
+fn update_proxy_settings(
+    config: &Rc<RefCell<Config>>,
+    toast_overlay: &adw::ToastOverlay,
+    network_group: &adw::PreferencesGroup,
+    change: impl FnOnce(&mut ProxySettings),
+) {
+    let mut config_mut = config.borrow_mut();
+    change(&mut config_mut.proxy);
+    show_proxy_status(network_group, &config_mut.proxy);
+
+    // Save the config
+    if let Err(e) = save_config(&config_mut) {
+        let toast = adw::Toast::new(&format!("Failed to save config: {}", e));
+        toast_overlay.add_toast(toast);
+    }
+}
+
+/// Shows on the network group whether the proxy settings can be used
+///
+/// Invalid settings never fall back to a direct connection, so until they are
+/// fixed downloads, mod searches and sign-in fail.
+fn show_proxy_status(network_group: &adw::PreferencesGroup, proxy: &ProxySettings) {
+    match check_proxy_settings(proxy) {
+        Ok(()) => network_group.set_description(None),
+        Err(e) => network_group.set_description(Some(&format!(
+            "The proxy settings are invalid, nothing connects until they are fixed: {}", e
+        ))),
+    }
+}
+
+/// Asks before removing the files of a cleanup plan
+///
+/// The files are looked up again on removal, the plan only fills in the dialog.
+fn show_cleanup_dialog(
+    window: &adw::ApplicationWindow,
+    toast_overlay: &adw::ToastOverlay,
+    config_snapshot: Config,
+    plan: &CleanupPlan,
+) {
+    let dialog = gtk::MessageDialog::new(
+        Some(window),
+        gtk::DialogFlags::MODAL,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::None,
+        &format!(
+            "{} versions, {} libraries and {} assets are no longer used by any profile. Removing them frees {}.",
+            plan.versions.len(), plan.libraries.len(), plan.assets.len(), format_file_size(plan.total_size())
+        ),
+    );
+    dialog.set_title(Some("Remove Unused Files"));
+
+    dialog.add_button("Cancel", gtk::ResponseType::Cancel);
+    dialog.add_button("Remove", gtk::ResponseType::Accept);
+    dialog.set_default_response(gtk::ResponseType::Cancel);
+
+    let toast_overlay = toast_overlay.clone();
+    let config_snapshot = RefCell::new(Some(config_snapshot));
+
+    dialog.connect_response(move |dialog, response| {
+        dialog.destroy();
+
+        let config_snapshot = match config_snapshot.borrow_mut().take() {
+            Some(config_snapshot) if response == gtk::ResponseType::Accept => config_snapshot,
+            _ => return,
+        };
+        let toast_overlay = toast_overlay.clone();
+
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || {
+                MinecraftManager::new(config_snapshot, Rc::new(FileManager::new())).remove_unused_files()
+            }).await;
+
+            let message = match result {
+                Ok(Ok(freed)) => format!("Freed {}", format_file_size(freed)),
+                Ok(Err(e)) => {
+                    error!("Failed to remove unused files: {}", e);
+                    format!("Failed to remove unused files: {}", e)
+                },
+                Err(_) => "Failed to remove unused files".to_string(),
+            };
+            toast_overlay.add_toast(adw::Toast::new(&message));
+        });
+    });
+
+    dialog.present();
+}
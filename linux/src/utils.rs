@@ -1,6 +1,7 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::path::Path;
 use std::fs;
+use std::io::Write;
 use log::{info, warn, error, debug};
 
 /// Ensures that a directory exists, creating it if necessary
@@ -13,6 +14,162 @@ pub fn ensure_dir_exists<P: AsRef<Path>>(path: P) -> Result<()> {
     Ok(())
 }
 
+/// Writes a file atomically: the contents go to a temporary sibling file that is
+/// fsynced and then renamed over the target, so readers never see a partial file
+pub fn write_file_atomically<P: AsRef<Path>>(path: P, contents: &[u8]) -> Result<()> {
+    write_file_atomically_with_mode(path, contents, 0o666)
+}
+
+/// Writes a file atomically like `write_file_atomically`, creating it with `mode` on Unix
+///
+/// The mode applies before any contents are written, so e.g. `0o600` keeps
+/// secrets private from the start. The umask still applies.
+pub fn write_file_atomically_with_mode<P: AsRef<Path>>(path: P, contents: &[u8], mode: u32) -> Result<()> {
+    let path = path.as_ref();
+    let file_name = get_file_name(path)
+        .ok_or_else(|| anyhow::anyhow!("Invalid file path: {:?}", path))?;
+    let tmp_path = path.with_file_name(format!("{}.tmp", file_name));
+
+    // A temporary file left by a crash would keep its old mode
+    match fs::remove_file(&tmp_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {},
+    }
+
+    {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(mode);
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        let mut file = options.open(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+
+    fs::rename(&tmp_path, path)?;
+
+    // Sync the parent directory so the rename itself survives a crash
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = fs::File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    debug!("Atomically wrote {:?}", path);
+    Ok(())
+}
+
+/// Recursively copies a directory, following neither symlinks nor special files
+///
+/// A target directory created by the copy is removed again when the copy fails.
+pub fn copy_dir_all<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<u64> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    let created = !to.exists();
+
+    copy_dir_contents(from, to).inspect_err(|_| {
+        if created {
+            if let Err(e) = fs::remove_dir_all(to) {
+                warn!("Failed to remove the partial copy {:?}: {}", to, e);
+            }
+        }
+    })
+}
+
+fn copy_dir_contents(from: &Path, to: &Path) -> Result<u64> {
+    fs::create_dir_all(to)?;
+
+    let mut copied = 0;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = to.join(entry.file_name());
+
+        if file_type.is_dir() {
+            copied += copy_dir_contents(&entry.path(), &target)?;
+        } else if file_type.is_file() {
+            copied += fs::copy(entry.path(), &target)?;
+        } else {
+            debug!("Skipping {:?} while copying", entry.path());
+        }
+    }
+
+    Ok(copied)
+}
+
+/// Splits a command line into words the way a POSIX shell would
+///
+/// Handles single and double quotes and backslash escapes, but no expansions.
+pub fn split_shell_words(text: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+                continue;
+            },
+            '\\' => word.push(chars.next().ok_or_else(|| anyhow!("Trailing backslash in: {}", text))?),
+            '\'' => loop {
+                match chars.next() {
+                    Some('\'') => break,
+                    Some(c) => word.push(c),
+                    None => return Err(anyhow!("Unterminated single quote in: {}", text)),
+                }
+            },
+            '"' => loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                        Some(c) => {
+                            word.push('\\');
+                            word.push(c);
+                        },
+                        None => return Err(anyhow!("Unterminated double quote in: {}", text)),
+                    },
+                    Some(c) => word.push(c),
+                    None => return Err(anyhow!("Unterminated double quote in: {}", text)),
+                }
+            },
+            c => word.push(c),
+        }
+        in_word = true;
+    }
+
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Joins words into a command line that `split_shell_words` splits back into the same words
+pub fn join_shell_words<S: AsRef<str>>(words: &[S]) -> String {
+    words.iter()
+        .map(|word| {
+            let word = word.as_ref();
+            if !word.is_empty() && !word.chars().any(|c| c.is_whitespace() || "'\"\\$`".contains(c)) {
+                word.to_string()
+            } else {
+                format!("'{}'", word.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /// Formats a file size in bytes to a human-readable string
 pub fn format_file_size(size: u64) -> String {
     const KB: u64 = 1024;
@@ -145,4 +302,30 @@ pub fn to_title_case(s: &str) -> String {
     }
     
     result
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_quoted_words() {
+        let words = split_shell_words(r#"-Xss2M  -Dname="My World" 'a\b' c\ d MESA_GL_VERSION_OVERRIDE=4.5"#).unwrap();
+        assert_eq!(words, ["-Xss2M", "-Dname=My World", r"a\b", "c d", "MESA_GL_VERSION_OVERRIDE=4.5"]);
+        assert_eq!(split_shell_words(r#"A="" B="\"x\"""#).unwrap(), ["A=", r#"B="x""#]);
+        assert!(split_shell_words("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_unterminated_quotes() {
+        assert!(split_shell_words("A='b").is_err());
+        assert!(split_shell_words(r#"A="b"#).is_err());
+        assert!(split_shell_words(r"A=b\").is_err());
+    }
+
+    #[test]
+    fn joined_words_split_back() {
+        let words = ["-Dname=My World", "", "it's", r#"say "hi""#, r"C:\Games", "plain"];
+        assert_eq!(split_shell_words(&join_shell_words(&words)).unwrap(), words);
+    }
+}